@@ -0,0 +1,191 @@
+use std::hash::{Hash, Hasher};
+
+use image::{DynamicImage, RgbImage};
+
+use crate::primitives::WhiteBalance;
+
+/// Tone changes applied to every frame before it gets converted, for sources
+/// that are too dark or too flat to use the whole charset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjustments {
+    /// Values above 1 brighten the midtones, values below darken them
+    pub gamma: f32,
+    /// Added to every channel, from -255 to 255
+    pub brightness: i16,
+    /// Stretches channels away from the midpoint when above 1
+    pub contrast: f32,
+    /// 0 is grayscale, 1 leaves colors alone, above 1 makes them stronger
+    pub saturation: f32,
+    /// Turns every color around the color wheel, in degrees
+    pub hue: f32,
+    /// Shifts colors towards orange when positive, towards blue when
+    /// negative, from -100 to 100
+    pub temperature: i16,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            brightness: 0,
+            contrast: 1.0,
+            saturation: 1.0,
+            hue: 0.0,
+            temperature: 0,
+        }
+    }
+}
+
+// Floats aren't `Hash`, but their bits are good enough for telling settings
+// apart in the cache
+impl Hash for Adjustments {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.gamma.to_bits().hash(state);
+        self.brightness.hash(state);
+        self.contrast.to_bits().hash(state);
+        self.saturation.to_bits().hash(state);
+        self.hue.to_bits().hash(state);
+        self.temperature.hash(state);
+    }
+}
+
+impl Adjustments {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Copy of `image` with the adjustments applied. Tones go through a
+    /// lookup table since every channel follows the same curve, then colors
+    /// get graded as a whole.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        let table = self.table();
+        let mut adjusted: RgbImage = image.to_rgb8();
+        for channel in adjusted.iter_mut() {
+            *channel = table[usize::from(*channel)];
+        }
+
+        // Only the exact defaults leave colors alone, so those skip the work
+        #[allow(clippy::float_cmp)]
+        let grades = self.saturation != 1.0 || self.hue != 0.0 || self.temperature != 0;
+        if grades {
+            let matrix = self.color_matrix();
+            // Up to a quarter of the range, on red and blue in opposite ways
+            let warmth = f32::from(self.temperature) * 0.64;
+            let shift = [warmth, 0.0, -warmth];
+            for pixel in adjusted.pixels_mut() {
+                let color = pixel.0.map(f32::from);
+                for (channel, (row, shift)) in pixel.0.iter_mut().zip(matrix.iter().zip(shift)) {
+                    let value = row.iter().zip(color).map(|(m, c)| m * c).sum::<f32>() + shift;
+                    *channel = value.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+        DynamicImage::ImageRgb8(adjusted)
+    }
+
+    /// Hue rotation after saturation, both around the gray axis so that
+    /// neither changes how bright colors look
+    fn color_matrix(&self) -> [[f32; 3]; 3] {
+        const LUMA: [f32; 3] = [0.213, 0.715, 0.072];
+        let s = self.saturation;
+        let saturate: [[f32; 3]; 3] = [0, 1, 2].map(|row| {
+            [0, 1, 2].map(|column| {
+                let gray = LUMA[column] * (1.0 - s);
+                if row == column {
+                    gray + s
+                } else {
+                    gray
+                }
+            })
+        });
+
+        let (sin, cos) = self.hue.to_radians().sin_cos();
+        let rotate = [
+            [
+                0.213 + cos * 0.787 - sin * 0.213,
+                0.715 - cos * 0.715 - sin * 0.715,
+                0.072 - cos * 0.072 + sin * 0.928,
+            ],
+            [
+                0.213 - cos * 0.213 + sin * 0.143,
+                0.715 + cos * 0.285 + sin * 0.140,
+                0.072 - cos * 0.072 - sin * 0.283,
+            ],
+            [
+                0.213 - cos * 0.213 - sin * 0.787,
+                0.715 - cos * 0.715 + sin * 0.715,
+                0.072 + cos * 0.928 + sin * 0.072,
+            ],
+        ];
+
+        [0, 1, 2].map(|row| {
+            [0, 1, 2].map(|column| (0..3).map(|k| rotate[row][k] * saturate[k][column]).sum())
+        })
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn table(&self) -> [u8; 256] {
+        let mut table = [0; 256];
+        for (value, entry) in (0u8..=255).zip(table.iter_mut()) {
+            let mut value = f32::from(value) / 255.0;
+            value = (value - 0.5) * self.contrast + 0.5;
+            value += f32::from(self.brightness) / 255.0;
+            value = value.clamp(0.0, 1.0).powf(1.0 / self.gamma);
+            *entry = (value * 255.0).round() as u8;
+        }
+        table
+    }
+}
+
+/// Scales each channel so that the frame's color cast goes away
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn white_balance(image: &DynamicImage, mode: WhiteBalance) -> DynamicImage {
+    let mut balanced = image.to_rgb8();
+
+    let mut histograms = [[0u32; 256]; 3];
+    for pixel in balanced.pixels() {
+        for (histogram, &channel) in histograms.iter_mut().zip(&pixel.0) {
+            histogram[usize::from(channel)] += 1;
+        }
+    }
+
+    let pixels = u64::from(balanced.width()) * u64::from(balanced.height());
+    let references = histograms.map(|histogram| match mode {
+        WhiteBalance::GrayWorld => {
+            let sum = (0..256u64)
+                .zip(histogram)
+                .map(|(value, count)| value * u64::from(count))
+                .sum::<u64>();
+            sum as f32 / pixels.max(1) as f32
+        }
+        // The brightest 1% is skipped, so that a few blown out pixels don't
+        // decide the white point
+        WhiteBalance::WhitePatch => {
+            let mut remaining = pixels / 100;
+            let mut value = 255;
+            while value > 0 && remaining >= u64::from(histogram[value]) {
+                remaining -= u64::from(histogram[value]);
+                value -= 1;
+            }
+            value as f32
+        }
+    });
+
+    let target = match mode {
+        WhiteBalance::GrayWorld => references.iter().sum::<f32>() / 3.0,
+        WhiteBalance::WhitePatch => 255.0,
+    };
+    let gains = references.map(|reference| target / reference.max(1.0));
+
+    for pixel in balanced.pixels_mut() {
+        for (channel, gain) in pixel.0.iter_mut().zip(gains) {
+            *channel = (f32::from(*channel) * gain).round().min(255.0) as u8;
+        }
+    }
+    DynamicImage::ImageRgb8(balanced)
+}
@@ -0,0 +1,124 @@
+use std::{fs::File, io::BufReader, path::Path, time::Duration};
+
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    error::ImageFormatHint,
+    AnimationDecoder, DynamicImage, Frames, ImageDecoder, ImageError, ImageFormat,
+};
+
+use crate::{converter::AsciiConverter, primitives::Options};
+
+/// Browsers show frames with delays shorter than this for [`SLOW_DELAY`]
+/// instead, which animations made for them have come to count on
+const MIN_DELAY: Duration = Duration::from_millis(20);
+const SLOW_DELAY: Duration = Duration::from_millis(100);
+
+/// Sets up the conversion of animated GIF, APNG and WebP images, frame by
+/// frame and without ffmpeg
+pub struct AsciiAnimationBuilder {
+    options: Options,
+}
+
+/// The frames of an animated image, converted as they're decoded. Each one
+/// comes with how long it stays on screen.
+pub struct AsciiAnimation {
+    frames: Frames<'static>,
+    converter: AsciiConverter,
+    /// Size of the animation, which every frame gets drawn over in full
+    pub dimensions: (u32, u32),
+}
+
+impl AsciiAnimationBuilder {
+    pub fn new(options: Options) -> Self {
+        Self { options }
+    }
+
+    /// Opens the animated image at `path`. Its format comes from the
+    /// extension, see [`is_animation`].
+    pub fn open(self, path: &Path) -> Result<AsciiAnimation, ImageError> {
+        let Some(format) = animation_format(path) else {
+            return Err(ImageError::Unsupported(ImageFormatHint::from(path).into()));
+        };
+        let file = BufReader::new(File::open(path)?);
+        let (dimensions, frames) = match format {
+            ImageFormat::Gif => {
+                let decoder = GifDecoder::new(file)?;
+                (decoder.dimensions(), decoder.into_frames())
+            }
+            ImageFormat::WebP => {
+                let decoder = WebPDecoder::new(file)?;
+                (decoder.dimensions(), decoder.into_frames())
+            }
+            _ => {
+                let decoder = PngDecoder::new(file)?;
+                (decoder.dimensions(), decoder.apng().into_frames())
+            }
+        };
+        Ok(AsciiAnimation {
+            frames,
+            converter: AsciiConverter::new(self.options),
+            dimensions,
+        })
+    }
+}
+
+impl Iterator for AsciiAnimation {
+    type Item = Result<(String, Duration), ImageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = match self.frames.next()? {
+            Ok(frame) => frame,
+            Err(error) => return Some(Err(error)),
+        };
+        let delay = Duration::from(frame.delay());
+        let image = DynamicImage::ImageRgba8(frame.into_buffer());
+        Some(Ok((self.converter.convert(&image).to_owned(), delay)))
+    }
+}
+
+/// A framerate that fits the shortest of `delays`, and how many times each
+/// frame has to repeat at it to stay on screen for its own delay. Frames
+/// start as close as they can to when they would in the animation.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn pace(delays: &[Duration]) -> (f64, Vec<usize>) {
+    let delays = delays
+        .iter()
+        .map(|&delay| if delay < MIN_DELAY { SLOW_DELAY } else { delay })
+        .collect::<Vec<_>>();
+    let shortest = delays.iter().min().copied().unwrap_or(SLOW_DELAY);
+    let framerate = 1.0 / shortest.as_secs_f64();
+
+    let mut elapsed = Duration::ZERO;
+    let mut shown = 0;
+    let repeats = delays
+        .iter()
+        .map(|&delay| {
+            elapsed += delay;
+            let until = (elapsed.as_secs_f64() * framerate).round() as usize;
+            let repeats = until.saturating_sub(shown).max(1);
+            shown += repeats;
+            repeats
+        })
+        .collect();
+    (framerate, repeats)
+}
+
+/// Whether `path` is an image [`AsciiAnimationBuilder`] opens, going by its
+/// extension. Only `.apng` counts for APNGs, since most PNGs are stills.
+pub fn is_animation(path: &Path) -> bool {
+    animation_format(path).is_some()
+}
+
+fn animation_format(path: &Path) -> Option<ImageFormat> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "gif" => Some(ImageFormat::Gif),
+        "webp" => Some(ImageFormat::WebP),
+        "apng" => Some(ImageFormat::Png),
+        _ => None,
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read},
+    path::Path,
+};
+
+use tar::{Archive, Builder};
+
+use crate::{
+    events::{parse_event, write_events, Event},
+    metadata::Metadata,
+    primitives::Container,
+    util::add_file,
+};
+use asciicore::container::{is_v3, read_entry, read_index, V3Writer};
+use asciicore::frame::index;
+
+/// A `.bapple` file loaded in memory. Frames are kept zstd compressed and in
+/// playback order.
+pub struct Bapple {
+    pub frames: Vec<Vec<u8>>,
+    pub audio: Option<Vec<u8>>,
+    /// Audio tracks after the first one, see [`track_name`]
+    pub extra_tracks: Vec<Vec<u8>>,
+    pub metadata: Metadata,
+    /// Sorted by frame
+    pub events: Vec<Event>,
+    /// What the archive gets written as
+    pub container: Container,
+}
+
+impl Bapple {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let (container, entries) = read_entries(path)?;
+        let mut frames = Vec::new();
+        let mut audio = None;
+        let mut tracks = Vec::new();
+        let mut metadata = Metadata::default();
+        let mut events = Vec::new();
+
+        for (name, data) in entries {
+            let stem = Path::new(&name)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default();
+
+            if let Some(number) = track_number(stem) {
+                tracks.push((number, data));
+                continue;
+            }
+            match stem {
+                "audio" => audio = Some(data),
+                // Rebuilt from the frames on write
+                "index" => (),
+                "metadata" => metadata = Metadata::parse(&String::from_utf8_lossy(&data)),
+                "events" => {
+                    events = String::from_utf8_lossy(&data)
+                        .lines()
+                        .filter_map(parse_event)
+                        .collect();
+                }
+                number => {
+                    let index = number
+                        .parse::<usize>()
+                        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+                    frames.push((index, data));
+                }
+            }
+        }
+
+        frames.sort_by_key(|frame| frame.0);
+        tracks.sort_by_key(|track| track.0);
+
+        Ok(Self {
+            frames: frames.into_iter().map(|frame| frame.1).collect(),
+            audio,
+            extra_tracks: tracks.into_iter().map(|track| track.1).collect(),
+            metadata,
+            events,
+            container,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut archive = Writer::create(path, self.container)?;
+
+        let names = (1..).map(|number| format!("{number:03}.zst"));
+        for (name, frame) in names.clone().zip(&self.frames) {
+            archive.add(&name, frame)?;
+        }
+        let index = index(names.zip(self.frames.iter().map(Vec::as_slice)));
+        archive.add("index", index.as_bytes())?;
+
+        if let Some(audio) = &self.audio {
+            archive.add("audio.mp3", audio)?;
+        }
+        for (number, track) in (2..).zip(&self.extra_tracks) {
+            archive.add(&track_name(number), track)?;
+        }
+
+        if !self.metadata.is_empty() {
+            archive.add("metadata", self.metadata.to_string().as_bytes())?;
+        }
+        if !self.events.is_empty() {
+            archive.add("events", write_events(&self.events).as_bytes())?;
+        }
+
+        archive.finish()
+    }
+}
+
+/// Entry of audio track `number`, counting from 1. The first track keeps the
+/// name it always had, so that older players still find it.
+pub fn track_name(number: usize) -> String {
+    if number == 1 {
+        "audio.mp3".to_string()
+    } else {
+        format!("audio{number}.mp3")
+    }
+}
+
+/// Which track an entry like `audio2` holds
+fn track_number(stem: &str) -> Option<usize> {
+    stem.strip_prefix("audio")?
+        .parse()
+        .ok()
+        .filter(|&number| number > 1)
+}
+
+/// An archive being written, in either container
+pub enum Writer {
+    Tar(Builder<File>),
+    V3(V3Writer),
+}
+
+impl Writer {
+    pub fn create(path: &Path, container: Container) -> io::Result<Self> {
+        Ok(match container {
+            Container::Tar => Self::Tar(Builder::new(File::create(path)?)),
+            Container::V3 => Self::V3(V3Writer::create(path)?),
+        })
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Tar(archive) => add_file(archive, name, data),
+            Self::V3(archive) => archive.add(name, data),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Tar(mut archive) => archive.finish(),
+            Self::V3(archive) => archive.finish(),
+        }
+    }
+}
+
+/// Names and data of an archive's entries
+pub type Entries = Vec<(String, Vec<u8>)>;
+
+/// Every entry of the archive at `path`, in the order they're stored
+pub fn read_entries(path: &Path) -> io::Result<(Container, Entries)> {
+    let mut file = File::open(path)?;
+    if is_v3(&mut file)? {
+        let entries = read_index(&mut file)?
+            .into_iter()
+            .map(|(name, range)| Ok((name, read_entry(&mut file, &range)?)))
+            .collect::<io::Result<_>>()?;
+        return Ok((Container::V3, entries));
+    }
+
+    let mut entries = Vec::new();
+    for entry in Archive::new(file).entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        entries.push((name, data));
+    }
+    Ok((Container::Tar, entries))
+}
+
+/// Number of frames in the archive at `path`, without decompressing them
+pub fn count_frames(path: &Path) -> io::Result<usize> {
+    let mut file = File::open(path)?;
+    let is_frame = |name: &Path| name.extension().is_some_and(|extension| extension == "zst");
+
+    if is_v3(&mut file)? {
+        let index = read_index(&mut file)?;
+        return Ok(index
+            .iter()
+            .filter(|(name, _)| is_frame(Path::new(name)))
+            .count());
+    }
+
+    let mut frames = 0;
+    for entry in Archive::new(file).entries()? {
+        if is_frame(&entry?.path()?) {
+            frames += 1;
+        }
+    }
+    Ok(frames)
+}
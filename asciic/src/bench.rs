@@ -0,0 +1,131 @@
+use std::{
+    error::Error,
+    io::Cursor,
+    time::{Duration, Instant},
+};
+
+use clap::ArgMatches;
+use image::{imageops::FilterType, DynamicImage, ImageOutputFormat, Rgb, RgbImage};
+
+use crate::{
+    color::hue,
+    error::CompilerError,
+    primitives::{Options, OutputSize, PaintStyle},
+    render_image,
+};
+use asciicore::frame::compress;
+
+/// Runs a made up clip through each step of compiling a video, one frame at
+/// a time, and prints how fast every step went. The clip is generated on the
+/// spot, so results only depend on the machine and the build.
+pub fn bench(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let count = *matches.get_one::<usize>("frames").unwrap();
+    let (width, height) = *matches.get_one::<(u32, u32)>("source").unwrap();
+    let size = *matches.get_one::<OutputSize>("frame-size").unwrap();
+    if count == 0 {
+        return Err(CompilerError::InvalidInput("--frames must be at least 1".into()).into());
+    }
+
+    let mut encoded = Vec::with_capacity(count);
+    for frame in 0..count {
+        let mut png = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(synthetic(width, height, frame, count))
+            .write_to(&mut png, ImageOutputFormat::Png)?;
+        encoded.push(png.into_inner());
+    }
+
+    let plain = Options {
+        redimension: size,
+        ..Options::default()
+    };
+    let colored = Options {
+        colorize: true,
+        style: PaintStyle::BgOnly,
+        ..plain
+    };
+
+    let mut times = [Duration::ZERO; 5];
+    let mut bytes = [0; 5];
+    let mut res = String::new();
+    for png in &encoded {
+        let image = time(&mut times[0], || image::load_from_memory(png))?;
+        bytes[0] += png.len();
+
+        let resized = time(&mut times[1], || {
+            image.resize_exact(size.0, size.1, FilterType::Nearest)
+        });
+        bytes[1] += image.as_bytes().len();
+
+        time(&mut times[2], || render_image(&resized, plain, &mut res));
+        bytes[2] += res.len();
+
+        time(&mut times[3], || render_image(&resized, colored, &mut res));
+        bytes[3] += res.len();
+
+        time(&mut times[4], || compress(res.as_bytes()))?;
+        bytes[4] += res.len();
+    }
+
+    println!(
+        ">> {count} frames of {width}x{height}, drawn at {}x{}, one frame at a time",
+        size.0, size.1
+    );
+    #[allow(clippy::cast_precision_loss)]
+    let frames = count as f64;
+    let stages = ["decode", "resize", "map", "colorize", "compress"];
+    for ((stage, time), bytes) in stages.iter().zip(times).zip(bytes) {
+        let seconds = time.as_secs_f64().max(f64::EPSILON);
+        #[allow(clippy::cast_precision_loss)]
+        let mib = bytes as f64 / seconds / 1024.0 / 1024.0;
+        println!(
+            "{stage:>9}: {:>9.1} frames/s {mib:>9.1} MiB/s {:>9.3} ms/frame",
+            frames / seconds,
+            seconds * 1000.0 / frames
+        );
+    }
+    let total = times.iter().sum::<Duration>().as_secs_f64();
+    let fps = frames / total.max(f64::EPSILON);
+    println!("    total: {fps:>9.1} frames/s");
+    Ok(())
+}
+
+fn time<T>(total: &mut Duration, stage: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = stage();
+    *total += start.elapsed();
+    result
+}
+
+/// A hue sweep scrolling under a bouncing circle, with some grain so that
+/// neither the encoder nor the color compression get it too easy
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn synthetic(width: u32, height: u32, frame: usize, count: usize) -> RgbImage {
+    let progress = frame as f32 / count as f32;
+    let center_x = width as f32 * (0.2 + 0.6 * progress);
+    let center_y = height as f32 * (0.5 + 0.3 * (progress * std::f32::consts::TAU).sin());
+    let radius = width.min(height) as f32 / 4.0;
+
+    let mut state = frame as u32 + 1;
+    RgbImage::from_fn(width, height, |x, y| {
+        // xorshift, plenty for grain
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let grain = (state % 16) as u8;
+
+        let dx = x as f32 - center_x;
+        let dy = y as f32 - center_y;
+        let color = if dx.hypot(dy) <= radius {
+            [240; 3]
+        } else {
+            let shade = y as f32 / height as f32;
+            hue((x as f32 / width as f32 + progress).fract())
+                .map(|channel| (f32::from(channel) * (1.0 - shade)) as u8)
+        };
+        Rgb(color.map(|channel| channel.saturating_add(grain)))
+    })
+}
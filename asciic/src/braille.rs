@@ -0,0 +1,63 @@
+use std::fmt;
+
+use image::{imageops::FilterType, DynamicImage};
+
+use crate::{
+    color::{luma, Rgb},
+    paint::{Canvas, Painter, Stroke},
+    primitives::Options,
+};
+
+/// Offset and bit of every dot in a braille cell, which is 2 dots wide and
+/// 4 dots tall
+const DOTS: [(u32, u32, u8); 8] = [
+    (0, 0, 0x01),
+    (0, 1, 0x02),
+    (0, 2, 0x04),
+    (1, 0, 0x08),
+    (1, 1, 0x10),
+    (1, 2, 0x20),
+    (0, 3, 0x40),
+    (1, 3, 0x80),
+];
+
+/// Draws the image with braille characters, lighting the dots brighter than
+/// the dot threshold. Each cell is painted with the average color of its
+/// lit dots.
+pub fn braille<C: Canvas>(
+    image: &DynamicImage,
+    options: Options,
+    canvas: C,
+) -> Result<C, fmt::Error> {
+    let (width, height) = (options.redimension.0, options.redimension.1);
+    let resized = image
+        .resize_exact(width * 2, height * 4, FilterType::Triangle)
+        .to_rgb8();
+
+    let mut painter = Painter::new(canvas, options, true);
+    painter.rows(height, |row| {
+        (0..width)
+            .map(|column| {
+                let mut pattern = 0;
+                let mut sum = [0u32; 3];
+                let mut lit = 0;
+
+                for (x, y, bit) in DOTS {
+                    let pixel = resized.get_pixel(column * 2 + x, row * 4 + y).0;
+                    if luma(pixel) > options.dot_threshold {
+                        pattern |= bit;
+                        lit += 1;
+                        for (sum, channel) in sum.iter_mut().zip(pixel) {
+                            *sum += u32::from(channel);
+                        }
+                    }
+                }
+
+                #[allow(clippy::cast_possible_truncation)]
+                let color: Rgb = sum.map(|sum| (sum / lit.max(1)) as u8);
+                Stroke::Paint(color, char::from_u32(0x2800 + u32::from(pattern)).unwrap())
+            })
+            .collect()
+    })?;
+    painter.finish()
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs::{copy, create_dir_all, hard_link, read, read_dir, remove_dir_all, write},
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use crate::primitives::{AudioMode, Options};
+
+/// Keeps the work of previous runs around: frames and audio extracted by
+/// ffmpeg are keyed by the video and the flags passed to ffmpeg, while
+/// converted frames are keyed by the source frame and the conversion
+/// settings. Re-running with different settings then skips ffmpeg, and
+/// re-running with the same ones skips almost everything.
+pub struct Cache {
+    video_dir: PathBuf,
+    converted_dir: PathBuf,
+}
+
+impl Cache {
+    pub fn open(dir: &Path, video_path: &str, ffmpeg_flags: &[&String]) -> io::Result<Self> {
+        let video_metadata = Path::new(video_path).metadata()?;
+
+        let mut hasher = DefaultHasher::new();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        Path::new(video_path).canonicalize()?.hash(&mut hasher);
+        video_metadata.len().hash(&mut hasher);
+        video_metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .hash(&mut hasher);
+        ffmpeg_flags.hash(&mut hasher);
+        let video_dir = dir.join(format!("{:016x}", hasher.finish()));
+
+        let cache = Self {
+            video_dir,
+            converted_dir: dir.join("converted"),
+        };
+        create_dir_all(cache.video_dir.join("frames"))?;
+        create_dir_all(&cache.converted_dir)?;
+        Ok(cache)
+    }
+
+    /// Brings back the frames of a previous run into `tmp_path`. Returns
+    /// false if there's nothing to bring back.
+    pub fn restore_frames(&self, tmp_path: &Path) -> io::Result<bool> {
+        if !self.video_dir.join("complete").exists() {
+            return Ok(false);
+        }
+
+        for entry in read_dir(self.video_dir.join("frames"))? {
+            let entry = entry?;
+            link(&entry.path(), &tmp_path.join(entry.file_name()))?;
+        }
+        Ok(true)
+    }
+
+    pub fn store_frames(&self, frames: &[PathBuf]) -> io::Result<()> {
+        let frames_dir = self.video_dir.join("frames");
+        remove_dir_all(&frames_dir)?;
+        create_dir_all(&frames_dir)?;
+
+        for frame in frames {
+            link(frame, &frames_dir.join(frame.file_name().unwrap()))?;
+        }
+        write(self.video_dir.join("complete"), [])
+    }
+
+    pub fn restore_audio(&self, tmp_path: &Path, audio: AudioMode) -> io::Result<bool> {
+        let cached = self.audio_path(audio);
+        if !cached.exists() {
+            return Ok(false);
+        }
+        link(&cached, &tmp_path.join("audio.mp3"))?;
+        Ok(true)
+    }
+
+    pub fn store_audio(&self, tmp_path: &Path, audio: AudioMode) -> io::Result<()> {
+        link(&tmp_path.join("audio.mp3"), &self.audio_path(audio))
+    }
+
+    /// Where the converted version of `frame` goes, under the settings it's
+    /// converted with
+    pub fn converted_path(&self, frame: &Path, options: Options) -> io::Result<PathBuf> {
+        let mut hasher = DefaultHasher::new();
+        read(frame)?.hash(&mut hasher);
+        let frame = hasher.finish();
+
+        // The audio doesn't end up in the frames
+        let mut hasher = DefaultHasher::new();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        Options {
+            audio: AudioMode::Skip,
+            ..options
+        }
+        .hash(&mut hasher);
+
+        Ok(self
+            .converted_dir
+            .join(format!("{frame:016x}-{:016x}.zst", hasher.finish())))
+    }
+
+    fn audio_path(&self, audio: AudioMode) -> PathBuf {
+        self.video_dir.join(match audio {
+            AudioMode::DuckMusic => "audio-ducked.mp3",
+            AudioMode::Skip | AudioMode::Extract => "audio.mp3",
+        })
+    }
+}
+
+/// Hard links `from` to `to`, copying it instead when they live on
+/// different filesystems
+fn link(from: &Path, to: &Path) -> io::Result<()> {
+    hard_link(from, to).or_else(|_| copy(from, to).map(drop))
+}
@@ -0,0 +1,226 @@
+use clap::ValueEnum;
+
+use crate::{
+    font::{can_draw, ink, GLYPH_HEIGHT, GLYPH_WIDTH},
+    primitives::CharsetPreset,
+};
+pub use asciicore::raster::{pick, Ramp, DEFAULT, TINY};
+
+/// Outputs narrower than this many columns count as tiny
+pub const TINY_WIDTH: u32 = 40;
+
+impl CharsetPreset {
+    pub fn ramp(self) -> &'static Ramp {
+        match self {
+            Self::Minimal => TINY,
+            Self::Standard => DEFAULT,
+            Self::Detailed70 => parse_charset(
+                " .'`^\",:;Il!i><~+_-?][}{1)(|\\/tfjrxnuvczXYUJCLQ0OZmwqpdbkhao*#MW&8%B@$",
+            )
+            .unwrap(),
+            Self::Blocks => parse_charset(" ░▒▓█").unwrap(),
+            Self::Dots => parse_charset(" ⠁⠃⠇⠏⠟⠿⡿⣿").unwrap(),
+        }
+    }
+}
+
+/// Prints every built in charset, with its name and its characters
+pub fn list_charsets() {
+    for preset in CharsetPreset::value_variants() {
+        let name = preset.to_possible_value().unwrap();
+        let chars = preset.ramp().iter().map(|&(_, c)| c).collect::<String>();
+        println!("{:<12}{chars}", name.get_name());
+    }
+}
+
+/// The characters of `ramp` with their highest brightness set to
+/// `thresholds` instead, which have to go up from one character to the next
+pub fn with_thresholds(ramp: &Ramp, thresholds: &[u8]) -> Result<&'static Ramp, String> {
+    if thresholds.len() != ramp.len() {
+        return Err(format!(
+            "Got {} thresholds for {} characters",
+            thresholds.len(),
+            ramp.len()
+        ));
+    }
+    if thresholds.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err("Thresholds have to go up from darkest to brightest".into());
+    }
+
+    let ramp = thresholds
+        .iter()
+        .zip(ramp)
+        .map(|(&max, &(_, ch))| (max, ch))
+        .collect::<Box<_>>();
+    Ok(Box::leak(ramp))
+}
+
+/// The characters of `ramp` sorted by how much of their cell the built in
+/// font inks, each standing for brightness up to halfway to the next one.
+/// Characters that ink as much as one before them are dropped, since they'd
+/// never get picked.
+pub fn by_ink(ramp: &Ramp) -> Result<&'static Ramp, String> {
+    if let Some(&(_, c)) = ramp.iter().find(|(_, c)| !can_draw(*c)) {
+        return Err(format!(
+            "There's no way to measure {c:?} yet, only ASCII, blocks, shades and braille"
+        ));
+    }
+
+    let mut measured = ramp
+        .iter()
+        .map(|&(_, c)| {
+            let lit = (0..GLYPH_HEIGHT)
+                .flat_map(|y| (0..GLYPH_WIDTH).map(move |x| (x, y)))
+                .filter(|&(x, y)| ink(c, x, y))
+                .count();
+            (lit, c)
+        })
+        .collect::<Vec<_>>();
+    measured.sort_by_key(|&(lit, _)| lit);
+    measured.dedup_by_key(|&mut (lit, _)| lit);
+
+    let (Some(&(least, _)), Some(&(most, _))) = (measured.first(), measured.last()) else {
+        return Err("There are no characters to measure".into());
+    };
+    if least == most {
+        return Err("Every character inks as much as the others".into());
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    let ramp = measured
+        .iter()
+        .zip(measured.iter().skip(1).map(Some).chain([None]))
+        .map(|(&(lit, c), next)| {
+            let max = next.map_or(255, |&(next, _)| {
+                ((lit + next - 2 * least) * 255 / (2 * (most - least))) as u8
+            });
+            (max, c)
+        })
+        .collect::<Box<_>>();
+    Ok(Box::leak(ramp))
+}
+
+/// Code points that take no column of their own: combining marks, joiners
+/// and variation selectors
+const ZERO_WIDTH: &[(u32, u32)] = &[
+    (0x0300, 0x036f),
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x0e31, 0x0e3a),
+    (0x1ab0, 0x1aff),
+    (0x1dc0, 0x1dff),
+    (0x200b, 0x200f),
+    (0x20d0, 0x20ff),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0xe0100, 0xe01ef),
+];
+
+/// Code points that terminals draw two columns wide
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115f),
+    (0x2e80, 0x303e),
+    (0x3041, 0x33ff),
+    (0x3400, 0x4dbf),
+    (0x4e00, 0x9fff),
+    (0xa000, 0xa4cf),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe30, 0xfe4f),
+    (0xff00, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x1f300, 0x1f64f),
+    (0x1f900, 0x1f9ff),
+    (0x20000, 0x3fffd),
+];
+
+/// Columns that `c` takes up in a terminal
+pub fn width(c: char) -> usize {
+    let within = |ranges: &[(u32, u32)]| {
+        ranges
+            .iter()
+            .any(|&(start, end)| (start..=end).contains(&u32::from(c)))
+    };
+    if c.is_control() || within(ZERO_WIDTH) {
+        0
+    } else if within(WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Splits `text` into what shows up as single characters, keeping combining
+/// marks with the character before them
+fn graphemes(text: &str) -> Vec<&str> {
+    let mut graphemes = Vec::new();
+    let mut start = 0;
+    for (index, c) in text.char_indices().skip(1) {
+        if width(c) != 0 || c.is_control() {
+            graphemes.push(&text[start..index]);
+            start = index;
+        }
+    }
+    if !text.is_empty() {
+        graphemes.push(&text[start..]);
+    }
+    graphemes
+}
+
+/// Reads a ramp from characters going from darkest to brightest, spread
+/// evenly over the brightness range, or from the name of a built in one.
+/// Anything that wouldn't take exactly one column gets refused, since it
+/// would throw every row out of line. Characters can be repeated next to
+/// each other to give them more of the range, but not come back after
+/// others, or the brightness would go back and forth.
+pub fn parse_charset(value: &str) -> Result<&'static Ramp, String> {
+    if let Ok(preset) = CharsetPreset::from_str(value, true) {
+        return Ok(preset.ramp());
+    }
+
+    let graphemes = graphemes(value);
+    if graphemes.len() < 2 {
+        return Err("A charset needs at least 2 characters".into());
+    }
+
+    let mut ramp = Vec::with_capacity(graphemes.len());
+    for (index, grapheme) in graphemes.iter().enumerate() {
+        let mut chars = grapheme.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return Err(format!(
+                "{grapheme:?} is a character with marks combined into it, \
+                which frames can't hold"
+            ));
+        };
+        if c.is_control() {
+            return Err(format!(
+                "{c:?} (U+{:04X}) is a control character, which would mess with the terminal",
+                u32::from(c)
+            ));
+        }
+        if width(c) != 1 {
+            return Err(format!(
+                "{c:?} (U+{:04X}) takes {} columns instead of 1",
+                u32::from(c),
+                width(c)
+            ));
+        }
+
+        if ramp.iter().any(|&(_, seen)| seen == c) && ramp.last().map(|&(_, last)| last) != Some(c)
+        {
+            return Err(format!(
+                "{c:?} shows up again after other characters. Repeat it right next to \
+                itself to give it more of the range"
+            ));
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let max = ((index + 1) * 255 / graphemes.len()) as u8;
+        ramp.push((max, c));
+    }
+
+    // Parsed once per run, and needed for as long as frames get drawn
+    Ok(Box::leak(ramp.into_boxed_slice()))
+}
@@ -1,8 +1,16 @@
-use std::path::PathBuf;
+use std::{net::SocketAddr, path::PathBuf};
 
-use clap::{value_parser, Arg, Command};
+use clap::{builder::RangedU64ValueParser, value_parser, Arg, Command};
 
-use crate::primitives::{OutputSize, PaintStyle};
+use crate::{
+    charset::parse_charset,
+    color::{parse_hex, Rgb},
+    primitives::{
+        ColorMode, Container, Crop, DitherMode, Effect, ErrorFormat, FitMode, Luminance,
+        OutputFormat, OutputSize, PaintStyle, PlainProfile, Preset, RenderMode, Sampling,
+        ThresholdMetric, Transition, TwoLevel, WhiteBalance,
+    },
+};
 
 #[inline]
 pub fn cli() -> Command<'static> {
@@ -11,10 +19,541 @@ pub fn cli() -> Command<'static> {
         .about("An asciinema compiler")
         .author("by S0ra")
         .args(args())
+        .next_help_heading("SIZING")
+        .args(size_args())
+        .next_help_heading("IMAGE OUTPUT")
+        .args(image_args())
+        .next_help_heading("RENDERING")
+        .args(render_args())
+        .next_help_heading("CHARSETS")
+        .args(charset_args())
+        .next_help_heading("ADJUSTMENTS")
+        .args(adjust_args())
+        .next_help_heading("COLORS")
+        .args(color_args())
+        .next_help_heading("OVERLAY")
+        .args(overlay_args())
+        .next_help_heading("VIDEO OPTIONS")
+        .args(video_args())
+        .next_help_heading("TEMPORAL COMPRESSION")
+        .args(temporal_args())
+        .next_help_heading("PLAYER HINTS")
+        .args(player_args())
+        .next_help_heading("HOOKS")
+        .args(hook_args())
+        .subcommand(retime())
+        .subcommand(split())
+        .subcommand(join())
+        .subcommand(theme())
+        .subcommand(doctor())
+        .subcommand(charsets())
+        .subcommand(diff())
+        .subcommand(import())
+        .subcommand(slideshow())
+        .subcommand(view())
+        .subcommand(testcard())
+        .subcommand(bench())
+        .subcommand(daemon())
+        .arg(
+            Arg::new("error-format")
+                .long("error-format")
+                .global(true)
+                .takes_value(true)
+                .default_value("human")
+                .value_parser(value_parser!(ErrorFormat))
+                .help("How errors are printed to stderr"),
+        )
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
 }
 
 #[inline]
-fn args() -> [Arg<'static>; 10] {
+fn diff() -> Command<'static> {
+    Command::new("diff")
+        .about("Compares two archives frame by frame")
+        .args([
+            Arg::new("a")
+                .required(true)
+                .index(1)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Archive to compare against"),
+            Arg::new("b")
+                .required(true)
+                .index(2)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Archive to compare"),
+            Arg::new("show")
+                .long("show")
+                .takes_value(true)
+                .multiple_values(true)
+                .value_name("FRAME")
+                .value_parser(value_parser!(usize))
+                .help("Frames to render side by side, counting from 1"),
+        ])
+}
+
+#[inline]
+fn import() -> Command<'static> {
+    Command::new("import")
+        .about("Converts ANSI art (.ans, .nfo) into an archive, one frame per file")
+        .args([
+            Arg::new("input")
+                .required(true)
+                .index(1)
+                .takes_value(true)
+                .multiple_values(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("ANSI art files, in CP437 with an optional SAUCE record"),
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Output file name [default: <first input>.bapple]"),
+        ])
+}
+
+#[inline]
+fn view() -> Command<'static> {
+    Command::new("view")
+        .about("Shows a single frame (.txt or ANSI art), centered or with panning")
+        .args([
+            Arg::new("input")
+                .required_unless_present("source")
+                .index(1)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Frame to show"),
+            Arg::new("source")
+                .long("source")
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Renders this image at the size of the terminal instead"),
+            Arg::new("colorize")
+                .short('c')
+                .requires("source")
+                .help("Colorize the image given with --source"),
+        ])
+        .args(sixel_args())
+        .args(kitty_args())
+}
+
+#[cfg(feature = "sixel")]
+#[inline]
+fn sixel_args() -> [Arg<'static>; 1] {
+    [Arg::new("sixel")
+        .long("sixel")
+        .requires("source")
+        .help("Draws the image given with --source as sixels, in terminals that can show them")]
+}
+
+#[cfg(not(feature = "sixel"))]
+#[inline]
+fn sixel_args() -> [Arg<'static>; 0] {
+    []
+}
+
+#[cfg(feature = "kitty")]
+#[inline]
+fn kitty_args() -> [Arg<'static>; 1] {
+    [Arg::new("kitty")
+        .long("kitty")
+        .requires("source")
+        .help("Draws the image given with --source with the kitty graphics protocol, in terminals that support it. Tried before --sixel")]
+}
+
+#[cfg(not(feature = "kitty"))]
+#[inline]
+fn kitty_args() -> [Arg<'static>; 0] {
+    []
+}
+
+#[inline]
+fn slideshow() -> Command<'static> {
+    Command::new("slideshow")
+        .about("Compiles images into an archive that shows each of them for a while")
+        .args([
+            Arg::new("input")
+                .required(true)
+                .index(1)
+                .takes_value(true)
+                .multiple_values(true)
+                .value_parser(value_parser!(String))
+                .help("Slides, in order (glob patterns are accepted)"),
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .takes_value(true)
+                .default_value("slideshow")
+                .value_parser(value_parser!(PathBuf))
+                .help("Output file name"),
+            Arg::new("frame-size")
+                .short('s')
+                .long("size")
+                .takes_value(true)
+                .default_value("216x56")
+                .value_parser(value_parser!(OutputSize))
+                .help("The ratio that each frame should be resized"),
+            Arg::new("colorize").short('c').help("Colorize output"),
+            Arg::new("per-slide")
+                .long("per-slide")
+                .takes_value(true)
+                .default_value("5s")
+                .value_parser(parse_seconds)
+                .help("How long each slide stays up, like 5s or 500ms"),
+            Arg::new("transition")
+                .long("transition")
+                .takes_value(true)
+                .default_value("none")
+                .value_parser(value_parser!(Transition))
+                .help("How to go from one slide to the next"),
+            Arg::new("transition-time")
+                .long("transition-time")
+                .takes_value(true)
+                .default_value("1s")
+                .value_parser(parse_seconds)
+                .help("How long transitions take, on top of --per-slide"),
+            Arg::new("fps")
+                .long("fps")
+                .takes_value(true)
+                .default_value("10")
+                .value_parser(value_parser!(f64))
+                .help("Framerate of the output, which only matters for transitions"),
+            Arg::new("ignore-orientation")
+                .long("ignore-orientation")
+                .help("Keeps photos the way they're stored, instead of turning them the way their EXIF data says"),
+        ])
+}
+
+fn testcard() -> Command<'static> {
+    Command::new("testcard")
+        .about("Generates calibration frames, to check a terminal's colors, font and speed")
+        .args([
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .takes_value(true)
+                .default_value("testcard")
+                .value_parser(value_parser!(PathBuf))
+                .help("Output file name"),
+            Arg::new("frame-size")
+                .short('s')
+                .long("size")
+                .takes_value(true)
+                .default_value("120x40")
+                .value_parser(value_parser!(OutputSize))
+                .help("Size of the cards, in characters"),
+            Arg::new("colorize").short('c').help("Colorize output"),
+            Arg::new("cell-aspect")
+                .long("cell-aspect")
+                .takes_value(true)
+                .default_value("2.0")
+                .value_parser(value_parser!(f32))
+                .help("Cell proportions the aspect grid is drawn for"),
+            Arg::new("text")
+                .long("text")
+                .help("Also writes each card into its own .txt file"),
+            Arg::new("per-card")
+                .long("per-card")
+                .takes_value(true)
+                .default_value("3s")
+                .value_parser(parse_seconds)
+                .help("How long each card stays up, like 3s or 500ms"),
+            Arg::new("stress")
+                .long("stress")
+                .takes_value(true)
+                .default_value("5s")
+                .value_parser(parse_seconds)
+                .help("How long the stress frames run for, at --fps"),
+            Arg::new("fps")
+                .long("fps")
+                .takes_value(true)
+                .default_value("30")
+                .value_parser(value_parser!(f64))
+                .help("Framerate to test the terminal at"),
+        ])
+}
+
+fn bench() -> Command<'static> {
+    Command::new("bench")
+        .about("Times each step of compiling a made up clip, to compare machines and builds")
+        .args([
+            Arg::new("frames")
+                .long("frames")
+                .takes_value(true)
+                .default_value("60")
+                .value_parser(value_parser!(usize))
+                .help("How many frames to run through"),
+            Arg::new("source")
+                .long("source")
+                .takes_value(true)
+                .value_name("WxH")
+                .default_value("1280x720")
+                .value_parser(parse_dimensions)
+                .help("Size of the generated frames, in pixels"),
+            Arg::new("frame-size")
+                .short('s')
+                .long("size")
+                .takes_value(true)
+                .default_value("216x56")
+                .value_parser(value_parser!(OutputSize))
+                .help("Size the frames get drawn at, in characters"),
+        ])
+}
+
+fn daemon() -> Command<'static> {
+    Command::new("daemon")
+        .about("Watches a folder and compiles every video dropped into it")
+        .args([
+            Arg::new("watch")
+                .long("watch")
+                .required(true)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Folder to watch for videos"),
+            Arg::new("out")
+                .long("out")
+                .required(true)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Folder the archives get written to"),
+            Arg::new("preset")
+                .long("preset")
+                .takes_value(true)
+                .default_value("color")
+                .value_parser(value_parser!(Preset))
+                .help("Compile flags to use"),
+            Arg::new("status")
+                .long("status")
+                .takes_value(true)
+                .value_name("ADDRESS")
+                .value_parser(value_parser!(SocketAddr))
+                .help("Serves a plain text status page, like 127.0.0.1:7878"),
+            Arg::new("flags")
+                .index(1)
+                .multiple_values(true)
+                .allow_hyphen_values(true)
+                .takes_value(true)
+                .value_parser(value_parser!(String))
+                .help("More compile flags, added after the preset's")
+                .last(true),
+        ])
+}
+
+fn parse_dimensions(value: &str) -> Result<(u32, u32), String> {
+    value
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|&(width, height)| width > 0 && height > 0)
+        .ok_or_else(|| format!("Invalid size {value:?}, try 640x360"))
+}
+
+/// Parses `WxH+X+Y` regions, the way imagemagick writes them
+fn parse_crop(value: &str) -> Result<Crop, String> {
+    let error = || format!("Invalid region {value:?}, try 640x360+320+0");
+    let (size, offset) = value.split_once('+').ok_or_else(error)?;
+    let (width, height) = parse_dimensions(size).map_err(|_| error())?;
+    let (x, y) = offset
+        .split_once('+')
+        .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)))
+        .ok_or_else(error)?;
+    Ok(Crop {
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+fn parse_two_level(value: &str) -> Result<TwoLevel, String> {
+    match value {
+        "auto" => Ok(TwoLevel::Auto),
+        level => level
+            .parse()
+            .map(TwoLevel::At)
+            .map_err(|_| format!("Invalid level {value:?}, try auto or 0 to 255")),
+    }
+}
+
+/// Parses `STREAM[:LANG]` audio tracks. Tracks without a language are
+/// tagged `und`, the way ffmpeg does.
+fn parse_track(value: &str) -> Result<(usize, String), String> {
+    let (stream, language) = value.split_once(':').unwrap_or((value, "und"));
+    match stream.parse() {
+        Ok(stream) if !language.is_empty() && !language.contains([',', '\n']) => {
+            Ok((stream, language.to_string()))
+        }
+        _ => Err(format!("Invalid track {value:?}, try 1:commentary")),
+    }
+}
+
+fn parse_color(value: &str) -> Result<Rgb, String> {
+    parse_hex(value).ok_or_else(|| format!("Invalid color {value:?}, try #00ff00"))
+}
+
+/// Parses `X,Y` positions, where negative numbers count from the other edge
+fn parse_position(value: &str) -> Result<(i32, i32), String> {
+    value
+        .split_once(',')
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        .ok_or_else(|| format!("Invalid position {value:?}, try -1,-1"))
+}
+
+/// Parses sizes like `8MB`, `500K` or a plain number of bytes. Units go up
+/// by thousands, the way upload limits are usually given.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn parse_size(value: &str) -> Result<u64, String> {
+    let upper = value.trim().to_ascii_uppercase();
+    let number = upper.strip_suffix('B').unwrap_or(&upper);
+    let (number, scale) = [('K', 1e3), ('M', 1e6), ('G', 1e9)]
+        .into_iter()
+        .find_map(|(unit, scale)| Some((number.strip_suffix(unit)?, scale)))
+        .unwrap_or((number, 1.0));
+
+    match number.trim().parse::<f64>() {
+        Ok(size) if size * scale >= 1.0 && size.is_finite() => Ok((size * scale) as u64),
+        _ => Err(format!("Invalid size {value:?}, try 8MB")),
+    }
+}
+
+/// Parses durations like `5s`, `500ms` or a plain `1.5`, in seconds
+fn parse_seconds(value: &str) -> Result<f64, String> {
+    let (number, scale) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 1000.0)
+    } else {
+        (value.strip_suffix('s').unwrap_or(value), 1.0)
+    };
+
+    match number.trim().parse::<f64>() {
+        Ok(seconds) if seconds >= 0.0 => Ok(seconds / scale),
+        _ => Err(format!("Invalid duration {value:?}, try 5s or 500ms")),
+    }
+}
+
+#[inline]
+fn charsets() -> Command<'static> {
+    Command::new("charsets").about("Lists the built in charsets --charset takes by name")
+}
+
+#[inline]
+fn doctor() -> Command<'static> {
+    Command::new("doctor")
+        .about("Checks for common problems with your environment")
+        .arg(
+            Arg::new("output-dir")
+                .index(1)
+                .takes_value(true)
+                .default_value(".")
+                .value_parser(value_parser!(PathBuf))
+                .help("Where you plan on writing outputs to"),
+        )
+}
+
+#[inline]
+fn theme() -> Command<'static> {
+    Command::new("theme")
+        .about("Remaps every color of an existing archive to a palette")
+        .args([
+            Arg::new("input")
+                .required(true)
+                .index(1)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Archive to recolor"),
+            Arg::new("output")
+                .index(2)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Output file name [default: <input>_themed.bapple]"),
+            Arg::new("palette")
+                .short('p')
+                .long("palette")
+                .required(true)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("File with one hex color (#rrggbb) per line"),
+        ])
+}
+
+#[inline]
+fn retime() -> Command<'static> {
+    Command::new("retime")
+        .about("Changes the framerate of an existing archive")
+        .args([
+            Arg::new("input")
+                .required(true)
+                .index(1)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Archive to retime"),
+            Arg::new("output")
+                .index(2)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Output file name [default: <input>_<fps>fps.bapple]"),
+            Arg::new("fps")
+                .long("fps")
+                .required(true)
+                .takes_value(true)
+                .value_parser(value_parser!(f64))
+                .help("Framerate to convert to"),
+            Arg::new("from")
+                .long("from")
+                .takes_value(true)
+                .value_parser(value_parser!(f64))
+                .help("Framerate of the input, for archives that don't record it"),
+        ])
+}
+
+#[inline]
+fn split() -> Command<'static> {
+    Command::new("split")
+        .about("Cuts an archive into parts, for sharing where files can only be so big")
+        .args([
+            Arg::new("input")
+                .required(true)
+                .index(1)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Archive to split"),
+            Arg::new("output")
+                .index(2)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Name the parts are based on, like <output>.part1.bapple [default: <input>]"),
+            Arg::new("max-size")
+                .long("max-size")
+                .required(true)
+                .takes_value(true)
+                .value_name("SIZE")
+                .value_parser(parse_size)
+                .help("Largest a part can be, like 8MB or 500KB"),
+        ])
+}
+
+#[inline]
+fn join() -> Command<'static> {
+    Command::new("join")
+        .about("Puts an archive cut up by split back together")
+        .args([
+            Arg::new("input")
+                .required(true)
+                .index(1)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Any part of the archive, the others are found next to it"),
+            Arg::new("output")
+                .index(2)
+                .takes_value(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Output file name [default: the name it was split from]"),
+        ])
+}
+
+#[inline]
+fn args() -> [Arg<'static>; 14] {
     [
         Arg::new("video")
             .required_unless_present("image")
@@ -28,19 +567,13 @@ fn args() -> [Arg<'static>; 10] {
             .conflicts_with("image")
             .help("Output file name")
             .index(2),
-        Arg::new("frame-size")
-            .short('s')
-            .default_value("216x56")
-            .long("size")
-            .takes_value(true)
-            .required(false)
-            .help("The ratio that each frame should be resized")
-            .value_parser(value_parser!(OutputSize)),
         Arg::new("image")
             .short('i')
             .long("image")
             .takes_value(true)
-            .help("Compiles a single image"),
+            .multiple_values(true)
+            .value_parser(value_parser!(String))
+            .help("Compiles one or more images (glob patterns are accepted)"),
         Arg::new("colorize").short('c').help("Colorize output"),
         Arg::new("no-compression")
             .short('n')
@@ -78,5 +611,569 @@ fn args() -> [Arg<'static>; 10] {
             .default_value("bg-paint")
             .hide_default_value(true)
             .value_parser(value_parser!(PaintStyle)),
+        Arg::new("color-mode")
+            .long("color-mode")
+            .takes_value(true)
+            .requires("colorize")
+            .default_value("truecolor")
+            .value_parser(value_parser!(ColorMode))
+            .help("Which color sequences to emit, for terminals without 24-bit colors"),
+        Arg::new("dither")
+            .long("dither")
+            .takes_value(true)
+            .requires("colorize")
+            .default_value("none")
+            .value_parser(value_parser!(DitherMode))
+            .help("Spreads out the error of colors reduced by --color-mode"),
+        Arg::new("config")
+            .long("config")
+            .takes_value(true)
+            .value_name("FILE")
+            .value_parser(value_parser!(PathBuf))
+            .help(
+                "Loads conversion settings saved with --save-config, or the ones a .bapple \
+                was compiled with. Flags given here win over the file's",
+            ),
+        Arg::new("save-config")
+            .long("save-config")
+            .takes_value(true)
+            .value_name("FILE")
+            .value_parser(value_parser!(PathBuf))
+            .help("Saves the conversion settings given here as JSON, to be loaded with --config"),
+        Arg::new("from-calibration")
+            .long("from-calibration")
+            .takes_value(true)
+            .value_name("FILE")
+            .value_parser(value_parser!(PathBuf))
+            .help(
+                "Fits frames to what `asciix calibrate` measured the terminal could keep up \
+                with: its size, colors and compression threshold",
+            ),
+    ]
+}
+
+#[inline]
+fn size_args() -> [Arg<'static>; 8] {
+    [
+        Arg::new("frame-size")
+            .short('s')
+            .default_value("216x56")
+            .long("size")
+            .takes_value(true)
+            .required(false)
+            .help("The ratio that each frame should be resized")
+            .value_parser(value_parser!(OutputSize)),
+        Arg::new("fit")
+            .long("fit")
+            .takes_value(true)
+            .min_values(0)
+            .max_values(1)
+            .value_name("WxH")
+            .value_parser(value_parser!(OutputSize))
+            .help("Keeps the aspect ratio, fitting frames in WxH [default: the terminal's size]"),
+        Arg::new("cell-aspect")
+            .long("cell-aspect")
+            .takes_value(true)
+            .default_value("2.0")
+            .value_parser(value_parser!(f32))
+            .help("How many times taller than wide terminal cells are, for --fit and --fit-mode"),
+        Arg::new("fit-mode")
+            .long("fit-mode")
+            .takes_value(true)
+            .default_value("stretch")
+            .conflicts_with("pixel-art")
+            .value_parser(value_parser!(FitMode))
+            .help("How frames fill --size, or --fit, when their proportions don't match"),
+        Arg::new("crop")
+            .long("crop")
+            .takes_value(true)
+            .value_name("WxH+X+Y")
+            .value_parser(parse_crop)
+            .help("Only converts this region of each frame, in pixels from the top left"),
+        Arg::new("pixel-art")
+            .long("pixel-art")
+            .conflicts_with_all(&["glyph-match", "edges"])
+            .help("Gives each source pixel the same whole number of cells, for crisp sprites"),
+        Arg::new("sampling")
+            .long("sampling")
+            .takes_value(true)
+            .default_value("nearest")
+            .value_parser(value_parser!(Sampling))
+            .help("How the pixel standing for each cell is taken from the frame"),
+        Arg::new("color-sampling")
+            .long("color-sampling")
+            .takes_value(true)
+            .requires("colorize")
+            .value_parser(value_parser!(Sampling))
+            .help("Samples colors differently from the characters [default: --sampling]"),
+    ]
+}
+
+#[inline]
+fn hook_args() -> [Arg<'static>; 2] {
+    [
+        Arg::new("on-complete")
+            .long("on-complete")
+            .takes_value(true)
+            .value_name("CMD")
+            .help(
+                "Runs CMD through the shell once compiling succeeds, with details in ASCIIC_* \
+                variables",
+            ),
+        Arg::new("on-error")
+            .long("on-error")
+            .takes_value(true)
+            .value_name("CMD")
+            .help(
+                "Runs CMD through the shell when compiling fails, with details in ASCIIC_* \
+                variables",
+            ),
+    ]
+}
+
+#[inline]
+fn image_args() -> [Arg<'static>; 5] {
+    [
+        Arg::new("format")
+            .long("format")
+            .takes_value(true)
+            .requires("image")
+            .default_value("text")
+            .value_parser(value_parser!(OutputFormat))
+            .help("What images get compiled into"),
+        Arg::new("to-clipboard")
+            .long("to-clipboard")
+            .requires("image")
+            .conflicts_with("video")
+            .help("Copies the compiled image instead of writing it to a file"),
+        Arg::new("plain-profile")
+            .long("plain-profile")
+            .takes_value(true)
+            .requires("image")
+            .conflicts_with_all(&["video", "colorize"])
+            .value_parser(value_parser!(PlainProfile))
+            .help("Keeps text to plain ASCII lines short enough to paste into comments, commits or emails"),
+        Arg::new("with-plain")
+            .long("with-plain")
+            .requires_all(&["image", "colorize"])
+            .conflicts_with("to-clipboard")
+            .help("Also writes each image without colors into NAME.plain.txt, in the same pass"),
+        Arg::new("ignore-orientation")
+            .long("ignore-orientation")
+            .requires("image")
+            .help("Keeps photos the way they're stored, instead of turning them the way their EXIF data says"),
+    ]
+}
+
+#[inline]
+fn render_args() -> [Arg<'static>; 7] {
+    [
+        Arg::new("render")
+            .long("render")
+            .takes_value(true)
+            .default_value("ascii")
+            .value_parser(value_parser!(RenderMode))
+            .help("Which characters to draw frames with"),
+        Arg::new("luminance")
+            .long("luminance")
+            .takes_value(true)
+            .default_value("red")
+            .value_parser(value_parser!(Luminance))
+            .help("How brightness is measured when picking characters"),
+        Arg::new("dot-threshold")
+            .long("dot-threshold")
+            .takes_value(true)
+            .default_value("127")
+            .value_parser(value_parser!(u8))
+            .help("Brightness above which braille dots and quadrants are lit"),
+        Arg::new("glyph-match")
+            .long("glyph-match")
+            .help("Picks characters by their shape instead of brightness (experimental, slow)"),
+        Arg::new("anaglyph")
+            .long("anaglyph")
+            .requires("colorize")
+            .help("Draws side by side 3D frames as a red/cyan anaglyph"),
+        Arg::new("right-eye")
+            .long("right-eye")
+            .takes_value(true)
+            .requires_all(&["image", "colorize"])
+            .value_parser(value_parser!(PathBuf))
+            .help("Right view for the image given with -i, making an anaglyph of the pair"),
+        Arg::new("edges")
+            .long("edges")
+            .takes_value(true)
+            .min_values(0)
+            .max_values(1)
+            .default_missing_value("200")
+            .value_parser(value_parser!(u16))
+            .value_name("THRESHOLD")
+            .help("Draws outlines with / \\ | - _ strokes, past a Sobel magnitude [default: 200]"),
+    ]
+}
+
+#[inline]
+fn charset_args() -> [Arg<'static>; 6] {
+    [
+        Arg::new("auto-charset")
+            .long("auto-charset")
+            .help("Uses fewer, higher contrast characters on outputs under 40 columns"),
+        Arg::new("charset")
+            .long("charset")
+            .takes_value(true)
+            .value_name("CHARS")
+            .value_parser(parse_charset)
+            .conflicts_with_all(&["auto-charset", "glyph-match"])
+            .help("Characters to draw with, from darkest to brightest, one column wide each, or a built in charset's name (see asciic charsets)"),
+        Arg::new("thresholds")
+            .long("thresholds")
+            .takes_value(true)
+            .use_value_delimiter(true)
+            .value_name("LEVELS")
+            .value_parser(value_parser!(u8))
+            .conflicts_with_all(&["auto-charset", "glyph-match"])
+            .help(
+                "Highest brightness (0-255) of each character, like 20,40,80,100,130,200,250,255",
+            ),
+        Arg::new("measure-charset")
+            .long("measure-charset")
+            .requires("charset")
+            .conflicts_with("thresholds")
+            .help("Orders --charset by how much ink each character has, with thresholds to match"),
+        Arg::new("scene-charsets")
+            .long("scene-charsets")
+            .takes_value(true)
+            .multiple_values(true)
+            .min_values(2)
+            .value_name("CHARS")
+            .value_parser(parse_charset)
+            .conflicts_with_all(&[
+                "charset",
+                "thresholds",
+                "auto-charset",
+                "glyph-match",
+                "image",
+                "grid",
+            ])
+            .help(
+                "Charsets to choose from for each scene of a video, by what its brightness needs",
+            ),
+        Arg::new("two-level")
+            .long("two-level")
+            .takes_value(true)
+            .min_values(0)
+            .max_values(1)
+            .value_name("LEVEL")
+            .default_missing_value("auto")
+            .value_parser(parse_two_level)
+            .conflicts_with_all(&[
+                "colorize",
+                "glyph-match",
+                "auto-charset",
+                "thresholds",
+                "scene-charsets",
+                "edges",
+            ])
+            .help(
+                "Only draws blank cells and the brightest character, split at LEVEL or where \
+                it suits each frame best [default: auto]",
+            ),
+    ]
+}
+
+#[inline]
+fn adjust_args() -> [Arg<'static>; 7] {
+    [
+        Arg::new("gamma")
+            .long("gamma")
+            .takes_value(true)
+            .default_value("1.0")
+            .value_parser(value_parser!(f32))
+            .help("Brightens the midtones above 1, darkens them below"),
+        Arg::new("brightness")
+            .long("brightness")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .default_value("0")
+            .value_parser(value_parser!(i16).range(-255..=255))
+            .help("Added to every channel before converting"),
+        Arg::new("contrast")
+            .long("contrast")
+            .takes_value(true)
+            .default_value("1.0")
+            .value_parser(value_parser!(f32))
+            .help("Stretches colors away from gray above 1, flattens them below"),
+        Arg::new("saturation")
+            .long("saturation")
+            .takes_value(true)
+            .default_value("1.0")
+            .value_parser(value_parser!(f32))
+            .help("Makes colors stronger above 1, grayer below, and gray at 0"),
+        Arg::new("hue")
+            .long("hue")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .default_value("0")
+            .value_name("DEGREES")
+            .value_parser(value_parser!(f32))
+            .help("Turns every color around the color wheel"),
+        Arg::new("temperature")
+            .long("temperature")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .default_value("0")
+            .value_parser(value_parser!(i16).range(-100..=100))
+            .help("Warms colors up when positive, cools them down when negative"),
+        Arg::new("white-balance")
+            .long("white-balance")
+            .takes_value(true)
+            .value_parser(value_parser!(WhiteBalance))
+            .help("Takes out color casts, like the tint of phone footage"),
+    ]
+}
+
+#[inline]
+fn color_args() -> [Arg<'static>; 9] {
+    [
+        Arg::new("threshold-metric")
+            .long("threshold-metric")
+            .takes_value(true)
+            .requires("colorize")
+            .default_value("max-channel")
+            .value_parser(value_parser!(ThresholdMetric))
+            .help("How color changes are measured against --threshold"),
+        Arg::new("effect")
+            .long("effect")
+            .takes_value(true)
+            .requires("colorize")
+            .value_parser(value_parser!(Effect))
+            .help("Runs every cell through a built in effect"),
+        Arg::new("background")
+            .long("background")
+            .takes_value(true)
+            .value_name("COLOR")
+            .value_parser(parse_color)
+            .help(
+                "Draws --style fg-paint over this color (#rrggbb), whatever the terminal's theme",
+            ),
+        Arg::new("gradient")
+            .long("gradient")
+            .takes_value(true)
+            .use_value_delimiter(true)
+            .min_values(2)
+            .value_name("COLORS")
+            .requires("colorize")
+            .value_parser(parse_color)
+            .help("Paints cells by brightness along these colors, like '#1a0033,#ff66cc'"),
+        Arg::new("posterize")
+            .long("posterize")
+            .takes_value(true)
+            .value_name("LEVELS")
+            .requires("colorize")
+            .value_parser(value_parser!(u8).range(2..))
+            .help("Keeps each channel to this many levels, so colors change less often"),
+        Arg::new("dark-cutoff")
+            .long("dark-cutoff")
+            .takes_value(true)
+            .value_name("LEVEL")
+            .value_parser(value_parser!(u8))
+            .help("With --style bg-only, cells with no channel reaching LEVEL skip their color"),
+        Arg::new("dark-fill")
+            .long("dark-fill")
+            .takes_value(true)
+            .value_name("COLOR")
+            .requires("dark-cutoff")
+            .value_parser(parse_color)
+            .help("Paints cells under --dark-cutoff this color (#rrggbb), instead of leaving them blank"),
+        Arg::new("chroma-key")
+            .long("chroma-key")
+            .takes_value(true)
+            .value_name("COLOR")
+            .value_parser(parse_color)
+            .help("Leaves cells of this color (#rrggbb) blank, like a green screen"),
+        Arg::new("key-tolerance")
+            .long("key-tolerance")
+            .takes_value(true)
+            .default_value("60")
+            .requires("chroma-key")
+            .value_parser(value_parser!(u8))
+            .help("How far from --chroma-key colors can be and still get keyed out"),
+    ]
+}
+
+#[inline]
+fn overlay_args() -> [Arg<'static>; 3] {
+    [
+        Arg::new("overlay")
+            .long("overlay")
+            .takes_value(true)
+            .value_name("TEXT")
+            .help("Writes this text over every frame, like credits or a watermark"),
+        Arg::new("overlay-at")
+            .long("overlay-at")
+            .takes_value(true)
+            .value_name("X,Y")
+            .requires("overlay")
+            .allow_hyphen_values(true)
+            .default_value("0,0")
+            .value_parser(parse_position)
+            .help("Cell the overlay starts at, negative to count from the right and bottom"),
+        Arg::new("overlay-color")
+            .long("overlay-color")
+            .takes_value(true)
+            .value_name("COLOR")
+            .requires_all(&["overlay", "colorize"])
+            .value_parser(parse_color)
+            .help("Draws the overlay in this color (#rrggbb), instead of the frame's own"),
+    ]
+}
+
+#[inline]
+fn video_args() -> [Arg<'static>; 12] {
+    [
+        Arg::new("container")
+            .long("container")
+            .takes_value(true)
+            .default_value("tar")
+            .conflicts_with("image")
+            .value_parser(value_parser!(Container))
+            .help(
+                "How the archive is laid out, v3 being faster to open but needing a newer asciix",
+            ),
+        Arg::new("stall-timeout")
+            .long("stall-timeout")
+            .takes_value(true)
+            .default_value("60")
+            .value_parser(value_parser!(u64))
+            .help("Seconds without progress before ffmpeg is killed and retried (0 disables)"),
+        Arg::new("preview-live")
+            .long("preview-live")
+            .takes_value(true)
+            .min_values(0)
+            .max_values(1)
+            .default_missing_value("30")
+            .value_parser(value_parser!(u64).range(1..))
+            .conflicts_with("image")
+            .value_name("N")
+            .help("Shows every Nth converted frame in a corner while compiling [default: 30]"),
+        Arg::new("audio-only")
+            .long("audio-only")
+            .conflicts_with_all(&["image", "no-audio", "no-video"])
+            .help(
+                "Only extracts the audio, replacing the first track inside of an existing output",
+            ),
+        Arg::new("no-video")
+            .long("no-video")
+            .conflicts_with_all(&["image", "no-audio"])
+            .help("Only checks that the audio can be extracted, without writing anything"),
+        Arg::new("duck-music")
+            .long("duck-music")
+            .conflicts_with_all(&["image", "no-audio"])
+            .help("Brings speech forward over background music (needs ffmpeg 5.1+)"),
+        Arg::new("audio-track")
+            .long("audio-track")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("STREAM[:LANG]")
+            .conflicts_with_all(&["image", "no-audio", "audio-only", "no-video", "cache"])
+            .value_parser(parse_track)
+            .help("Stores the video's audio stream STREAM, counting from 0, as one of the tracks"),
+        Arg::new("rawvideo")
+            .long("rawvideo")
+            .takes_value(true)
+            .value_name("WxH")
+            .conflicts_with_all(&["image", "audio-only", "no-video", "duck-music", "cache"])
+            .value_parser(parse_dimensions)
+            .help("Reads the video as raw RGBA frames of WxH pixels, - being stdin"),
+        Arg::new("grid")
+            .long("grid")
+            .takes_value(true)
+            .value_name("COLUMNSxROWS")
+            .conflicts_with_all(&[
+                "image",
+                "rawvideo",
+                "audio-only",
+                "no-video",
+                "duck-music",
+                "cache",
+            ])
+            .value_parser(parse_dimensions)
+            .help("Reads the video as a sprite sheet, one frame per sprite in reading order"),
+        Arg::new("frame-delay")
+            .long("frame-delay")
+            .takes_value(true)
+            .requires("grid")
+            .default_value("100ms")
+            .value_parser(parse_seconds)
+            .help("How long each sprite of --grid stays up, like 100ms"),
+        Arg::new("cache")
+            .long("cache")
+            .takes_value(true)
+            .value_name("DIR")
+            .conflicts_with_all(&["image", "audio-only", "no-video"])
+            .value_parser(value_parser!(PathBuf))
+            .help("Keeps extracted and converted frames in DIR, so that later runs can skip work"),
+        Arg::new("plugin")
+            .long("plugin")
+            .takes_value(true)
+            .value_name("FILE")
+            .conflicts_with_all(&[
+                "image",
+                "rawvideo",
+                "grid",
+                "cache",
+                "audio-only",
+                "no-video",
+            ])
+            .value_parser(value_parser!(PathBuf))
+            .help("Runs every frame through a WASM plugin, see the README for what it exports"),
+    ]
+}
+
+#[inline]
+fn temporal_args() -> [Arg<'static>; 2] {
+    [
+        Arg::new("temporal")
+            .long("temporal")
+            .conflicts_with_all(&["image", "rawvideo", "grid", "audio-only", "no-video"])
+            .help(
+                "Stores frames as the cells that changed since the one before, which \
+                shrinks archives of mostly still videos a lot",
+            ),
+        Arg::new("keyframe-every")
+            .long("keyframe-every")
+            .takes_value(true)
+            .value_name("N")
+            .requires("temporal")
+            .default_value("60")
+            .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+            .help("Stores every Nth frame whole, so that players can skip ahead to it when late"),
+    ]
+}
+
+/// Recorded in the archive's metadata and used as the player's defaults
+#[inline]
+fn player_args() -> [Arg<'static>; 4] {
+    [
+        Arg::new("suggest-loop")
+            .long("suggest-loop")
+            .conflicts_with("image")
+            .help("Asks the player to loop this archive by default"),
+        Arg::new("suggest-no-drop")
+            .long("suggest-no-drop")
+            .conflicts_with("image")
+            .help("Asks the player to never skip frames, even when it falls behind"),
+        Arg::new("suggest-min-size")
+            .long("suggest-min-size")
+            .takes_value(true)
+            .conflicts_with("image")
+            .value_parser(value_parser!(OutputSize))
+            .help("Smallest terminal size the player should warn about [default: frame size]"),
+        Arg::new("events")
+            .long("events")
+            .takes_value(true)
+            .value_name("FILE")
+            .conflicts_with("image")
+            .value_parser(value_parser!(PathBuf))
+            .help("Event track for the player to hand out, as `FRAME KEY=VALUE` lines"),
     ]
 }
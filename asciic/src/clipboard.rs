@@ -0,0 +1,107 @@
+use std::{
+    env::var_os,
+    error::Error,
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::{
+    check_crop,
+    error::CompilerError,
+    export::{html, svg},
+    frame_cells,
+    orientation::open_image,
+    plain::tidy,
+    primitives::{Options, OutputFormat, PlainProfile},
+    render_image,
+};
+use arboard::Clipboard;
+use image::GenericImageView;
+
+/// Set for the copy of asciic that keeps the clipboard filled on Linux
+const HOLDER: &str = "ASCIIC_HOLD_CLIPBOARD";
+
+/// Converts `image` and puts the result on the clipboard, instead of writing
+/// it to a file
+pub fn copy_image(
+    image: &Path,
+    options: Options,
+    format: OutputFormat,
+    plain: Option<PlainProfile>,
+    oriented: bool,
+) -> Result<(), Box<dyn Error>> {
+    let image = open_image(image, oriented)?;
+    check_crop(options, image.dimensions())?;
+    let text = match format {
+        OutputFormat::Text => {
+            let mut text = String::new();
+            render_image(&image, options, &mut text);
+            if plain.is_some() {
+                text = tidy(&text);
+            }
+            text
+        }
+        OutputFormat::Html => html(&frame_cells(&image, options)),
+        OutputFormat::Svg => svg(&frame_cells(&image, options)),
+        OutputFormat::Png => {
+            return Err(CompilerError::InvalidInput(
+                "--to-clipboard only takes text, html or svg".into(),
+            )
+            .into())
+        }
+    };
+    copy(&text)
+}
+
+/// On X11 and Wayland, the clipboard only holds on to text for as long as
+/// the program that copied it is around, so a copy of asciic gets left
+/// behind to do that, until something else is copied. Other systems keep
+/// the text themselves.
+#[cfg(target_os = "linux")]
+fn copy(text: &str) -> Result<(), Box<dyn Error>> {
+    use std::{
+        env::current_exe,
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    // Opened here first, so that a missing display shows up as an error
+    Clipboard::new()?;
+    let mut holder = Command::new(current_exe()?)
+        .env(HOLDER, "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    holder.stdin.take().unwrap().write_all(text.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy(text: &str) -> Result<(), Box<dyn Error>> {
+    Clipboard::new()?.set_text(text)?;
+    Ok(())
+}
+
+/// Whether this process was started to hold the clipboard
+pub fn is_holder() -> bool {
+    var_os(HOLDER).is_some()
+}
+
+/// Copies stdin, then waits for something else to take the clipboard over
+#[cfg(target_os = "linux")]
+pub fn hold() -> Result<(), Box<dyn Error>> {
+    use arboard::SetExtLinux;
+
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    Clipboard::new()?.set().wait().text(text)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn hold() -> Result<(), Box<dyn Error>> {
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    copy(&text)
+}
@@ -0,0 +1,321 @@
+use std::{collections::VecDeque, error::Error, fs::read_to_string, path::Path};
+
+use crate::{
+    error::CompilerError,
+    primitives::{ColorMode, Luminance, ThresholdMetric},
+};
+use asciicore::ansi::Color;
+pub use asciicore::raster::luma;
+
+pub type Rgb = [u8; 3];
+
+/// Parses `rrggbb`, with or without a leading `#`
+pub fn parse_hex(hex: &str) -> Option<Rgb> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Reads a palette file: one hex color per line, anything after the color is
+/// ignored so that names or comments can follow it. Blank lines are skipped.
+pub fn read_palette(path: &Path) -> Result<Vec<Rgb>, Box<dyn Error>> {
+    let mut palette = Vec::new();
+
+    for (number, line) in read_to_string(path)?.lines().enumerate() {
+        let Some(color) = line.split_whitespace().next() else {
+            continue;
+        };
+        palette.push(parse_hex(color).ok_or_else(|| {
+            CompilerError::InvalidInput(format!("Invalid color at line {}: {color}", number + 1))
+        })?);
+    }
+
+    if palette.is_empty() {
+        return Err(CompilerError::InvalidInput("The palette is empty".into()).into());
+    }
+    Ok(palette)
+}
+
+/// Where `level` falls on a gradient through evenly spaced `stops`
+#[allow(clippy::cast_possible_truncation)]
+pub fn gradient(stops: &[Rgb], level: u8) -> Rgb {
+    let spans = stops.len().saturating_sub(1).max(1);
+    let position = usize::from(level) * spans;
+    let (span, into) = (position / 255, position % 255);
+    let from = stops[span.min(stops.len() - 1)];
+    let to = stops[(span + 1).min(stops.len() - 1)];
+    [0, 1, 2].map(|channel| {
+        let (from, to) = (from[channel] as usize, to[channel] as usize);
+        ((from * (255 - into) + to * into) / 255) as u8
+    })
+}
+
+/// Snaps every channel to the nearest of `levels` evenly spaced values
+#[allow(clippy::cast_possible_truncation)]
+pub fn posterize(color: Rgb, levels: u8) -> Rgb {
+    let steps = u32::from(levels.max(2) - 1);
+    color.map(|channel| {
+        let step = (u32::from(channel) * steps + 127) / 255;
+        (step * 255 / steps) as u8
+    })
+}
+
+#[inline]
+pub fn distance(a: Rgb, b: Rgb) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(&a, b)| u32::from(a.abs_diff(b)).pow(2))
+        .sum()
+}
+
+/// How far apart `a` and `b` are by `metric`, in the same units as the
+/// compression threshold
+pub fn difference(metric: ThresholdMetric, a: Rgb, b: Rgb) -> f32 {
+    match metric {
+        ThresholdMetric::MaxChannel => f32::from(
+            a.iter()
+                .zip(b)
+                .map(|(&a, b)| a.abs_diff(b))
+                .max()
+                .unwrap_or(0),
+        ),
+        #[allow(clippy::cast_precision_loss)]
+        ThresholdMetric::Euclidean => (distance(a, b) as f32).sqrt(),
+        ThresholdMetric::Oklab => {
+            let (a, b) = (oklab(a), oklab(b));
+            let squared = a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum::<f32>();
+            squared.sqrt() * 100.0
+        }
+    }
+}
+
+/// `color` in Oklab, where distances follow how different colors look
+fn oklab(color: Rgb) -> [f32; 3] {
+    let [red, green, blue] = color.map(|channel| {
+        let channel = f32::from(channel) / 255.0;
+        if channel <= 0.040_45 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    // Responses of the long, medium and short cones
+    let [long, medium, short] = [
+        0.412_221_47 * red + 0.536_332_55 * green + 0.051_445_995 * blue,
+        0.211_903_5 * red + 0.680_699_5 * green + 0.107_396_96 * blue,
+        0.088_302_46 * red + 0.281_718_85 * green + 0.629_978_7 * blue,
+    ]
+    .map(f32::cbrt);
+    [
+        0.210_454_26 * long + 0.793_617_8 * medium - 0.004_072_047 * short,
+        1.977_998_5 * long - 2.428_592_2 * medium + 0.450_593_7 * short,
+        0.025_904_037 * long + 0.782_771_77 * medium - 0.808_675_77 * short,
+    ]
+}
+
+/// Index of the palette entry closest to `color`
+pub fn nearest(palette: &[Rgb], color: Rgb) -> usize {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, &entry)| distance(entry, color))
+        .map_or(0, |(index, _)| index)
+}
+
+/// Channel levels of the 6x6x6 cube in the xterm 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Closest entry of the xterm 256 color palette, skipping the first 16
+/// colors since terminals are free to redefine them.
+pub fn xterm256(color: Rgb) -> u8 {
+    let level = |channel: u8| {
+        CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, &level)| level.abs_diff(channel))
+            .map_or(0, |(index, _)| index)
+    };
+    let [r, g, b] = color.map(level);
+    let cube = [CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]];
+
+    #[allow(clippy::cast_possible_truncation)]
+    let step = gray_step((color.iter().map(|&c| u32::from(c)).sum::<u32>() / 3) as u8);
+    let gray = 8 + step * 10;
+
+    if distance([gray; 3], color) < distance(cube, color) {
+        232 + step
+    } else {
+        #[allow(clippy::cast_possible_truncation)]
+        let index = (16 + 36 * r + 6 * g + b) as u8;
+        index
+    }
+}
+
+/// Color of an entry of the xterm 256 color palette
+pub fn xterm256_rgb(index: u8) -> Rgb {
+    match index {
+        0..=15 => ANSI16[usize::from(index)],
+        16..=231 => {
+            let index = usize::from(index - 16);
+            [index / 36, index / 6 % 6, index % 6].map(|level| CUBE_LEVELS[level])
+        }
+        _ => [8 + (index - 232) * 10; 3],
+    }
+}
+
+/// The 16 base colors as xterm draws them, in SGR order with the bright
+/// variants last
+pub const ANSI16: [Rgb; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+/// Closest step of the xterm gray ramp (232-255), which goes from 8 to 238
+/// in steps of 10
+#[inline]
+fn gray_step(value: u8) -> u8 {
+    (value.saturating_sub(3) / 10).min(23)
+}
+
+/// Brightness of a color according to `formula`
+#[allow(clippy::cast_possible_truncation)]
+pub fn brightness(formula: Luminance, color: Rgb) -> u8 {
+    let [r, g, b] = color.map(u32::from);
+    match formula {
+        Luminance::Red => color[0],
+        Luminance::MaxChannel => color[0].max(color[1]).max(color[2]),
+        Luminance::Average => ((r + g + b) / 3) as u8,
+        Luminance::Rec601 => luma(color),
+        Luminance::Rec709 => ((2126 * r + 7152 * g + 722 * b) / 10000) as u8,
+    }
+}
+
+/// The color a terminal ends up showing for `color` in the given mode
+pub fn displayed(mode: ColorMode, color: Rgb) -> Rgb {
+    match mode {
+        ColorMode::Truecolor => color,
+        ColorMode::Ansi256 => xterm256_rgb(xterm256(color)),
+        ColorMode::Ansi16 => ANSI16[nearest(&ANSI16, color)],
+        ColorMode::Gray => [luma(color); 3],
+        ColorMode::Gray256 => [8 + gray_step(luma(color)) * 10; 3],
+    }
+}
+
+/// Rough distance between two neighbouring colors in the given mode
+pub fn color_step(mode: ColorMode) -> f32 {
+    match mode {
+        ColorMode::Truecolor | ColorMode::Gray => 0.0,
+        ColorMode::Gray256 => 10.0,
+        ColorMode::Ansi256 => 40.0,
+        ColorMode::Ansi16 => 128.0,
+    }
+}
+
+/// SGR sequence painting either the foreground or the background with `color`
+pub fn escape(mode: ColorMode, foreground: bool, color: Rgb) -> String {
+    let layer = if foreground { 3 } else { 4 };
+    let [r, g, b] = color;
+
+    match mode {
+        ColorMode::Truecolor => format!("\x1b[{layer}8;2;{r};{g};{b}m"),
+        ColorMode::Ansi256 => format!("\x1b[{layer}8;5;{}m", xterm256(color)),
+        ColorMode::Gray => {
+            let luma = luma(color);
+            format!("\x1b[{layer}8;2;{luma};{luma};{luma}m")
+        }
+        ColorMode::Gray256 => format!("\x1b[{layer}8;5;{}m", 232 + gray_step(luma(color))),
+        ColorMode::Ansi16 => {
+            let index = nearest(&ANSI16, color);
+            // Bright colors live at 90-97 and 100-107
+            let base = if index < 8 {
+                layer * 10
+            } else {
+                layer * 10 + 60
+            };
+            format!("\x1b[{}m", base + index % 8)
+        }
+    }
+}
+
+/// Same as [`escape`], as the color that ends up in the sequence
+pub fn palette_color(mode: ColorMode, color: Rgb) -> Color {
+    match mode {
+        ColorMode::Truecolor => Color::Rgb(color),
+        ColorMode::Ansi256 => Color::Indexed(xterm256(color)),
+        ColorMode::Gray => Color::Rgb([luma(color); 3]),
+        ColorMode::Gray256 => Color::Indexed(232 + gray_step(luma(color))),
+        #[allow(clippy::cast_possible_truncation)]
+        ColorMode::Ansi16 => Color::Indexed(nearest(&ANSI16, color) as u8),
+    }
+}
+
+/// How many escape sequences [`EscapeCache`] holds on to
+const CACHED_ESCAPES: usize = 16;
+
+/// Remembers the most recently used escape sequences, since frames tend to
+/// reuse a handful of colors over and over, especially once quantized.
+pub struct EscapeCache {
+    mode: ColorMode,
+    foreground: bool,
+    entries: VecDeque<(Rgb, String)>,
+}
+
+impl EscapeCache {
+    pub fn new(mode: ColorMode, foreground: bool) -> Self {
+        Self {
+            mode,
+            foreground,
+            entries: VecDeque::with_capacity(CACHED_ESCAPES),
+        }
+    }
+
+    /// Same as [`escape`], moving `color` to the front of the cache
+    pub fn get(&mut self, color: Rgb) -> &str {
+        if let Some(index) = self.entries.iter().position(|(cached, _)| *cached == color) {
+            let entry = self.entries.remove(index).unwrap();
+            self.entries.push_front(entry);
+        } else {
+            if self.entries.len() == CACHED_ESCAPES {
+                self.entries.pop_back();
+            }
+            self.entries
+                .push_front((color, escape(self.mode, self.foreground, color)));
+        }
+
+        &self.entries[0].1
+    }
+}
+
+/// Fully saturated color `position` of the way around the color wheel
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn hue(position: f32) -> [u8; 3] {
+    let sector = position * 6.0;
+    let rising = (sector.fract() * 255.0) as u8;
+    let falling = 255 - rising;
+    match sector as u8 {
+        0 => [255, rising, 0],
+        1 => [falling, 255, 0],
+        2 => [0, 255, rising],
+        3 => [0, falling, 255],
+        4 => [rising, 0, 255],
+        _ => [255, 0, falling],
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    env::args_os,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs::{read_to_string, write},
+    path::{Path, PathBuf},
+};
+
+use clap::{ArgMatches, ValueSource};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{archive::Bapple, cli::cli, error::CompilerError};
+
+/// Flags a config holds: their name in it, their id, and how they're passed
+const FLAGS: [(&str, &str, &str); 28] = [
+    ("size", "frame-size", "--size"),
+    ("fit", "fit", "--fit"),
+    ("fit-mode", "fit-mode", "--fit-mode"),
+    ("cell-aspect", "cell-aspect", "--cell-aspect"),
+    ("style", "style", "--style"),
+    ("charset", "charset", "--charset"),
+    ("thresholds", "thresholds", "--thresholds"),
+    ("two-level", "two-level", "--two-level"),
+    ("threshold", "compression-threshold", "--threshold"),
+    ("threshold-metric", "threshold-metric", "--threshold-metric"),
+    ("colorize", "colorize", "-c"),
+    ("color-mode", "color-mode", "--color-mode"),
+    ("dither", "dither", "--dither"),
+    ("render", "render", "--render"),
+    ("sampling", "sampling", "--sampling"),
+    ("color-sampling", "color-sampling", "--color-sampling"),
+    ("luminance", "luminance", "--luminance"),
+    ("gamma", "gamma", "--gamma"),
+    ("brightness", "brightness", "--brightness"),
+    ("contrast", "contrast", "--contrast"),
+    ("saturation", "saturation", "--saturation"),
+    ("hue", "hue", "--hue"),
+    ("temperature", "temperature", "--temperature"),
+    ("effect", "effect", "--effect"),
+    ("gradient", "gradient", "--gradient"),
+    ("posterize", "posterize", "--posterize"),
+    ("background", "background", "--background"),
+    ("dark-fill", "dark-fill", "--dark-fill"),
+];
+
+/// Conversion settings that can be saved and loaded back, so that a look
+/// can be reused between videos. Values are kept the way they'd be written
+/// on the command line. Archives hold the config they were compiled with in
+/// their metadata too, so they can be compiled again the same way.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AsciiConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fit_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell_aspect: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thresholds: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub two_level: Option<String>,
+    /// Compression threshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold_metric: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub colorize: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dither: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub render: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_sampling: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub luminance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gamma: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contrast: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saturation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hue: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effect: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gradient: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub posterize: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dark_fill: Option<String>,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl AsciiConfig {
+    /// The settings that were given on the command line, leaving out the
+    /// defaults. `--fit` on its own depends on the terminal, so it's left
+    /// out too.
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let mut config = Map::new();
+        for (name, id, _) in FLAGS {
+            if matches.value_source(id) != Some(ValueSource::CommandLine) {
+                continue;
+            }
+            let value = match matches.get_raw(id) {
+                Some(values) if values.len() > 0 => Value::String(
+                    values
+                        .map(|value| value.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+                Some(_) if id == "fit" => continue,
+                _ => Value::Bool(true),
+            };
+            config.insert(name.to_string(), value);
+        }
+        serde_json::from_value(Value::Object(config)).expect("every flag has a field")
+    }
+
+    /// Reads a config saved with `--save-config`, or the one an archive was
+    /// compiled with
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let config = if path
+            .extension()
+            .is_some_and(|extension| extension == "bapple")
+        {
+            Bapple::open(path)?.metadata.config.ok_or_else(|| {
+                CompilerError::InvalidInput(format!(
+                    "{} doesn't say what it was compiled with",
+                    path.display()
+                ))
+            })?
+        } else {
+            read_to_string(path)?
+        };
+        serde_json::from_str(&config)
+            .map_err(|error| format!("{} isn't a valid config: {error}", path.display()).into())
+    }
+
+    /// The settings `asciix calibrate` recommended for the terminal it ran in
+    pub fn from_calibration(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut config = Self::default();
+        for (key, value) in read_to_string(path)?
+            .lines()
+            .filter_map(|line| line.split_once('='))
+        {
+            let value = Some(value.trim().to_string());
+            match key.trim() {
+                "size" => config.fit = value,
+                "threshold" => config.threshold = value,
+                "color_depth" => {
+                    config.color_mode = match value.as_deref() {
+                        Some("256") => Some("ansi256".into()),
+                        Some("16") => Some("ansi16".into()),
+                        _ => None,
+                    };
+                    config.colorize = value.as_deref() != Some("none");
+                }
+                _ => (),
+            }
+        }
+        if config.fit.is_none() {
+            return Err(format!("{} isn't a calibration from asciix", path.display()).into());
+        }
+        // The threshold only matters to colors
+        if !config.colorize {
+            config.threshold = None;
+        }
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.to_string() == "{}"
+    }
+
+    /// Command line flags that stand for the config
+    fn args(&self) -> Vec<String> {
+        let Ok(Value::Object(config)) = serde_json::to_value(self) else {
+            unreachable!("configs are objects");
+        };
+        FLAGS
+            .iter()
+            .filter_map(|&(name, _, flag)| match config.get(name)? {
+                Value::String(value) => Some(format!("{flag}={value}")),
+                _ => Some(flag.to_string()),
+            })
+            .collect()
+    }
+}
+
+/// One line of JSON, to fit in an archive's metadata
+impl Display for AsciiConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&json)
+    }
+}
+
+/// Parses the command line again with the flags of `--from-calibration` and
+/// `--config` in front, if they were given. Flags given on the command line
+/// win over the config's, which win over the calibration's.
+pub fn apply_config(matches: ArgMatches) -> Result<ArgMatches, Box<dyn Error>> {
+    let calibration = matches
+        .get_one::<PathBuf>("from-calibration")
+        .map(|path| AsciiConfig::from_calibration(path))
+        .transpose()?;
+    let config = matches
+        .get_one::<PathBuf>("config")
+        .map(|path| AsciiConfig::load(path))
+        .transpose()?;
+    if calibration.is_none() && config.is_none() {
+        return Ok(matches);
+    }
+
+    let mut args = args_os();
+    let program = args.next().unwrap_or_default();
+    let args = [program]
+        .into_iter()
+        .chain(
+            calibration
+                .iter()
+                .chain(&config)
+                .flat_map(AsciiConfig::args)
+                .map(Into::into),
+        )
+        .chain(args);
+    Ok(cli()
+        .args_override_self(true)
+        .try_get_matches_from(args)
+        .unwrap_or_else(|error| error.exit()))
+}
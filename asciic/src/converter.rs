@@ -0,0 +1,90 @@
+use std::{fs::File, io::BufReader, mem::take, path::Path};
+
+use image::{
+    codecs::png::PngDecoder, io::Reader, ColorType, DynamicImage, ImageBuffer, ImageDecoder,
+    ImageError,
+};
+
+use crate::{primitives::Options, render_both, render_image};
+
+/// Converts frame after frame with the same settings, holding on to its
+/// buffers in between, so that long videos don't allocate a few megabytes
+/// for every frame. Each thread keeps one of its own.
+pub struct AsciiConverter {
+    pub options: Options,
+    /// The last frame converted
+    pub frame: String,
+    /// The last frame [`AsciiConverter::convert_both`] converted, without colors
+    pub plain: String,
+    /// Decoded pixels, lent to each image and taken back once it's converted
+    pixels: Vec<u8>,
+}
+
+impl AsciiConverter {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            frame: String::new(),
+            plain: String::new(),
+            pixels: Vec::new(),
+        }
+    }
+
+    pub fn convert(&mut self, image: &DynamicImage) -> &str {
+        render_image(image, self.options, &mut self.frame);
+        &self.frame
+    }
+
+    /// Converts `image` with colors and without at once, sharing the work
+    /// that goes into both, like resizing and picking characters
+    pub fn convert_both(&mut self, image: &DynamicImage) -> (&str, &str) {
+        render_both(image, self.options, &mut self.frame, &mut self.plain);
+        (&self.frame, &self.plain)
+    }
+
+    /// Decodes and converts the image at `path`
+    pub fn convert_file(&mut self, path: &Path) -> Result<&str, ImageError> {
+        let image = self.decode(path)?;
+        render_image(&image, self.options, &mut self.frame);
+        self.pixels = image.into_bytes();
+        Ok(&self.frame)
+    }
+
+    /// ffmpeg hands frames over as 8 bit PNGs, which get decoded straight
+    /// into the pixels of the last one. Anything else goes through
+    /// [`Reader`], into a buffer of its own.
+    fn decode(&mut self, path: &Path) -> Result<DynamicImage, ImageError> {
+        if path.extension().is_none_or(|extension| extension != "png") {
+            return Reader::open(path)?.decode();
+        }
+        let decoder = PngDecoder::new(BufReader::new(File::open(path)?))?;
+        let (width, height) = decoder.dimensions();
+        let color = decoder.color_type();
+        if !matches!(
+            color,
+            ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8
+        ) {
+            return DynamicImage::from_decoder(decoder);
+        }
+
+        let mut pixels = take(&mut self.pixels);
+        pixels.resize(
+            usize::try_from(decoder.total_bytes()).unwrap_or(usize::MAX),
+            0,
+        );
+        decoder.read_image(&mut pixels)?;
+        let image = match color {
+            ColorType::L8 => {
+                ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8)
+            }
+            ColorType::La8 => {
+                ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8)
+            }
+            ColorType::Rgb8 => {
+                ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8)
+            }
+            _ => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8),
+        };
+        Ok(image.expect("the buffer was sized by the decoder"))
+    }
+}
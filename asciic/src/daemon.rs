@@ -0,0 +1,197 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env::current_exe,
+    error::Error,
+    fmt::Write as _,
+    fs::{create_dir_all, read_dir},
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+    thread::{sleep, spawn},
+    time::{Duration, Instant, SystemTime},
+};
+
+use clap::ArgMatches;
+
+use crate::{error::CompilerError, primitives::Preset};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Files that get picked up, anything else dropped in the folder is ignored
+const VIDEO_EXTENSIONS: [&str; 10] = [
+    "mp4", "mkv", "webm", "mov", "avi", "m4v", "flv", "wmv", "gif", "y4m",
+];
+
+impl Preset {
+    /// Compile flags the preset stands for
+    fn flags(self) -> &'static [&'static str] {
+        match self {
+            Self::Mono => &[],
+            Self::Color => &["-c"],
+            // Discord's ```ansi code blocks only show the 16 base colors
+            Self::Discord => &["-c", "--color-mode", "ansi16", "-s", "80x24"],
+        }
+    }
+}
+
+/// What the status endpoint reports
+#[derive(Default)]
+struct Status {
+    compiled: usize,
+    failed: Vec<String>,
+    current: Option<String>,
+    waiting: usize,
+}
+
+/// Compiles every video that shows up in `--watch` into `--out`, one at a
+/// time, until stopped. Each compile runs in its own asciic process, so a bad
+/// video only takes its own compile down. Videos are left alone until their
+/// size stops changing, so ones still being copied in don't get picked up
+/// half written, and the ones that already have an output are skipped.
+pub fn daemon(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let watch = matches.get_one::<PathBuf>("watch").unwrap();
+    let out = matches.get_one::<PathBuf>("out").unwrap();
+    let preset = *matches.get_one::<Preset>("preset").unwrap();
+    let extra = matches
+        .get_many::<String>("flags")
+        .map(|flags| flags.cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    if !watch.is_dir() {
+        return Err(
+            CompilerError::InvalidInput(format!("{} isn't a directory", watch.display())).into(),
+        );
+    }
+    create_dir_all(out)?;
+    let asciic = current_exe()?;
+
+    let status = Arc::new(Mutex::new(Status::default()));
+    if let Some(&address) = matches.get_one::<SocketAddr>("status") {
+        let listener = TcpListener::bind(address)?;
+        let status = Arc::clone(&status);
+        spawn(move || serve_status(&listener, &status));
+        println!(">> Status available at http://{address}");
+    }
+    println!(
+        ">> Watching {} for videos, compiling into {}",
+        watch.display(),
+        out.display()
+    );
+
+    let mut sizes: HashMap<PathBuf, _> = HashMap::new();
+    let mut failed = HashSet::new();
+    loop {
+        let videos = new_videos(watch, out, &failed)?;
+        let ready = videos
+            .iter()
+            .filter(|&(path, size)| sizes.get(path) == Some(size))
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        status.lock().unwrap().waiting = videos.len();
+        sizes = videos;
+
+        for video in ready {
+            let name = video.file_name().unwrap().to_string_lossy().into_owned();
+            status.lock().unwrap().current = Some(name.clone());
+            println!(">> Compiling {name}");
+
+            let start = Instant::now();
+            let compiled = Command::new(&asciic)
+                .args(preset.flags())
+                .arg(&video)
+                .arg(output_path(out, &video))
+                .args(&extra)
+                .stdout(Stdio::null())
+                .status();
+
+            let mut status = status.lock().unwrap();
+            status.current = None;
+            status.waiting = status.waiting.saturating_sub(1);
+            match compiled {
+                Ok(exit) if exit.success() => {
+                    status.compiled += 1;
+                    println!(">> Compiled {name} in {:.1?}", start.elapsed());
+                }
+                result => {
+                    let reason =
+                        result.map_or_else(|error| error.to_string(), |exit| exit.to_string());
+                    eprintln!("WARN: Couldn't compile {name}: {reason}");
+                    status.failed.push(name);
+                    failed.insert(video);
+                }
+            }
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
+
+/// Videos in `watch` without an output yet, with their size and when they
+/// last changed
+fn new_videos(
+    watch: &Path,
+    out: &Path,
+    failed: &HashSet<PathBuf>,
+) -> Result<HashMap<PathBuf, (u64, SystemTime)>, Box<dyn Error>> {
+    let mut videos = HashMap::new();
+    for entry in read_dir(watch)? {
+        let path = entry?.path();
+        let is_video = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                VIDEO_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+            });
+        if !is_video || failed.contains(&path) || output_path(out, &path).exists() {
+            continue;
+        }
+        // Files can go away between listing them and looking them up
+        if let Ok(metadata) = path.metadata() {
+            videos.insert(path, (metadata.len(), metadata.modified()?));
+        }
+    }
+    Ok(videos)
+}
+
+fn output_path(out: &Path, video: &Path) -> PathBuf {
+    out.join(video.file_stem().unwrap_or_default())
+        .with_extension("bapple")
+}
+
+/// Answers every connection with a plain text summary, whatever was asked
+fn serve_status(listener: &TcpListener, status: &Mutex<Status>) {
+    for mut stream in listener.incoming().flatten() {
+        // The request itself doesn't matter, but clients expect it to be read
+        stream.set_read_timeout(Some(Duration::from_secs(1))).ok();
+        let mut request = Vec::new();
+        let mut chunk = [0; 1024];
+        while !request.windows(4).any(|end| end == b"\r\n\r\n") && request.len() < 8192 {
+            match stream.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => request.extend_from_slice(&chunk[..read]),
+            }
+        }
+
+        let body = {
+            let status = status.lock().unwrap();
+            let mut body = String::new();
+            writeln!(
+                body,
+                "compiling: {}",
+                status.current.as_deref().unwrap_or("-")
+            )
+            .unwrap();
+            writeln!(body, "waiting: {}", status.waiting).unwrap();
+            writeln!(body, "compiled: {}", status.compiled).unwrap();
+            writeln!(body, "failed: {}", status.failed.join(", ")).unwrap();
+            body
+        };
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .ok();
+    }
+}
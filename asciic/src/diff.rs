@@ -0,0 +1,131 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::ArgMatches;
+
+use crate::archive::Bapple;
+use asciicore::ansi::{cells, render_row, Cell};
+use asciicore::frame::decompress;
+
+/// Compares two archives frame by frame, to see what an encoder change
+/// actually did to the output
+pub fn diff(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let (path_a, path_b) = (
+        matches.get_one::<PathBuf>("a").unwrap(),
+        matches.get_one::<PathBuf>("b").unwrap(),
+    );
+    let (a, b) = (Bapple::open(path_a)?, Bapple::open(path_b)?);
+    a.metadata.require_whole_frames("diff")?;
+    b.metadata.require_whole_frames("diff")?;
+    let shown = matches
+        .get_many::<usize>("show")
+        .unwrap_or_default()
+        .copied()
+        .collect::<Vec<_>>();
+
+    println!("frame\ta (bytes)\tb (bytes)\tdelta\tchanged cells");
+
+    let total = a.frames.len().max(b.frames.len());
+    let mut changed_sum = 0.0;
+    for index in 0..total {
+        let (frame_a, frame_b) = (a.frames.get(index), b.frames.get(index));
+        let size = |frame: Option<&Vec<u8>>| frame.map_or(0, Vec::len);
+        #[allow(clippy::cast_possible_wrap)]
+        let delta = size(frame_b) as i64 - size(frame_a) as i64;
+
+        let (cells_a, cells_b) = (decode(frame_a)?, decode(frame_b)?);
+        let changed = changed_cells(&cells_a, &cells_b);
+        changed_sum += changed;
+
+        println!(
+            "{}\t{}\t{}\t{delta:+}\t{:.1}%",
+            index + 1,
+            frame_a.map_or_else(|| "-".to_string(), |frame| frame.len().to_string()),
+            frame_b.map_or_else(|| "-".to_string(), |frame| frame.len().to_string()),
+            changed * 100.0
+        );
+
+        if shown.contains(&(index + 1)) {
+            side_by_side(&cells_a, &cells_b);
+        }
+    }
+
+    let bytes = |bapple: &Bapple| bapple.frames.iter().map(Vec::len).sum::<usize>();
+    #[allow(clippy::cast_precision_loss)]
+    let average = changed_sum / total.max(1) as f64;
+    println!(
+        ">> {} frames / {} bytes in {}\n\
+        >> {} frames / {} bytes in {}\n\
+        >> {:.1}% of the cells changed on average",
+        a.frames.len(),
+        bytes(&a),
+        path_a.display(),
+        b.frames.len(),
+        bytes(&b),
+        path_b.display(),
+        average * 100.0
+    );
+    Ok(())
+}
+
+fn decode(frame: Option<&Vec<u8>>) -> Result<Vec<Vec<Cell>>, Box<dyn Error>> {
+    let Some(frame) = frame else {
+        return Ok(Vec::new());
+    };
+    Ok(cells(&String::from_utf8_lossy(&decompress(frame)?)))
+}
+
+/// Fraction of cells that differ in character or color. Cells that only
+/// exist in one of the frames count as changed.
+fn changed_cells(a: &[Vec<Cell>], b: &[Vec<Cell>]) -> f64 {
+    let mut total = 0;
+    let mut changed = 0;
+
+    for row in 0..a.len().max(b.len()) {
+        let (row_a, row_b) = (a.get(row), b.get(row));
+        let len = |row: Option<&Vec<Cell>>| row.map_or(0, Vec::len);
+        let width = len(row_a).max(len(row_b));
+
+        total += width;
+        changed += (0..width)
+            .filter(|&x| row_a.and_then(|row| row.get(x)) != row_b.and_then(|row| row.get(x)))
+            .count();
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    if total == 0 {
+        0.0
+    } else {
+        changed as f64 / total as f64
+    }
+}
+
+/// Prints both frames next to each other, followed by a map of what changed
+fn side_by_side(a: &[Vec<Cell>], b: &[Vec<Cell>]) {
+    let width = |frame: &[Vec<Cell>]| frame.iter().map(Vec::len).max().unwrap_or(0);
+    let (width_a, width_b) = (width(a), width(b));
+
+    for row in 0..a.len().max(b.len()) {
+        let (row_a, row_b) = (a.get(row), b.get(row));
+        let changes = (0..width_a.max(width_b))
+            .map(|x| {
+                if row_a.and_then(|row| row.get(x)) == row_b.and_then(|row| row.get(x)) {
+                    '.'
+                } else {
+                    '#'
+                }
+            })
+            .collect::<String>();
+
+        println!(
+            "{} | {} | {changes}",
+            render(row_a, width_a),
+            render(row_b, width_b)
+        );
+    }
+}
+
+/// Draws a row of cells back, padded with spaces up to `width`
+fn render(row: Option<&Vec<Cell>>, width: usize) -> String {
+    let row = row.map_or(&[][..], Vec::as_slice);
+    render_row(row) + &" ".repeat(width - row.len())
+}
@@ -0,0 +1,58 @@
+use image::RgbImage;
+
+use crate::color::Rgb;
+
+/// Error diffusion dithering: rounds every pixel with `quantize` and spreads
+/// what got lost onto the neighbours that haven't been visited yet, so that
+/// gradients survive being reduced to a handful of colors.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn floyd_steinberg(image: &mut RgbImage, quantize: impl Fn(Rgb) -> Rgb) {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut pixels = image
+        .pixels()
+        .map(|pixel| pixel.0.map(f32::from))
+        .collect::<Vec<_>>();
+
+    for y in 0..height {
+        for x in 0..width {
+            let old = pixels[y * width + x].map(|c| c.round().clamp(0.0, 255.0) as u8);
+            let new = quantize(old);
+            image.put_pixel(x as u32, y as u32, image::Rgb(new));
+
+            let error = [0, 1, 2].map(|c| pixels[y * width + x][c] - f32::from(new[c]));
+            let mut spread = |x: usize, y: usize, weight: f32| {
+                if x < width && y < height {
+                    for (channel, error) in pixels[y * width + x].iter_mut().zip(error) {
+                        *channel += error * weight;
+                    }
+                }
+            };
+
+            spread(x + 1, y, 7.0 / 16.0);
+            if x > 0 {
+                spread(x - 1, y + 1, 3.0 / 16.0);
+            }
+            spread(x, y + 1, 5.0 / 16.0);
+            spread(x + 1, y + 1, 1.0 / 16.0);
+        }
+    }
+}
+
+/// 4x4 Bayer threshold map
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Ordered dithering: nudges every pixel by a fixed pattern before rounding
+/// it with `quantize`. Unlike error diffusion, a pixel only depends on its
+/// own value and position, so similar frames stay stable from one to the next.
+/// `spread` is roughly the distance between two neighbouring colors.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn bayer(image: &mut RgbImage, quantize: impl Fn(Rgb) -> Rgb, spread: f32) {
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let threshold = f32::from(BAYER[y as usize % 4][x as usize % 4]);
+        let offset = spread * ((threshold + 0.5) / 16.0 - 0.5);
+        let nudged = pixel
+            .0
+            .map(|c| (f32::from(c) + offset).round().clamp(0.0, 255.0) as u8);
+        pixel.0 = quantize(nudged);
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use asciicore::termcaps::{probe, TermCaps};
+use clap::ArgMatches;
+use tempfile::{tempfile_in, TempDir};
+
+use crate::error::CompilerError;
+
+enum Check {
+    Ok(String),
+    Warn(String, &'static str),
+    Fail(String, &'static str),
+}
+
+/// Looks for the usual environment problems and prints how to fix them
+pub fn doctor(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let output_dir = matches.get_one::<PathBuf>("output-dir").unwrap();
+
+    let caps = probe();
+    let checks = [
+        ("terminal colors", check_colors(caps)),
+        ("terminal size", check_size(caps)),
+        ("terminal features", check_features(caps)),
+        ("ffmpeg", check_binary("ffmpeg", "-version", true)),
+        ("ffprobe", check_binary("ffprobe", "-version", true)),
+        ("mpv", check_binary("mpv", "--version", false)),
+        ("temporary files", check_temp_dir()),
+        ("output directory", check_writable(output_dir)),
+    ];
+
+    let mut failed = 0;
+    for (name, check) in checks {
+        match check {
+            Check::Ok(detail) => println!("[ OK ] {name}: {detail}"),
+            Check::Warn(detail, fix) => println!("[WARN] {name}: {detail}\n       -> {fix}"),
+            Check::Fail(detail, fix) => {
+                failed += 1;
+                println!("[FAIL] {name}: {detail}\n       -> {fix}");
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(CompilerError::ChecksFailed(failed).into());
+    }
+    Ok(())
+}
+
+fn check_colors(caps: TermCaps) -> Check {
+    if caps.truecolor {
+        Check::Ok("24-bit colors are supported".into())
+    } else {
+        Check::Warn(
+            "the terminal doesn't seem to support 24-bit colors".into(),
+            "Colored archives may look wrong, try a terminal like kitty, alacritty or wezterm",
+        )
+    }
+}
+
+fn check_size(caps: TermCaps) -> Check {
+    match caps.size {
+        Some((width, height)) if width >= 216 && height >= 56 => {
+            Check::Ok(format!("{width}x{height}"))
+        }
+        Some((width, height)) => Check::Warn(
+            format!("{width}x{height} is smaller than the default frame size (216x56)"),
+            "Zoom out, or compile with a smaller --size",
+        ),
+        None => Check::Warn(
+            "couldn't be detected".into(),
+            "Run this from an interactive terminal",
+        ),
+    }
+}
+
+fn check_features(caps: TermCaps) -> Check {
+    let cell = caps.cell_pixels.map_or_else(
+        || "unknown cell size".to_string(),
+        |(width, height)| format!("{width}x{height}px cells"),
+    );
+    let images = match (caps.kitty_graphics, caps.sixel) {
+        (true, true) => ", kitty graphics, sixels",
+        (true, false) => ", kitty graphics",
+        (false, true) => ", sixels",
+        (false, false) => "",
+    };
+
+    if caps.synchronized_output {
+        Check::Ok(format!("synchronized output, {cell}{images}"))
+    } else {
+        Check::Warn(
+            format!("no synchronized output, {cell}{images}"),
+            "Playback may tear on large frames, try a terminal that supports mode 2026",
+        )
+    }
+}
+
+fn check_binary(binary: &str, version_flag: &str, required: bool) -> Check {
+    let fix = if required {
+        "Install ffmpeg (it ships ffprobe too) and make sure it's in your PATH"
+    } else {
+        "Install mpv if you want asciix to play audio"
+    };
+
+    match Command::new(binary).arg(version_flag).output() {
+        Ok(output) if output.status.success() => Check::Ok(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        ),
+        _ if required => Check::Fail("not found".into(), fix),
+        _ => Check::Warn("not found".into(), fix),
+    }
+}
+
+fn check_temp_dir() -> Check {
+    // asciic keeps its frames in a temporary directory inside of the current one
+    match TempDir::new_in(".") {
+        Ok(_) => Check::Ok("the current directory is writable".into()),
+        Err(error) => Check::Fail(
+            error.to_string(),
+            "Run asciic from a directory you can write to",
+        ),
+    }
+}
+
+fn check_writable(dir: &Path) -> Check {
+    match tempfile_in(dir) {
+        Ok(_) => Check::Ok(format!("{} is writable", dir.display())),
+        Err(error) => Check::Fail(
+            format!("{}: {error}", dir.display()),
+            "Pick another output path or fix its permissions",
+        ),
+    }
+}
@@ -0,0 +1,70 @@
+use image::{imageops::FilterType, DynamicImage, GrayImage};
+
+use crate::primitives::OutputSize;
+
+/// Pixels sampled per cell along each axis, so that thin outlines survive
+/// the downscale
+const SAMPLES: u32 = 2;
+
+/// Runs a Sobel filter over the image and returns, for each cell of `size`,
+/// a stroke following the edge that goes through it, or `None` when the
+/// gradient is weaker than `threshold`. The result is in row-major order.
+pub fn edge_glyphs(image: &DynamicImage, size: OutputSize, threshold: u16) -> Vec<Option<char>> {
+    let luma = image
+        .resize_exact(size.0 * SAMPLES, size.1 * SAMPLES, FilterType::Triangle)
+        .to_luma8();
+
+    let mut strokes = Vec::with_capacity((size.0 * size.1) as usize);
+    for row in 0..size.1 {
+        for column in 0..size.0 {
+            let (mut gx, mut gy) = (0, 0);
+            for y in 0..SAMPLES {
+                for x in 0..SAMPLES {
+                    let (dx, dy) = sobel(&luma, column * SAMPLES + x, row * SAMPLES + y);
+                    gx += dx;
+                    gy += dy;
+                }
+            }
+
+            #[allow(clippy::cast_possible_wrap)]
+            let samples = (SAMPLES * SAMPLES) as i32;
+            strokes.push(stroke(gx / samples, gy / samples, threshold));
+        }
+    }
+    strokes
+}
+
+/// Horizontal and vertical gradients at a pixel, clamping at the borders
+#[allow(clippy::cast_possible_wrap)]
+fn sobel(luma: &GrayImage, x: u32, y: u32) -> (i32, i32) {
+    let at = |dx: i32, dy: i32| {
+        let x = (x as i32 + dx).clamp(0, luma.width() as i32 - 1);
+        let y = (y as i32 + dy).clamp(0, luma.height() as i32 - 1);
+        #[allow(clippy::cast_sign_loss)]
+        let pixel = luma.get_pixel(x as u32, y as u32).0[0];
+        i32::from(pixel)
+    };
+
+    let gx = at(1, -1) + 2 * at(1, 0) + at(1, 1) - at(-1, -1) - 2 * at(-1, 0) - at(-1, 1);
+    let gy = at(-1, 1) + 2 * at(0, 1) + at(1, 1) - at(-1, -1) - 2 * at(0, -1) - at(1, -1);
+    (gx, gy)
+}
+
+/// Character running along the edge, which is perpendicular to the gradient.
+/// Horizontal edges use `_` when the brighter side is above, and `-` otherwise.
+#[allow(clippy::cast_precision_loss)]
+fn stroke(gx: i32, gy: i32, threshold: u16) -> Option<char> {
+    if gx.pow(2) + gy.pow(2) < i32::from(threshold).pow(2) {
+        return None;
+    }
+
+    // Angle of the gradient, folded into 0..180 since edges have no direction
+    let angle = (gy as f32).atan2(gx as f32).to_degrees().rem_euclid(180.0);
+    Some(match angle {
+        a if !(22.5..157.5).contains(&a) => '|',
+        a if a < 67.5 => '/',
+        a if a < 112.5 && gy < 0 => '_',
+        a if a < 112.5 => '-',
+        _ => '\\',
+    })
+}
@@ -0,0 +1,94 @@
+use std::{
+    error::Error,
+    fmt::{self, Write},
+    io,
+};
+
+use image::ImageError;
+
+use crate::primitives::ErrorFormat;
+
+/// Failures that wrappers around asciic may want to tell apart. Every kind
+/// gets its own exit code, see the error catalog in the README.
+#[derive(Debug)]
+pub enum CompilerError {
+    /// A program asciic depends on isn't installed
+    MissingTool(String),
+    /// ffmpeg or ffprobe exited with an error
+    ToolFailed(String),
+    /// ffmpeg stopped making progress, even after being retried
+    ToolStalled(String),
+    /// Input that made it past argument parsing, but can't be used
+    InvalidInput(String),
+    /// Some of the images passed with -i couldn't be compiled
+    ImagesFailed(usize),
+    /// `asciic doctor` found problems
+    ChecksFailed(usize),
+}
+
+impl fmt::Display for CompilerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTool(tool) => write!(f, "{tool} was not found, is it installed?"),
+            Self::ToolFailed(message)
+            | Self::ToolStalled(message)
+            | Self::InvalidInput(message) => f.write_str(message),
+            Self::ImagesFailed(count) => write!(f, "{count} image(s) failed to compile"),
+            Self::ChecksFailed(count) => write!(f, "{count} check(s) failed"),
+        }
+    }
+}
+
+impl Error for CompilerError {}
+
+/// Stable name and exit code of an error. Errors asciic doesn't know
+/// anything about exit with 1, clap's own usage errors already exit with 2.
+pub fn classify(error: &(dyn Error + 'static)) -> (&'static str, i32) {
+    if let Some(error) = error.downcast_ref::<CompilerError>() {
+        return match error {
+            CompilerError::InvalidInput(_) => ("invalid_input", 4),
+            CompilerError::ImagesFailed(_) => ("images_failed", 6),
+            CompilerError::MissingTool(_) => ("missing_tool", 10),
+            CompilerError::ToolFailed(_) => ("tool_failed", 11),
+            CompilerError::ToolStalled(_) => ("tool_stalled", 12),
+            CompilerError::ChecksFailed(_) => ("checks_failed", 20),
+        };
+    }
+
+    if error.is::<io::Error>() {
+        ("io", 3)
+    } else if error.is::<ImageError>() {
+        ("image", 5)
+    } else {
+        ("other", 1)
+    }
+}
+
+/// Prints `error` to stderr and returns the code to exit with
+pub fn report(error: &(dyn Error + 'static), format: ErrorFormat) -> i32 {
+    let (kind, code) = classify(error);
+
+    match format {
+        ErrorFormat::Human => eprintln!("Error: {error}"),
+        ErrorFormat::Json => eprintln!(
+            "{{\"error\":\"{kind}\",\"exit_code\":{code},\"message\":\"{}\"}}",
+            escape_json(&error.to_string())
+        ),
+    }
+
+    code
+}
+
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
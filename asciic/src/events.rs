@@ -0,0 +1,58 @@
+use std::{error::Error, fmt::Write, fs::read_to_string, path::Path};
+
+use crate::error::CompilerError;
+
+/// A key/value pair the player hands out once its frame shows up, to cue
+/// lights, sound effects or anything else that follows the animation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Counting from 1, like frame names
+    pub frame: usize,
+    pub key: String,
+    pub value: String,
+}
+
+/// Reads an event track made of `FRAME KEY=VALUE` lines. Blank lines and
+/// lines starting with `#` are skipped.
+pub fn read_events(path: &Path) -> Result<Vec<Event>, Box<dyn Error>> {
+    let mut events = Vec::new();
+
+    for (number, line) in read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let event = parse_event(line).ok_or_else(|| {
+            CompilerError::InvalidInput(format!(
+                "Line {} of {} should look like `FRAME KEY=VALUE`, with frames counting from 1",
+                number + 1,
+                path.display()
+            ))
+        })?;
+        events.push(event);
+    }
+
+    events.sort_by_key(|event| event.frame);
+    Ok(events)
+}
+
+pub fn parse_event(line: &str) -> Option<Event> {
+    let (frame, pair) = line.split_once(char::is_whitespace)?;
+    let (key, value) = pair.split_once('=')?;
+    let key = key.trim();
+
+    Some(Event {
+        frame: frame.parse().ok().filter(|&frame| frame > 0)?,
+        key: (!key.is_empty()).then(|| key.to_string())?,
+        value: value.to_string(),
+    })
+}
+
+/// The `events` entry of an archive, in the same format [`read_events`] takes
+pub fn write_events(events: &[Event]) -> String {
+    let mut track = String::new();
+    for event in events {
+        writeln!(track, "{} {}={}", event.frame, event.key, event.value).unwrap();
+    }
+    track
+}
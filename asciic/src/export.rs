@@ -0,0 +1,174 @@
+use std::fmt::Write;
+
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    color::{xterm256_rgb, Rgb, ANSI16},
+    font::{ink, GLYPH_HEIGHT, GLYPH_WIDTH},
+};
+use asciicore::ansi::{AsciiFrame, Cell, Color, Pen};
+
+/// The frame as a `<pre>` block, with a styled `<span>` for each run of cells
+/// that share their colors. Cells in the terminal's own colors are left
+/// unstyled, so that they take the page's colors instead.
+pub fn html(frame: &AsciiFrame) -> String {
+    let mut html = String::from("<pre style=\"font-family: monospace; line-height: 1.2\">\n");
+
+    for row in frame.rows() {
+        for run in runs(row) {
+            let text = escape_markup(&run.iter().map(|cell| cell.ch).collect::<String>());
+            let Pen { fg, bg } = run[0].pen;
+
+            let mut style = String::new();
+            if let Some(fg) = fg {
+                write!(style, "color:{};", hex(fg)).unwrap();
+            }
+            if let Some(bg) = bg {
+                write!(style, "background-color:{};", hex(bg)).unwrap();
+            }
+
+            if style.is_empty() {
+                html.push_str(&text);
+            } else {
+                write!(html, "<span style=\"{style}\">{text}</span>").unwrap();
+            }
+        }
+        html.push('\n');
+    }
+
+    html.push_str("</pre>\n");
+    html
+}
+
+/// Cell size in SVG user units. Each run is stretched to its cells with
+/// `textLength`, so that fonts with other proportions can't skew the grid.
+const SVG_CELL: (usize, usize) = (10, 20);
+
+/// The frame as an SVG image: a `<text>` for each row, with a `<tspan>` for
+/// each run of cells sharing a foreground and a `<rect>` behind each run
+/// sharing a background. Default colors are left to whatever embeds it.
+pub fn svg(frame: &AsciiFrame) -> String {
+    let (cell_width, cell_height) = SVG_CELL;
+    let (width, height) = (frame.width * cell_width, frame.height * cell_height);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+        viewBox=\"0 0 {width} {height}\" font-family=\"monospace\" font-size=\"{}\" \
+        xml:space=\"preserve\">\n",
+        cell_height * 4 / 5
+    );
+
+    for (y, row) in frame.rows().enumerate() {
+        let top = y * cell_height;
+        let mut x = 0;
+        for run in row.chunk_by(|a, b| a.pen.bg == b.pen.bg) {
+            if let Some(bg) = run[0].pen.bg {
+                writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{top}\" width=\"{}\" height=\"{cell_height}\" fill=\"{}\"/>",
+                    x * cell_width,
+                    run.len() * cell_width,
+                    hex(bg)
+                )
+                .unwrap();
+            }
+            x += run.len();
+        }
+
+        let mut spans = String::new();
+        let mut x = 0;
+        for run in row.chunk_by(|a, b| a.pen.fg == b.pen.fg) {
+            let text: String = run.iter().map(|cell| cell.ch).collect();
+            if !text.trim().is_empty() {
+                write!(
+                    spans,
+                    "<tspan x=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\"",
+                    x * cell_width,
+                    run.len() * cell_width
+                )
+                .unwrap();
+                if let Some(fg) = run[0].pen.fg {
+                    write!(spans, " fill=\"{}\"", hex(fg)).unwrap();
+                }
+                write!(spans, ">{}</tspan>", escape_markup(&text)).unwrap();
+            }
+            x += run.len();
+        }
+        if !spans.is_empty() {
+            // Baseline a fifth of the way up from the bottom of the cell
+            writeln!(
+                svg,
+                "<text y=\"{}\">{spans}</text>",
+                top + cell_height * 4 / 5
+            )
+            .unwrap();
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Draws the frame with the embedded 8x13 font, one glyph per cell. Cells
+/// without a background stay transparent, and text without a foreground
+/// takes the usual light gray.
+#[allow(clippy::cast_possible_truncation)]
+pub fn render_to_image(frame: &AsciiFrame) -> RgbaImage {
+    let mut image = RgbaImage::new(
+        frame.width as u32 * GLYPH_WIDTH,
+        frame.height as u32 * GLYPH_HEIGHT,
+    );
+
+    for (row, cells) in frame.rows().enumerate() {
+        for (column, cell) in cells.iter().enumerate() {
+            let fg = cell.pen.fg.map_or(ANSI16[7], rgb);
+            let bg = cell.pen.bg.map_or(Rgba([0; 4]), |bg| {
+                let [r, g, b] = rgb(bg);
+                Rgba([r, g, b, 255])
+            });
+            let (x0, y0) = (column as u32 * GLYPH_WIDTH, row as u32 * GLYPH_HEIGHT);
+
+            for y in 0..GLYPH_HEIGHT {
+                for x in 0..GLYPH_WIDTH {
+                    let pixel = if ink(cell.ch, x, y) {
+                        Rgba([fg[0], fg[1], fg[2], 255])
+                    } else {
+                        bg
+                    };
+                    image.put_pixel(x0 + x, y0 + y, pixel);
+                }
+            }
+        }
+    }
+    image
+}
+
+/// Splits a row wherever the colors change
+fn runs(row: &[Cell]) -> impl Iterator<Item = &[Cell]> {
+    row.chunk_by(|a, b| a.pen == b.pen)
+}
+
+/// `#rrggbb`, with palette entries as xterm draws them
+fn hex(color: Color) -> String {
+    let [r, g, b] = rgb(color);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn rgb(color: Color) -> Rgb {
+    match color {
+        Color::Indexed(index) => xterm256_rgb(index),
+        Color::Rgb(rgb) => rgb,
+    }
+}
+
+fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
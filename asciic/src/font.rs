@@ -0,0 +1,69 @@
+use crate::quadrant::BLOCKS;
+
+/// The public domain 8x13 font from xorg's misc-misc fonts, as a 1bpp bitmap
+/// of 16 glyphs per row, starting at ' ' and ending at '~'
+const FONT: &[u8] = include_bytes!("../assets/font_8x13.raw");
+const GLYPHS_PER_ROW: usize = 16;
+
+pub const GLYPH_WIDTH: u32 = 8;
+pub const GLYPH_HEIGHT: u32 = 13;
+
+/// Rows of the glyph for `c`, most significant bit being the leftmost pixel.
+/// Anything outside of printable ASCII is drawn as '?'.
+pub fn glyph(c: char) -> [u8; GLYPH_HEIGHT as usize] {
+    let index = if (' '..='~').contains(&c) {
+        c as usize - 0x20
+    } else {
+        '?' as usize - 0x20
+    };
+    let (row, column) = (index / GLYPHS_PER_ROW, index % GLYPHS_PER_ROW);
+
+    let mut rows = [0; GLYPH_HEIGHT as usize];
+    for (y, bits) in rows.iter_mut().enumerate() {
+        *bits = FONT[(row * GLYPH_HEIGHT as usize + y) * GLYPHS_PER_ROW + column];
+    }
+    rows
+}
+
+/// Whether the pixel at (`x`, `y`) of a glyph is lit
+#[inline]
+pub fn is_lit(rows: &[u8; GLYPH_HEIGHT as usize], x: u32, y: u32) -> bool {
+    rows[y as usize] & (0x80 >> x) != 0
+}
+
+/// Whether [`ink`] knows what `c` looks like, instead of falling back to '?'
+pub fn can_draw(c: char) -> bool {
+    (' '..='~').contains(&c)
+        || BLOCKS.contains(&c)
+        || matches!(c, '░' | '▒' | '▓' | '\u{2800}'..='\u{28ff}')
+}
+
+/// Whether the pixel at (`x`, `y`) of the cell showing `c` is lit. Block,
+/// shade and braille characters are drawn from their shapes, since the font
+/// only covers ASCII.
+pub fn ink(c: char, x: u32, y: u32) -> bool {
+    if let Some(pattern) = BLOCKS.iter().position(|&block| block == c) {
+        let quadrant = y * 2 / GLYPH_HEIGHT * 2 + x * 2 / GLYPH_WIDTH;
+        return pattern & 1 << quadrant != 0;
+    }
+
+    match c {
+        '░' => x.is_multiple_of(2) && y.is_multiple_of(2),
+        '▒' => (x + y).is_multiple_of(2),
+        '▓' => x.is_multiple_of(2) || y.is_multiple_of(2),
+        '\u{2800}'..='\u{28ff}' => {
+            let (column, row) = (x * 2 / GLYPH_WIDTH, y * 4 / GLYPH_HEIGHT);
+            // Dots 1 to 3 and 4 to 6 run down each column, then 7 and 8
+            let bit = if row == 3 {
+                6 + column
+            } else {
+                column * 3 + row
+            };
+            // A dot is the middle of its part of the cell, not all of it
+            let inside = (1..3).contains(&(x - column * GLYPH_WIDTH / 2))
+                && (1..3).contains(&(y - row * GLYPH_HEIGHT / 4));
+            inside && (c as u32 - 0x2800) & 1 << bit != 0
+        }
+        c => is_lit(&glyph(c), x, y),
+    }
+}
@@ -0,0 +1,55 @@
+use image::{imageops::FilterType, DynamicImage};
+
+use crate::{
+    font::{glyph, is_lit, GLYPH_HEIGHT, GLYPH_WIDTH},
+    primitives::OutputSize,
+};
+
+/// Characters considered when matching shapes. Mostly strokes and blobs,
+/// since letters rarely fit a block better than these do.
+const CANDIDATES: &str = " .,:;'`\"-_=+*^~|/\\()<>[]{}!ilI17LTVXYovxO0#%&$@";
+
+/// Picks, for each cell of `size`, the character whose glyph looks the most
+/// like that part of the image, instead of going by brightness alone.
+/// The result is in row-major order.
+pub fn match_glyphs(image: &DynamicImage, size: OutputSize) -> Vec<char> {
+    let luma = image
+        .resize_exact(
+            size.0 * GLYPH_WIDTH,
+            size.1 * GLYPH_HEIGHT,
+            FilterType::Triangle,
+        )
+        .to_luma8();
+
+    let glyphs = CANDIDATES
+        .chars()
+        .map(|c| (c, glyph(c)))
+        .collect::<Vec<_>>();
+
+    let mut matched = Vec::with_capacity((size.0 * size.1) as usize);
+    for row in 0..size.1 {
+        for column in 0..size.0 {
+            let (x0, y0) = (column * GLYPH_WIDTH, row * GLYPH_HEIGHT);
+
+            let (best, _) = glyphs
+                .iter()
+                .map(|(c, rows)| {
+                    let mut error = 0u32;
+                    for y in 0..GLYPH_HEIGHT {
+                        for x in 0..GLYPH_WIDTH {
+                            let pixel = u32::from(luma.get_pixel(x0 + x, y0 + y).0[0]);
+                            let ink = if is_lit(rows, x, y) { 255 } else { 0 };
+                            error += pixel.abs_diff(ink).pow(2);
+                        }
+                    }
+                    (*c, error)
+                })
+                .min_by_key(|&(_, error)| error)
+                .unwrap();
+
+            matched.push(best);
+        }
+    }
+
+    matched
+}
@@ -0,0 +1,67 @@
+use std::{error::Error, fs::metadata, path::PathBuf, process::Command, time::Duration};
+
+use clap::ArgMatches;
+
+use crate::{archive::count_frames, error::classify};
+
+/// Runs `--on-complete` or `--on-error` through the system's shell, depending
+/// on how the compile went. Details about it are passed along in `ASCIIC_*` variables.
+pub fn run_hooks(matches: &ArgMatches, result: &Result<(), Box<dyn Error>>, elapsed: Duration) {
+    let hook = if result.is_ok() {
+        "on-complete"
+    } else {
+        "on-error"
+    };
+    let Some(command) = matches.get_one::<String>(hook) else {
+        return;
+    };
+
+    let mut shell = shell(command);
+    shell.env("ASCIIC_ELAPSED", format!("{:.1}", elapsed.as_secs_f64()));
+
+    if let Some(video) = matches.get_one::<String>("video") {
+        shell.env("ASCIIC_INPUT", video);
+    }
+    // -i writes its outputs next to each input instead
+    if !matches.contains_id("image") {
+        let mut output = matches.get_one::<PathBuf>("output").unwrap().clone();
+        output.set_extension("bapple");
+
+        // A failed compile can leave an older archive behind
+        if result.is_ok() {
+            if let Ok(frames) = count_frames(&output) {
+                shell.env("ASCIIC_FRAMES", frames.to_string());
+            }
+            if let Ok(file) = metadata(&output) {
+                shell.env("ASCIIC_BYTES", file.len().to_string());
+            }
+        }
+        shell.env("ASCIIC_OUTPUT", output);
+    }
+
+    if let Err(error) = result {
+        let (kind, code) = classify(error.as_ref());
+        shell
+            .env("ASCIIC_ERROR", error.to_string())
+            .env("ASCIIC_ERROR_KIND", kind)
+            .env("ASCIIC_EXIT_CODE", code.to_string());
+    }
+
+    match shell.status() {
+        Ok(status) if !status.success() => eprintln!("WARN: --{hook} exited with {status}"),
+        Err(error) => eprintln!("WARN: --{hook} couldn't run: {error}"),
+        Ok(_) => (),
+    }
+}
+
+/// `sh -c`, or `cmd /C` on Windows
+fn shell(command: &str) -> Command {
+    let (program, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut shell = Command::new(program);
+    shell.args([flag, command]);
+    shell
+}
@@ -0,0 +1,297 @@
+use std::{error::Error, fs::read, path::PathBuf};
+
+use clap::ArgMatches;
+
+use crate::{archive::Bapple, metadata::Metadata, primitives::Container};
+use asciicore::ansi::{render, Cell, Color, Pen};
+use asciicore::frame::compress;
+
+/// Code page 437, as drawn by DOS. Control characters are only used as
+/// glyphs when they don't mean anything to the ANSI interpreter.
+const CP437: [char; 256] = {
+    let low = [
+        ' ', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼', '►', '◄',
+        '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+    ];
+    let high = [
+        'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', 'É', 'æ',
+        'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', 'á', 'í', 'ó', 'ú',
+        'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', '░', '▒', '▓', '│', '┤', '╡',
+        '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟',
+        '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘',
+        '┌', '█', '▄', '▌', '▐', '▀', 'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ',
+        '∞', 'φ', 'ε', '∩', '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²',
+        '■', '\u{a0}',
+    ];
+
+    let mut table = [' '; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = match i {
+            0..=31 => low[i],
+            127 => '⌂',
+            128.. => high[i - 128],
+            #[allow(clippy::cast_possible_truncation)]
+            _ => i as u8 as char,
+        };
+        i += 1;
+    }
+    table
+};
+
+/// Where the text of a file ends, and how it should be laid out according
+/// to its SAUCE record, if it has one
+struct Sauce {
+    content_len: usize,
+    width: usize,
+    ice_colors: bool,
+}
+
+impl Sauce {
+    fn read(data: &[u8]) -> Self {
+        let record = data.len().checked_sub(128).map(|start| &data[start..]);
+        let eof = data.iter().position(|&b| b == 0x1a).unwrap_or(data.len());
+
+        match record {
+            Some(record) if record.starts_with(b"SAUCE") => {
+                let width = usize::from(u16::from_le_bytes([record[96], record[97]]));
+                Self {
+                    content_len: eof,
+                    width: if width == 0 { 80 } else { width },
+                    ice_colors: record[105] & 1 != 0,
+                }
+            }
+            _ => Self {
+                content_len: eof,
+                width: 80,
+                ice_colors: false,
+            },
+        }
+    }
+}
+
+/// Turns classic ANSI art (CP437 text with ANSI.SYS escape sequences) into
+/// rows of cells
+pub fn parse_ansi_art(data: &[u8]) -> Vec<Vec<Cell>> {
+    let sauce = Sauce::read(data);
+    let data = &data[..sauce.content_len];
+
+    let mut screen: Vec<Vec<Cell>> = Vec::new();
+    let (mut row, mut column) = (0usize, 0usize);
+    let mut saved = (0, 0);
+    let mut pen = Pen::default();
+    let (mut bold, mut blink) = (false, false);
+
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            0x1b if data.get(i + 1) == Some(&b'[') => {
+                let start = i + 2;
+                let end = data[start..]
+                    .iter()
+                    .position(|b| (0x40..=0x7e).contains(b))
+                    .map_or(data.len(), |end| start + end);
+                let params = String::from_utf8_lossy(&data[start..end.min(data.len())]);
+                let numbers = params
+                    .split(';')
+                    .map(|param| param.parse::<usize>().unwrap_or(0))
+                    .collect::<Vec<_>>();
+                let count = numbers.first().copied().unwrap_or(0).max(1);
+
+                match data.get(end) {
+                    Some(b'm') => {
+                        for &param in &numbers {
+                            match param {
+                                0 => (pen, bold, blink) = (Pen::default(), false, false),
+                                1 => bold = true,
+                                5 => blink = true,
+                                22 => bold = false,
+                                25 => blink = false,
+                                _ => (),
+                            }
+                        }
+                        #[allow(clippy::cast_possible_truncation)]
+                        let params = numbers.iter().map(|&n| n as u16).collect::<Vec<_>>();
+                        pen.apply(&params);
+                    }
+                    Some(b'A') => row = row.saturating_sub(count),
+                    Some(b'B') => row += count,
+                    Some(b'C') => column = (column + count).min(sauce.width - 1),
+                    Some(b'D') => column = column.saturating_sub(count),
+                    Some(b'H' | b'f') => {
+                        row = numbers.first().copied().unwrap_or(1).max(1) - 1;
+                        column = numbers.get(1).copied().unwrap_or(1).max(1) - 1;
+                    }
+                    Some(b's') => saved = (row, column),
+                    Some(b'u') => (row, column) = saved,
+                    Some(b'J') if numbers.first() == Some(&2) => {
+                        screen.clear();
+                        (row, column) = (0, 0);
+                    }
+                    Some(b'K') => {
+                        if let Some(line) = screen.get_mut(row) {
+                            line.truncate(column);
+                        }
+                    }
+                    _ => (),
+                }
+                i = end + 1;
+                continue;
+            }
+            b'\r' => column = 0,
+            b'\n' => (row, column) = (row + 1, 0),
+            b'\t' => column = ((column / 8 + 1) * 8).min(sauce.width - 1),
+            byte => {
+                let mut cell_pen = pen;
+                // Bold means bright in ANSI.SYS, and so does blink on the
+                // background with iCE colors
+                if let (true, Some(Color::Indexed(index @ 0..=7))) = (bold, pen.fg) {
+                    cell_pen.fg = Some(Color::Indexed(index + 8));
+                }
+                if let (true, Some(Color::Indexed(index @ 0..=7))) =
+                    (blink && sauce.ice_colors, pen.bg)
+                {
+                    cell_pen.bg = Some(Color::Indexed(index + 8));
+                }
+
+                if screen.len() <= row {
+                    screen.resize(row + 1, Vec::new());
+                }
+                let line = &mut screen[row];
+                if line.len() <= column {
+                    line.resize(column + 1, Cell::blank());
+                }
+                line[column] = Cell {
+                    ch: CP437[usize::from(byte)],
+                    pen: cell_pen,
+                };
+
+                column += 1;
+                if column == sauce.width {
+                    (row, column) = (row + 1, 0);
+                }
+            }
+        }
+        i += 1;
+    }
+
+    for line in &mut screen {
+        line.resize(sauce.width, Cell::blank());
+    }
+    screen
+}
+
+/// Converts ANSI art files into an archive, one frame per file
+pub fn import(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let inputs = matches
+        .get_many::<PathBuf>("input")
+        .unwrap()
+        .collect::<Vec<_>>();
+
+    let mut frames = Vec::new();
+    let mut size = (0, 0);
+    for input in &inputs {
+        let screen = parse_ansi_art(&read(input)?);
+        size.0 = size.0.max(screen.first().map_or(0, Vec::len));
+        size.1 = size.1.max(screen.len());
+        frames.push(compress(render(&screen).as_bytes())?);
+    }
+
+    let output = matches
+        .get_one::<PathBuf>("output")
+        .cloned()
+        .unwrap_or_else(|| inputs[0].with_extension("bapple"));
+
+    #[allow(clippy::cast_possible_truncation)]
+    let bapple = Bapple {
+        frames,
+        audio: None,
+        extra_tracks: Vec::new(),
+        metadata: Metadata {
+            color_depth: Some("16".to_string()),
+            min_size: Some((size.0 as u32, size.1 as u32)),
+            ..Metadata::default()
+        },
+        events: Vec::new(),
+        container: Container::Tar,
+    };
+    bapple.write(&output)?;
+
+    println!(
+        ">> Imported {} file(s) into {}",
+        inputs.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(screen: &[Vec<Cell>]) -> Vec<String> {
+        screen
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.ch).collect::<String>())
+            .map(|row| row.trim_end().to_string())
+            .collect()
+    }
+
+    /// A SAUCE record for `width` columns, after an end of file marker
+    fn sauce(width: u16, ice_colors: bool) -> Vec<u8> {
+        let mut record = vec![0x1a];
+        record.extend_from_slice(b"SAUCE00");
+        record.resize(1 + 96, b' ');
+        record.extend_from_slice(&width.to_le_bytes());
+        record.resize(1 + 105, 0);
+        record.push(u8::from(ice_colors));
+        record.resize(1 + 128, 0);
+        record
+    }
+
+    #[test]
+    fn lays_out_text() {
+        let screen = parse_ansi_art(b"ab\r\ncd\n\tx\xdb\xb0");
+        assert_eq!(text(&screen), ["ab", "cd", "        x█░"]);
+        assert!(screen.iter().all(|row| row.len() == 80));
+    }
+
+    #[test]
+    fn wraps_at_the_width() {
+        let art = [b"0123456789".as_slice(), &sauce(4, false)].concat();
+        assert_eq!(text(&parse_ansi_art(&art)), ["0123", "4567", "89"]);
+    }
+
+    #[test]
+    fn moves_the_cursor() {
+        let screen = parse_ansi_art(b"\x1b[2;3Hx\x1b[sy\x1b[Az\x1b[u\x1b[2Dw\x1b[200Cv");
+        assert_eq!(text(&screen)[0], "    z");
+        assert!(text(&screen)[1].starts_with(" wxy "));
+        assert_eq!(screen[1][79].ch, 'v');
+
+        let screen = parse_ansi_art(b"old\x1b[2Jnew\x1b[1D\x1b[K");
+        assert_eq!(text(&screen), ["ne"]);
+    }
+
+    #[test]
+    fn brightens_bold_and_blink() {
+        let screen = parse_ansi_art(b"\x1b[1;31;44ma\x1b[0;5;31;44mb");
+        let pens = screen[0][..2]
+            .iter()
+            .map(|cell| cell.pen)
+            .collect::<Vec<_>>();
+        assert_eq!(pens[0].fg, Some(Color::Indexed(9)));
+        assert_eq!(pens[0].bg, Some(Color::Indexed(4)));
+        // Blink only brightens backgrounds with iCE colors
+        assert_eq!(pens[1].bg, Some(Color::Indexed(4)));
+
+        let art = [b"\x1b[5;44mb".as_slice(), &sauce(80, true)].concat();
+        assert_eq!(parse_ansi_art(&art)[0][0].pen.bg, Some(Color::Indexed(12)));
+    }
+
+    #[test]
+    fn survives_truncated_sequences() {
+        assert_eq!(text(&parse_ansi_art(b"a\x1b[1;3")), ["a"]);
+        assert_eq!(text(&parse_ansi_art(b"a\x1b")), ["a←"]);
+    }
+}
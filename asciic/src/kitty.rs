@@ -0,0 +1,48 @@
+use std::{fmt::Write, io::Cursor};
+
+use image::{DynamicImage, ImageError, ImageOutputFormat};
+
+/// Most base64 a single escape can carry
+const CHUNK: usize = 4096;
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Escapes that draw `image` at the cursor with the kitty graphics protocol.
+/// It goes as a PNG, split over as many escapes as it takes, and the
+/// terminal is asked not to answer so that nothing ends up on stdin.
+pub fn encode(image: &DynamicImage) -> Result<String, ImageError> {
+    let mut png = Cursor::new(Vec::new());
+    image.write_to(&mut png, ImageOutputFormat::Png)?;
+    let data = base64(png.get_ref());
+
+    let chunks = data.as_bytes().chunks(CHUNK).collect::<Vec<_>>();
+    let mut out = String::with_capacity(data.len() + chunks.len() * 16);
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        if index == 0 {
+            out.push_str("\x1b_Ga=T,f=100,q=2,");
+        } else {
+            out.push_str("\x1b_G");
+        }
+        write!(out, "m={more};").unwrap();
+        // Always ASCII, since it's base64
+        out.push_str(std::str::from_utf8(chunk).unwrap());
+        out.push_str("\x1b\\");
+    }
+    Ok(out)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|index| group.get(index).copied().unwrap_or(0));
+        let bits = u32::from(a) << 16 | u32::from(b) << 8 | u32::from(c);
+        for (index, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            if index <= group.len() {
+                out.push(BASE64[(bits >> shift & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
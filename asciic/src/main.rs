@@ -1,62 +1,553 @@
 #![warn(clippy::pedantic)]
 
 use std::{
+    borrow::Cow,
+    collections::BTreeMap,
     error::Error,
-    fs::{read_dir, File},
-    io::{Read, Write},
+    fmt,
+    fs::{read, remove_file, write, File},
+    io::{self, BufWriter, Write},
+    iter::repeat_n,
     path::{Path, PathBuf},
-    str::FromStr,
+    process::exit,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{channel, Receiver},
         Arc,
     },
+    thread::scope,
+    time::{Duration, Instant},
 };
 
-use image::{imageops::FilterType, io::Reader, GenericImageView, ImageError};
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use tar::Builder;
+use image::{DynamicImage, GenericImageView, RgbImage};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 use tempfile::TempDir;
-use zstd::encode_all;
+use zstd::{bulk::Compressor, zstd_safe::compress_bound};
 
+use adjust::{white_balance, Adjustments};
+use animation::{is_animation, pace, AsciiAnimationBuilder};
+use archive::{track_name, Bapple, Writer};
+use asciicore::ansi::AsciiFrame;
+use asciicore::plugin::Plugin;
+use asciicore::{
+    frame::{compress, decompress, index, is_intact},
+    termcaps::terminal_size,
+};
+use bench::bench;
+use braille::braille;
+use cache::Cache;
+use charset::{list_charsets, Ramp};
+use clap::ArgMatches;
 use cli::cli;
+use clipboard::copy_image;
+use color::{brightness, color_step, displayed, gradient, posterize, Rgb};
+use config::{apply_config, AsciiConfig};
+use converter::AsciiConverter;
+use daemon::daemon;
+use diff::diff;
+use dither::{bayer, floyd_steinberg};
+use doctor::doctor;
+use edges::edge_glyphs;
+use error::{report, CompilerError};
+use events::{read_events, write_events, Event};
+use export::{html, render_to_image, svg};
+use glyph::match_glyphs;
+use hooks::run_hooks;
+use import::import;
+use metadata::{sync_markers, Metadata};
+use orientation::{open_image, oriented_dimensions};
+use otsu::otsu;
+use paint::{Canvas, Cells, IoSink, Painter, Stroke, Text};
+use plain::tidy;
+use preview::show_preview;
 use primitives::{
-    Options, OutputSize,
-    PaintStyle::{self, BgOnly, BgPaint, FgPaint},
+    AudioMode, ChromaKey, ColorMode, Container, Crop, DitherMode, Effect, ErrorFormat, Fit,
+    FitMode, Luminance, Options, OutputFormat, OutputSize, Overlay,
+    PaintStyle::{self, BgOnly, FgPaint, Mixed, Shade},
+    PlainProfile, RenderMode, Sampling, ThresholdMetric, TwoLevel, WhiteBalance,
 };
-use util::{add_file, clean, clean_abort, ffmpeg, max_sub, pause};
+use probe::{ffprobe, VideoInfo};
+use quadrant::quadrant;
+use raw::{compile_raw, open_raw, RawFormat};
+use retime::retime;
+use sample::sample;
+use scenes::{charset_at, find_scenes};
+use sheet::compile_sheet;
+use slideshow::slideshow;
+use split::{join, split};
+use stereo::{anaglyph, compile_pair};
+use style::CellContext;
+use testcard::testcard;
+use theme::theme;
+use util::{clean, clean_abort, expand_globs, ffmpeg, list_frames, max_sub, pause, replace_audio};
+use view::view;
+use watchdog::Watchdog;
+use y4m::Y4m;
 
+mod adjust;
+mod animation;
+mod archive;
+mod bench;
+mod braille;
+mod cache;
+mod charset;
 mod cli;
+mod clipboard;
+mod color;
+mod config;
+mod converter;
+mod daemon;
+mod diff;
+mod dither;
+mod doctor;
+mod edges;
+mod error;
+mod events;
+mod export;
+mod font;
+mod glyph;
+mod hooks;
+mod import;
+mod keys;
+#[cfg(feature = "kitty")]
+mod kitty;
+mod metadata;
+mod orientation;
+mod otsu;
+mod overlay;
+mod paint;
+mod plain;
+mod preview;
 mod primitives;
+mod probe;
+mod quadrant;
+mod raw;
+mod retime;
+mod sample;
+mod scenes;
+mod sheet;
+#[cfg(feature = "sixel")]
+mod sixel;
+mod slideshow;
+mod split;
+mod stereo;
+mod style;
+mod surface;
+mod testcard;
+mod theme;
 mod util;
+mod view;
+mod watchdog;
+mod y4m;
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
+    if clipboard::is_holder() {
+        clipboard::hold().ok();
+        return;
+    }
     let matches = cli().get_matches();
+    let format = error_format(&matches);
+    let matches =
+        apply_config(matches).unwrap_or_else(|error| exit(report(error.as_ref(), format)));
+
+    let start = Instant::now();
+    let result = run(&matches);
+
+    let code = result
+        .as_ref()
+        .err()
+        .map(|error| report(error.as_ref(), error_format(&matches)));
+
+    if matches.subcommand().is_none() {
+        run_hooks(&matches, &result, start.elapsed());
+    }
+    if let Some(code) = code {
+        exit(code);
+    }
+}
+
+fn error_format(matches: &ArgMatches) -> ErrorFormat {
+    match matches.subcommand() {
+        Some((_, matches)) => matches,
+        None => matches,
+    }
+    .get_one::<ErrorFormat>("error-format")
+    .copied()
+    .unwrap_or(ErrorFormat::Human)
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    match matches.subcommand() {
+        Some(("retime", matches)) => return retime(matches),
+        Some(("split", matches)) => return split(matches),
+        Some(("join", matches)) => return join(matches),
+        Some(("theme", matches)) => return theme(matches),
+        Some(("doctor", matches)) => return doctor(matches),
+        Some(("charsets", _)) => {
+            list_charsets();
+            return Ok(());
+        }
+        Some(("diff", matches)) => return diff(matches),
+        Some(("import", matches)) => return import(matches),
+        Some(("slideshow", matches)) => return slideshow(matches),
+        Some(("view", matches)) => return view(matches),
+        Some(("testcard", matches)) => return testcard(matches),
+        Some(("bench", matches)) => return bench(matches),
+        Some(("daemon", matches)) => return daemon(matches),
+        _ => (),
+    }
+
+    let options = read_options(matches)?;
+    if let Some(path) = matches.get_one::<PathBuf>("save-config") {
+        AsciiConfig::from_matches(matches).save(path)?;
+    }
+
+    if let Some(images) = matches.get_many::<String>("image") {
+        let images = expand_globs(images)?;
+        let oriented = !matches.contains_id("ignore-orientation");
+        if let Some(right) = matches.get_one::<PathBuf>("right-eye") {
+            let [left] = images.as_slice() else {
+                return Err(CompilerError::InvalidInput(
+                    "--right-eye pairs with exactly one image".into(),
+                )
+                .into());
+            };
+            return compile_pair(left, right, options, oriented);
+        }
+        let format = *matches.get_one::<OutputFormat>("format").unwrap();
+        let plain = matches.get_one::<PlainProfile>("plain-profile").copied();
+        let with_plain = matches.contains_id("with-plain");
+        if with_plain && format != OutputFormat::Text {
+            return Err(CompilerError::InvalidInput(
+                "--with-plain only works with --format text".into(),
+            )
+            .into());
+        }
+        let options = match plain {
+            Some(_) if format != OutputFormat::Text => {
+                return Err(CompilerError::InvalidInput(
+                    "--plain-profile only works with --format text".into(),
+                )
+                .into())
+            }
+            Some(profile) => profile.limit(options)?,
+            None => options,
+        };
+        if matches.contains_id("to-clipboard") {
+            let [image] = images.as_slice() else {
+                return Err(CompilerError::InvalidInput(
+                    "--to-clipboard takes exactly one image".into(),
+                )
+                .into());
+            };
+            return copy_image(image, options, format, plain, oriented);
+        }
+        return compile_images(images, options, format, plain, with_plain, oriented);
+    }
 
+    // Read up front, so that mistakes show up before any work is done
+    let events = match matches.get_one::<PathBuf>("events") {
+        Some(path) => read_events(path)?,
+        None => Vec::new(),
+    };
+
+    if let Some(&grid) = matches.get_one::<(u32, u32)>("grid") {
+        return compile_sheet(matches, options, grid, &events);
+    }
+
+    // Loaded first, so that a broken plugin fails before ffmpeg runs
+    let plugin = matches
+        .get_one::<PathBuf>("plugin")
+        .map(|path| Plugin::load(path))
+        .transpose()
+        .map_err(|error| -> Box<dyn Error> { error })?;
+
+    let video = matches.get_one::<String>("video").unwrap();
+    let is_stream = matches.contains_id("rawvideo")
+        || video == "-"
+        || Path::new(video).extension().is_some_and(|ext| ext == "y4m");
+    let animated = !is_stream && is_animation(Path::new(video));
+    if (is_stream || animated) && (plugin.is_some() || matches.contains_id("temporal")) {
+        return Err(CompilerError::InvalidInput(
+            "--plugin and --temporal only work with videos that go through ffmpeg".into(),
+        )
+        .into());
+    }
+    if is_stream {
+        return compile_stream(matches, options, &events);
+    }
+    if animated {
+        return compile_animation(matches, options, &events);
+    }
+
+    compile_video(matches, options, &events, plugin.as_ref())
+}
+
+/// Compiles raw frames from `--rawvideo`, or y4m ones that bring their own
+/// size and framerate, without going through ffmpeg
+fn compile_stream(
+    matches: &ArgMatches,
+    options: Options,
+    events: &[Event],
+) -> Result<(), Box<dyn Error>> {
+    let mut input = open_raw(matches.get_one::<String>("video").unwrap())?;
+    let (format, framerate) = if let Some(&dimensions) = matches.get_one("rawvideo") {
+        (RawFormat::Rgba(dimensions), None)
+    } else {
+        let y4m = Y4m::read_header(&mut input)?;
+        (RawFormat::Y4m(y4m), y4m.framerate)
+    };
+    check_crop(options, format.dimensions())?;
+
+    let mut output = matches.get_one::<PathBuf>("output").unwrap().clone();
+    let metadata = player_metadata(matches, options, framerate, Some(format.dimensions()));
+    let container = *matches.get_one::<Container>("container").unwrap();
+    compile_raw(
+        input,
+        format,
+        &mut output,
+        container,
+        options,
+        &metadata,
+        events,
+    )
+}
+
+/// Compiles animated GIF, APNG and WebP images frame by frame, without going
+/// through ffmpeg. Frames repeat as needed to keep their delays at a single
+/// framerate.
+fn compile_animation(
+    matches: &ArgMatches,
+    options: Options,
+    events: &[Event],
+) -> Result<(), Box<dyn Error>> {
+    let input = Path::new(matches.get_one::<String>("video").unwrap());
+    let animation = AsciiAnimationBuilder::new(options).open(input)?;
+    let dimensions = animation.dimensions;
+    check_crop(options, dimensions)?;
+
+    let mut frames = Vec::new();
+    let mut delays = Vec::new();
+    for frame in animation {
+        let (frame, delay) = frame?;
+        frames.push(compress(frame.as_bytes())?);
+        delays.push(delay);
+        print!("\rProcessing: {} frames", frames.len());
+    }
+    let (framerate, repeats) = pace(&delays);
+    let count = frames.len();
+
+    let mut output = matches.get_one::<PathBuf>("output").unwrap().clone();
+    output.set_extension("bapple");
+    let bapple = Bapple {
+        frames: frames
+            .into_iter()
+            .zip(repeats)
+            .flat_map(|(frame, repeats)| repeat_n(frame, repeats))
+            .collect(),
+        audio: None,
+        extra_tracks: Vec::new(),
+        metadata: player_metadata(matches, options, Some(framerate), Some(dimensions)),
+        events: events.to_vec(),
+        container: *matches.get_one::<Container>("container").unwrap(),
+    };
+    bapple.write(&output)?;
+
+    println!(
+        "\n\n\
+        >=== Done! ===<\n\
+        >> {count} frame(s) played at {framerate:.2} fps\n\
+        >> Output available at {}",
+        output.display()
+    );
+    Ok(())
+}
+
+/// Conversion settings from the command line, checked for combinations
+/// that can't work
+fn read_options(matches: &ArgMatches) -> Result<Options, Box<dyn Error>> {
+    let fit_mode = *matches.get_one::<FitMode>("fit-mode").unwrap();
     let options = Options {
         redimension: *matches.get_one::<OutputSize>("frame-size").unwrap(),
+        fit: (matches.contains_id("fit")
+            || matches.contains_id("pixel-art")
+            || fit_mode != FitMode::Stretch)
+            .then(|| Fit {
+                max: matches
+                    .get_one::<OutputSize>("fit")
+                    .copied()
+                    .or_else(|| {
+                        // Pixel art fits in --size, unless --fit asks for the terminal
+                        matches
+                            .contains_id("fit")
+                            .then(terminal_size)
+                            .flatten()
+                            .map(|(width, height)| OutputSize(width, height))
+                    })
+                    .filter(|size| size.0 > 0 && size.1 > 0)
+                    .unwrap_or(*matches.get_one::<OutputSize>("frame-size").unwrap()),
+                cell_aspect: *matches.get_one::<f32>("cell-aspect").unwrap(),
+            }),
+        fit_mode,
+        pixel_art: matches.contains_id("pixel-art"),
+        crop: matches.get_one::<Crop>("crop").copied(),
         colorize: matches.contains_id("colorize"),
+        color_mode: *matches.get_one::<ColorMode>("color-mode").unwrap(),
+        dither: *matches.get_one::<DitherMode>("dither").unwrap(),
+        glyph_match: matches.contains_id("glyph-match"),
+        auto_charset: matches.contains_id("auto-charset"),
+        charset: read_charset(matches)?,
+        anaglyph: matches.contains_id("anaglyph") || matches.contains_id("right-eye"),
+        edges: matches.get_one::<u16>("edges").copied(),
+        render: *matches.get_one::<RenderMode>("render").unwrap(),
+        sampling: *matches.get_one::<Sampling>("sampling").unwrap(),
+        color_sampling: *matches
+            .get_one::<Sampling>("color-sampling")
+            .or_else(|| matches.get_one::<Sampling>("sampling"))
+            .unwrap(),
+        luminance: *matches.get_one::<Luminance>("luminance").unwrap(),
+        dot_threshold: *matches.get_one::<u8>("dot-threshold").unwrap(),
+        two_level: matches.get_one::<TwoLevel>("two-level").copied(),
         skip_compression: matches.contains_id("no-compression"),
         style: *matches.get_one::<PaintStyle>("style").unwrap(),
         compression_threshold: *matches.get_one::<u8>("compression-threshold").unwrap(),
-        skip_audio: matches.contains_id("no-audio"),
+        threshold_metric: *matches
+            .get_one::<ThresholdMetric>("threshold-metric")
+            .unwrap(),
+        adjust: Adjustments {
+            gamma: *matches.get_one::<f32>("gamma").unwrap(),
+            brightness: *matches.get_one::<i16>("brightness").unwrap(),
+            contrast: *matches.get_one::<f32>("contrast").unwrap(),
+            saturation: *matches.get_one::<f32>("saturation").unwrap(),
+            hue: *matches.get_one::<f32>("hue").unwrap(),
+            temperature: *matches.get_one::<i16>("temperature").unwrap(),
+        },
+        white_balance: matches.get_one::<WhiteBalance>("white-balance").copied(),
+        chroma_key: matches
+            .get_one::<Rgb>("chroma-key")
+            .map(|&color| ChromaKey {
+                color,
+                tolerance: *matches.get_one::<u8>("key-tolerance").unwrap(),
+            }),
+        effect: matches.get_one::<Effect>("effect").copied(),
+        gradient: matches
+            .get_many::<Rgb>("gradient")
+            .map(|stops| &*Box::leak(stops.copied().collect())),
+        posterize: matches.get_one::<u8>("posterize").copied(),
+        background: matches.get_one::<Rgb>("background").copied(),
+        dark_cutoff: matches.get_one::<u8>("dark-cutoff").copied(),
+        dark_fill: matches.get_one::<Rgb>("dark-fill").copied(),
+        overlay: matches.get_one::<String>("overlay").map(|text| {
+            let &(x, y) = matches.get_one::<(i32, i32)>("overlay-at").unwrap();
+            Overlay {
+                text: String::leak(text.clone()),
+                x,
+                y,
+                color: matches.get_one::<Rgb>("overlay-color").copied(),
+            }
+        }),
+        audio: if matches.contains_id("no-audio") {
+            AudioMode::Skip
+        } else if matches.contains_id("duck-music") {
+            AudioMode::DuckMusic
+        } else {
+            AudioMode::Extract
+        },
     };
+
+    check_options(matches, options)?;
+    Ok(options)
+}
+
+/// Flags that only make sense with some of the others
+fn check_options(matches: &ArgMatches, options: Options) -> Result<(), CompilerError> {
+    // Modes that pick their characters cell by cell from a brightness ramp
+    let ascii_only = [
+        ("--glyph-match", options.glyph_match),
+        ("--chroma-key", options.chroma_key.is_some()),
+        ("--edges", options.edges.is_some()),
+        ("--pixel-art", options.pixel_art),
+        ("--charset", matches.contains_id("charset")),
+        ("--thresholds", matches.contains_id("thresholds")),
+        ("--scene-charsets", matches.contains_id("scene-charsets")),
+        ("--two-level", options.two_level.is_some()),
+        ("--effect", options.effect.is_some()),
+        ("--dark-cutoff", options.dark_cutoff.is_some()),
+        ("--gradient", options.gradient.is_some()),
+        ("--posterize", options.posterize.is_some()),
+        ("--style mixed", matches!(options.style, Mixed)),
+    ];
+    if let Some((flag, _)) = ascii_only
+        .iter()
+        .find(|(_, used)| *used && options.render != RenderMode::Ascii)
+    {
+        return Err(CompilerError::InvalidInput(format!(
+            "{flag} only works with --render ascii"
+        )));
+    }
+    if options.background.is_some() && !matches!(options.style, FgPaint) {
+        return Err(CompilerError::InvalidInput(
+            "--background only works with --style fg-paint".into(),
+        ));
+    }
+    if options.dark_cutoff.is_some() && !matches!(options.style, BgOnly) {
+        return Err(CompilerError::InvalidInput(
+            "--dark-cutoff only works with --style bg-only".into(),
+        ));
+    }
+    if options.fit.is_some_and(|fit| fit.cell_aspect <= 0.0) {
+        return Err(CompilerError::InvalidInput(
+            "--cell-aspect must be positive".into(),
+        ));
+    }
+    if options.adjust.gamma <= 0.0 || options.adjust.contrast < 0.0 {
+        return Err(CompilerError::InvalidInput(
+            "--gamma must be positive, and --contrast can't be negative".into(),
+        ));
+    }
+    if options.adjust.saturation < 0.0 {
+        return Err(CompilerError::InvalidInput(
+            "--saturation can't be negative".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Makes sure `--crop` fits in sources of the given size
+fn check_crop(options: Options, dimensions: (u32, u32)) -> Result<(), CompilerError> {
+    options.crop.map_or(Ok(()), |crop| crop.check(dimensions))
+}
+
+/// `--charset`, or the default ramp when only `--thresholds` are given
+fn read_charset(matches: &ArgMatches) -> Result<Option<&'static Ramp>, CompilerError> {
+    let charset = matches.get_one::<&Ramp>("charset").copied();
+    if matches.contains_id("measure-charset") {
+        return charset
+            .map(charset::by_ink)
+            .transpose()
+            .map_err(|error| CompilerError::InvalidInput(format!("--measure-charset: {error}")));
+    }
+    let Some(thresholds) = matches.get_many::<u8>("thresholds") else {
+        return Ok(charset);
+    };
+
+    let thresholds = thresholds.copied().collect::<Vec<_>>();
+    charset::with_thresholds(charset.unwrap_or(charset::DEFAULT), &thresholds)
+        .map(Some)
+        .map_err(|error| CompilerError::InvalidInput(format!("--thresholds: {error}")))
+}
+
+fn compile_video(
+    matches: &ArgMatches,
+    options: Options,
+    events: &[Event],
+    plugin: Option<&Plugin>,
+) -> Result<(), Box<dyn Error>> {
     let ffmpeg_flags = matches
         .get_many::<String>("ffmpeg-flags")
         .unwrap_or_default()
         .collect::<Vec<_>>();
 
-    if let Some(image) = matches.get_one::<String>("image") {
-        let image_path = PathBuf::from_str(image)?;
-        let processed_img = process_image(&image_path, options)?;
-
-        File::create(format!(
-            "{}.txt",
-            image_path.file_stem().unwrap().to_str().unwrap()
-        ))?
-        .write_all(processed_img.as_bytes())?;
-        return Ok(());
-    }
-
     let video_path = matches.get_one::<String>("video").unwrap();
     let mut output = matches.get_one::<PathBuf>("output").unwrap().clone();
 
@@ -72,200 +563,1048 @@ fn main() -> Result<(), Box<dyn Error>> {
         clean_abort(tmp_handler.path());
     })?;
 
+    let watchdog = Watchdog {
+        watch: tmp_path,
+        timeout: Duration::from_secs(*matches.get_one::<u64>("stall-timeout").unwrap()),
+        retries: 1,
+    };
+
     println!(">=== Running FFMPEG ===<");
 
-    // Split file into frames
-    ffmpeg(
-        &[
-            "-r",
-            "1",
-            "-i",
+    let audio_only = matches.contains_id("audio-only");
+    if audio_only || matches.contains_id("no-video") {
+        extract_audio(
             video_path,
-            "-r",
-            "1",
-            &format!("{}/%03d.png", tmp_path.to_str().unwrap()),
-        ],
+            tmp_path,
+            &ffmpeg_flags,
+            &watchdog,
+            options.audio == AudioMode::DuckMusic,
+            false,
+            None,
+        )
+        .inspect_err(|_| clean(tmp_path))?;
+        audio_only_mode(audio_only, &mut output, tmp_path)?;
+
+        clean(tmp_path);
+        return Ok(());
+    }
+
+    let cache = matches
+        .get_one::<PathBuf>("cache")
+        .map(|dir| Cache::open(dir, video_path, &ffmpeg_flags))
+        .transpose()
+        .inspect_err(|_| clean(tmp_path))?;
+
+    let video_info = extract(
+        video_path,
+        tmp_path,
         &ffmpeg_flags,
-    )
-    .unwrap_or_else(|_| {
-        clean_abort(tmp_path);
-    });
+        &watchdog,
+        cache.as_ref(),
+        options,
+    )?;
+
+    let frames = list_frames(tmp_path);
+
+    println!("\nStarting frame generation ...");
+
+    let metadata = video_metadata(
+        matches,
+        options,
+        video_info.and_then(|info| info.framerate),
+        &frames,
+    );
+
+    // The audio gets extracted while the frames convert, since neither needs
+    // the other until the archive is written
+    scope(|scope| {
+        let audio = scope.spawn(|| {
+            fetch_audio(
+                video_path,
+                tmp_path,
+                &ffmpeg_flags,
+                &watchdog,
+                options.audio,
+                &audio_streams(matches),
+                cache.as_ref(),
+            )
+            .map_err(sendable)
+        });
 
-    // Extract audio
-    if !options.skip_audio {
+        read_frames(
+            frames,
+            &mut output,
+            *matches.get_one::<Container>("container").unwrap(),
+            |position| scene_options(matches, &metadata, options, position),
+            &should_stop,
+            matches.get_one::<u64>("preview-live").copied(),
+            &metadata,
+            events,
+            cache.as_ref(),
+            plugin,
+            || {
+                let tracks = audio
+                    .join()
+                    .unwrap()
+                    .map_err(|error| -> Box<dyn Error> { error })?;
+                Ok(read_tracks(tmp_path, tracks)?)
+            },
+        )
+    })
+    .inspect_err(|_| clean(tmp_path))?;
+
+    println!(
+        "\n\n\
+        >=== Done! ===<\n\
+        >> Output available at {}",
+        output.display()
+    );
+
+    clean(tmp_path);
+    Ok(())
+}
+
+/// `options`, with the charset `--scene-charsets` picked for the scene
+/// of the frame at `position`
+fn scene_options(
+    matches: &ArgMatches,
+    metadata: &Metadata,
+    options: Options,
+    position: usize,
+) -> Options {
+    let (Some(mut charsets), Some(scenes)) = (
+        matches.get_many::<&Ramp>("scene-charsets"),
+        metadata.scenes.as_deref(),
+    ) else {
+        return options;
+    };
+    Options {
+        charset: charsets.nth(charset_at(scenes, position)).copied(),
+        ..options
+    }
+}
+
+/// Same as [`player_metadata`], plus sync markers when there's audio to
+/// keep up with, and the scenes that `--scene-charsets` split the video into
+fn video_metadata(
+    matches: &ArgMatches,
+    options: Options,
+    framerate: Option<f64>,
+    frames: &[PathBuf],
+) -> Metadata {
+    let mut metadata = player_metadata(
+        matches,
+        options,
+        framerate,
+        frames
+            .first()
+            .and_then(|frame| image::image_dimensions(frame).ok()),
+    );
+    if options.audio != AudioMode::Skip {
+        metadata.sync = framerate
+            .map(|framerate| sync_markers(framerate, frames.len()))
+            .filter(|markers| !markers.is_empty());
+    }
+    metadata.audio_tracks = matches
+        .get_many::<(usize, String)>("audio-track")
+        .map(|tracks| tracks.map(|(_, language)| language.clone()).collect());
+    if let Some(charsets) = matches.get_many::<&Ramp>("scene-charsets") {
+        println!(">> Looking for scenes ...");
+        let charsets = charsets.copied().collect::<Vec<_>>();
+        metadata.scenes = Some(find_scenes(frames, options, &charsets));
+    }
+    metadata
+}
+
+/// Playback hints for the player. `dimensions` are the ones of the source
+/// frames, which `--fit` needs to work out the output size.
+fn player_metadata(
+    matches: &ArgMatches,
+    options: Options,
+    framerate: Option<f64>,
+    dimensions: Option<(u32, u32)>,
+) -> Metadata {
+    // Fitted frames all come out the same size, so any of them will do
+    let fitted = options
+        .fit
+        .zip(dimensions)
+        .map(|(fit, dimensions)| match options.fit_mode {
+            FitMode::Stretch => fit.size(dimensions),
+            FitMode::Contain | FitMode::Cover => fit.max,
+        });
+    let min_size = matches
+        .get_one::<OutputSize>("suggest-min-size")
+        .copied()
+        .or(fitted)
+        .unwrap_or(options.redimension);
+
+    Metadata {
+        framerate,
+        color_depth: Some(
+            match (options.colorize, options.color_mode) {
+                (false, _) => "none",
+                (true, ColorMode::Truecolor | ColorMode::Gray) => "truecolor",
+                (true, ColorMode::Ansi256 | ColorMode::Gray256) => "256",
+                (true, ColorMode::Ansi16) => "16",
+            }
+            .to_string(),
+        ),
+        loop_stream: matches.contains_id("suggest-loop").then_some(true),
+        min_size: Some((min_size.0, min_size.1)),
+        drop_frames: matches.contains_id("suggest-no-drop").then_some(false),
+        slides: None,
+        scenes: None,
+        sync: None,
+        audio_tracks: None,
+        part: None,
+        config: Some(AsciiConfig::from_matches(matches))
+            .filter(|config| !config.is_empty())
+            .map(|config| config.to_string()),
+        keyframes: matches
+            .contains_id("temporal")
+            .then(|| *matches.get_one::<usize>("keyframe-every").unwrap()),
+    }
+}
+
+/// Splits the video into frames, unless the cache already has them from a
+/// previous run, then makes sure `--crop` fits them
+fn extract(
+    video_path: &str,
+    tmp_path: &Path,
+    ffmpeg_flags: &[&String],
+    watchdog: &Watchdog,
+    cache: Option<&Cache>,
+    options: Options,
+) -> Result<Option<VideoInfo>, Box<dyn Error>> {
+    let video_info = ffprobe(video_path).ok();
+
+    if let Some(true) = cache
+        .map(|cache| cache.restore_frames(tmp_path))
+        .transpose()?
+    {
+        println!(">> Reusing the frames of a previous run");
+    } else {
         ffmpeg(
             &[
+                "-r",
+                "1",
                 "-i",
                 video_path,
-                &format!("{}/audio.mp3", tmp_path.to_str().unwrap()),
+                "-r",
+                "1",
+                &format!("{}/%03d.png", tmp_path.to_str().unwrap()),
             ],
-            &ffmpeg_flags,
+            ffmpeg_flags,
+            watchdog,
         )
-        .unwrap_or_else(|_| {
+        .inspect_err(|_| clean(tmp_path))?;
+
+        verify_frames(video_info, video_path, tmp_path, ffmpeg_flags, watchdog)?;
+        if let Some(cache) = cache {
+            cache.store_frames(&list_frames(tmp_path))?;
+        }
+    }
+
+    if let Some(frame) = list_frames(tmp_path).first() {
+        check_crop(options, image::image_dimensions(frame)?).inspect_err(|_| clean(tmp_path))?;
+    }
+    Ok(video_info)
+}
+
+/// Extracts the audio next to the frames, unless the cache already has it
+/// from a previous run. With `--audio-track`, each of `tracks` gets
+/// extracted on its own instead. Returns how many tracks there are.
+fn fetch_audio(
+    video_path: &str,
+    tmp_path: &Path,
+    ffmpeg_flags: &[&String],
+    watchdog: &Watchdog,
+    audio: AudioMode,
+    tracks: &[usize],
+    cache: Option<&Cache>,
+) -> Result<usize, Box<dyn Error>> {
+    if audio == AudioMode::Skip {
+        return Ok(0);
+    }
+    for (number, &stream) in (1..).zip(tracks) {
+        extract_audio(
+            video_path,
+            tmp_path,
+            ffmpeg_flags,
+            watchdog,
+            audio == AudioMode::DuckMusic,
+            true,
+            Some((stream, number)),
+        )?;
+    }
+    if !tracks.is_empty() {
+        return Ok(tracks.len());
+    }
+
+    if let Some(true) = cache
+        .map(|cache| cache.restore_audio(tmp_path, audio))
+        .transpose()?
+    {
+        println!(">> Reusing the audio of a previous run");
+    } else {
+        extract_audio(
+            video_path,
+            tmp_path,
+            ffmpeg_flags,
+            watchdog,
+            audio == AudioMode::DuckMusic,
+            true,
+            None,
+        )?;
+        if let Some(cache) = cache {
+            cache.store_audio(tmp_path, audio)?;
+        }
+    }
+
+    Ok(1)
+}
+
+/// The audio streams `--audio-track` asks for, in the order they become tracks
+fn audio_streams(matches: &ArgMatches) -> Vec<usize> {
+    matches
+        .get_many::<(usize, String)>("audio-track")
+        .map(|tracks| tracks.map(|(stream, _)| *stream).collect())
+        .unwrap_or_default()
+}
+
+/// Reads back the `count` tracks [`fetch_audio`] extracted
+fn read_tracks(tmp_path: &Path, count: usize) -> io::Result<Vec<Vec<u8>>> {
+    (1..=count)
+        .map(|number| read(tmp_path.join(track_name(number))))
+        .collect()
+}
+
+/// Makes an error fit to cross threads. The compiler's own errors and IO
+/// errors keep their type, so that they still get the right exit code.
+fn sendable(error: Box<dyn Error>) -> Box<dyn Error + Send + Sync> {
+    match error.downcast::<CompilerError>() {
+        Ok(error) => error,
+        Err(error) => match error.downcast::<io::Error>() {
+            Ok(error) => error,
+            Err(error) => error.to_string().into(),
+        },
+    }
+}
+
+/// Either swaps the audio of an existing archive or just reports that the
+/// extraction worked, depending on `replace`
+fn audio_only_mode(
+    replace: bool,
+    output: &mut PathBuf,
+    tmp_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let audio = read(tmp_path.join("audio.mp3"))?;
+
+    if replace {
+        output.set_extension("bapple");
+        let frames = replace_audio(output, &audio).unwrap_or_else(|error| {
+            eprintln!("Couldn't rewrite {}: {error}", output.display());
             clean_abort(tmp_path);
         });
+        println!(
+            "\n>> Replaced the audio of {} ({frames} frames kept)",
+            output.display()
+        );
+    } else {
+        println!(
+            "\n>> Audio track extracted fine ({} bytes), nothing was written",
+            audio.len()
+        );
     }
+    Ok(())
+}
 
-    let frames = read_dir(tmp_path)?
-        .filter_map(Result::ok)
-        .filter(|e| e.file_name() != *"audio.mp3")
-        .map(|entry| entry.path())
-        .collect::<Vec<PathBuf>>();
+/// Lifts dialogue out of the center channel, then evens out the loudness so
+/// that narration stays intelligible over background music
+const DUCK_MUSIC_FILTER: &str = "aformat=channel_layouts=stereo,\
+    dialoguenhance=original=0.6:enhance=2,\
+    loudnorm=I=-16:LRA=7:TP=-1.5";
 
-    println!("\nStarting frame generation ...");
+/// Extracts the audio into `audio.mp3`. In the background, ffmpeg keeps off
+/// the terminal's input and only prints errors, so that it doesn't get in the
+/// way of the frame conversion's progress. `track` picks a single audio
+/// stream instead, along with the number of the track it becomes.
+fn extract_audio(
+    video_path: &str,
+    tmp_path: &Path,
+    ffmpeg_flags: &[&String],
+    watchdog: &Watchdog,
+    duck_music: bool,
+    background: bool,
+    track: Option<(usize, usize)>,
+) -> Result<(), Box<dyn Error>> {
+    let number = track.map_or(1, |(_, number)| number);
+    let output = format!("{}/{}", tmp_path.to_str().unwrap(), track_name(number));
+    let map = track.map(|(stream, _)| format!("0:a:{stream}"));
+    let mut args = Vec::new();
+    if background {
+        args.extend(["-nostdin", "-loglevel", "error"]);
+    }
+    args.extend(["-i", video_path]);
+    if let Some(map) = &map {
+        args.extend(["-map", map]);
+    }
+    if duck_music {
+        args.extend(["-af", DUCK_MUSIC_FILTER]);
+    }
+    args.push(&output);
 
-    read_frames(frames, tmp_path, &mut output, options, &should_stop);
+    ffmpeg(&args, ffmpeg_flags, watchdog)
+}
 
-    println!(
-        "\n\n\
-        >=== Done! ===<\n\
-        >> Output available at {}",
-        output.display()
+/// Cross-checks the split against ffprobe, retrying once without forcing a
+/// framerate if they diverge. Only warns, since some containers lie.
+fn verify_frames(
+    video_info: Option<VideoInfo>,
+    video_path: &str,
+    tmp_path: &Path,
+    ffmpeg_flags: &[&String],
+    watchdog: &Watchdog,
+) -> Result<(), Box<dyn Error>> {
+    let Some(expected) = video_info.and_then(|info| info.expected_frames()) else {
+        eprintln!("\nWARN: Couldn't run ffprobe, skipping frame count verification");
+        return Ok(());
+    };
+
+    let extracted = list_frames(tmp_path).len() as u64;
+    if frame_count_matches(extracted, expected) {
+        return Ok(());
+    }
+
+    eprintln!(
+        "\nWARN: Extracted {extracted} frames, but ffprobe expected {expected}. \
+        Retrying with passthrough timestamps..."
     );
+    for frame in list_frames(tmp_path) {
+        remove_file(frame).ok();
+    }
 
-    clean(tmp_path);
+    ffmpeg(
+        &[
+            "-i",
+            video_path,
+            "-vsync",
+            "passthrough",
+            &format!("{}/%03d.png", tmp_path.to_str().unwrap()),
+        ],
+        ffmpeg_flags,
+        watchdog,
+    )
+    .inspect_err(|_| clean(tmp_path))?;
+
+    let extracted = list_frames(tmp_path).len() as u64;
+    if !frame_count_matches(extracted, expected) {
+        eprintln!(
+            "\nWARN: Still got {extracted} frames out of {expected}, \
+            the output may play at the wrong speed"
+        );
+    }
+    Ok(())
+}
+
+#[inline]
+fn frame_count_matches(extracted: u64, expected: u64) -> bool {
+    max_sub(extracted, expected) <= (expected / 100).max(1)
+}
+
+fn compile_images(
+    images: Vec<PathBuf>,
+    options: Options,
+    format: OutputFormat,
+    plain: Option<PlainProfile>,
+    with_plain: bool,
+    oriented: bool,
+) -> Result<(), Box<dyn Error>> {
+    check_outputs(&images)?;
+    // Images that can't be read fail on their own later on
+    for image in &images {
+        if let Ok(dimensions) = oriented_dimensions(image, oriented) {
+            check_crop(options, dimensions).map_err(|error| {
+                CompilerError::InvalidInput(format!("{}: {error}", image.display()))
+            })?;
+        }
+    }
+    let failed = images
+        .into_par_iter()
+        .filter(|image_path| {
+            let result = open_image(image_path, oriented)
+                .map_err(|e| e.to_string())
+                .and_then(|image| {
+                    // Written next to the image, so that images from different
+                    // folders can't overwrite each other's output
+                    let mut output = image_path.clone();
+                    let written = match format {
+                        OutputFormat::Text if with_plain => {
+                            let mut converter = AsciiConverter::new(options);
+                            let (colored, plain) = converter.convert_both(&image);
+                            output.set_extension("txt");
+                            write(&output, colored)
+                                .and_then(|()| write(output.with_extension("plain.txt"), plain))
+                        }
+                        OutputFormat::Text if plain.is_some() => {
+                            output.set_extension("txt");
+                            let mut text = String::new();
+                            render_image(&image, options, &mut text);
+                            write(&output, tidy(&text))
+                        }
+                        OutputFormat::Text => {
+                            output.set_extension("txt");
+                            // Written as it renders, without building the frame first
+                            File::create(&output)
+                                .map(BufWriter::new)
+                                .and_then(|mut file| {
+                                    write_image_io(&image, options, &mut file)?;
+                                    file.flush()
+                                })
+                        }
+                        OutputFormat::Html => {
+                            output.set_extension("html");
+                            write(&output, html(&frame_cells(&image, options)))
+                        }
+                        OutputFormat::Svg => {
+                            output.set_extension("svg");
+                            write(&output, svg(&frame_cells(&image, options)))
+                        }
+                        OutputFormat::Png => {
+                            // Keeps sources from being overwritten
+                            output.set_extension("ascii.png");
+                            render_to_image(&frame_cells(&image, options))
+                                .save(&output)
+                                .map_err(io::Error::other)
+                        }
+                    };
+                    written.map_err(|e| e.to_string())
+                });
+
+            if let Err(error) = &result {
+                eprintln!("Failed to compile {}: {error}", image_path.display());
+            }
+            result.is_err()
+        })
+        .count();
+
+    if failed > 0 {
+        return Err(CompilerError::ImagesFailed(failed).into());
+    }
+    Ok(())
+}
+
+/// Makes sure every image has a file name of its own to compile into, next
+/// to it. Images that would end up writing over each other's output, like
+/// `photo.png` and `photo.jpg`, are rejected before anything is written.
+fn check_outputs(images: &[PathBuf]) -> Result<(), CompilerError> {
+    let mut seen = BTreeMap::new();
+    for image in images {
+        if image.file_name().is_none() {
+            return Err(CompilerError::InvalidInput(format!(
+                "{} doesn't name an image file",
+                image.display()
+            )));
+        }
+        if let Some(other) = seen.insert(image.with_extension(""), image) {
+            return Err(CompilerError::InvalidInput(format!(
+                "{} and {} would both be compiled into {}",
+                other.display(),
+                image.display(),
+                image.with_extension("*").display()
+            )));
+        }
+    }
     Ok(())
 }
 
+/// Converts and archives the extracted frames. `options` gives the settings
+/// for each frame, by its position.
+#[allow(clippy::too_many_arguments)]
 fn read_frames(
     frames: Vec<PathBuf>,
-    tmp_path: &Path,
     output: &mut PathBuf,
-    options: Options,
+    container: Container,
+    options: impl Fn(usize) -> Options + Sync,
     should_stop: &Arc<AtomicBool>,
-) {
+    preview_every: Option<u64>,
+    metadata: &Metadata,
+    events: &[Event],
+    cache: Option<&Cache>,
+    plugin: Option<&Plugin>,
+    audio: impl FnOnce() -> Result<Vec<Vec<u8>>, Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
     output.set_extension("bapple");
     let processed = AtomicUsize::new(0);
     let total = frames.len();
 
-    let mut tar_archive = Builder::new(File::create(output).unwrap());
+    let mut archive = Writer::create(output, container)?;
 
-    let encoded_frames = frames
-        .into_par_iter()
-        .map(|path| {
-            if should_stop.load(Ordering::Relaxed) {
-                pause();
-            }
-            let image = match process_image(&path, options) {
-                Ok(p) => p,
-                Err(error) => {
-                    eprintln!("Image processing failed. This is probably an ffmpeg related issue");
-                    eprintln!("You should try rerunning this program.");
-                    eprintln!("In any case, here's the error message: \n\n{error:?}");
-
-                    clean_abort(tmp_path); // Prevents littering temporary directory when image processing fails
-                }
-            };
+    // Every thread keeps its own buffers around, instead of allocating and
+    // freeing a few megabytes for each frame
+    let scratch = || {
+        (
+            AsciiConverter::new(options(0)),
+            Vec::new(),
+            Compressor::new(1).unwrap(),
+            plugin.map(|plugin| plugin.instantiate().expect("checked when it was loaded")),
+        )
+    };
 
-            processed.fetch_add(1, Ordering::Relaxed);
-            let now = processed.load(Ordering::Relaxed);
+    let (sender, converted) = channel();
+    let (encoded_frames, repaired) = scope(|scope| {
+        // Frames get archived as soon as the ones before them are, instead of
+        // after the last one converts
+        let linker = scope.spawn(|| link(converted, &mut archive, metadata.keyframes));
 
-            print!("\rProcessing: {}% {now}/{total}", (100 * now) / total);
+        frames
+            .into_par_iter()
+            .enumerate()
+            .map_init(
+                scratch,
+                |(converter, compressed, compressor, plugin), (position, path)| {
+                    if should_stop.load(Ordering::Relaxed) {
+                        pause();
+                    }
 
-            // Linking
+                    let options = options(position);
+                    let cached = cache.and_then(|cache| cache.converted_path(&path, options).ok());
+                    // A damaged cache entry gets converted again
+                    let data = cached.as_ref().and_then(|cached| read(cached).ok());
+                    if let Some(data) = data.filter(|data| is_intact(data)) {
+                        let now = processed.fetch_add(1, Ordering::Relaxed) + 1;
+                        print!(
+                            "\rProcessing: {}% {now}/{total} (cached)",
+                            (100 * now) / total
+                        );
+                        return (position, path, Some(data));
+                    }
 
-            (path, encode_all(image.as_bytes(), 1).unwrap())
-        })
-        .collect::<Vec<_>>();
+                    // One bad image from ffmpeg shouldn't throw away the whole
+                    // conversion, so its frame gets patched over instead
+                    converter.options = options;
+                    if let Err(error) = converter.convert_file(&path) {
+                        eprintln!("\nCan't read {}: {error}", path.display());
+                        processed.fetch_add(1, Ordering::Relaxed);
+                        return (position, path, None);
+                    }
+                    let image = &mut converter.frame;
+                    if let Some(plugin) = plugin {
+                        if let Err(error) = plugin.apply(image, position) {
+                            eprintln!("\nPlugin failed on {}: {error}", path.display());
+                            processed.fetch_add(1, Ordering::Relaxed);
+                            return (position, path, None);
+                        }
+                    }
 
-    let mut processed = 0;
+                    processed.fetch_add(1, Ordering::Relaxed);
+                    let now = processed.load(Ordering::Relaxed);
 
-    // Handle file IO on a single thread to prevent inconsistencies
-    for (path, data) in encoded_frames {
-        processed += 1;
-        print!(
-            "\rLinking: {}% {processed}/{total}",
-            (processed * 100) / total
-        );
+                    if preview_every.is_some_and(|n| (now as u64).is_multiple_of(n)) {
+                        show_preview(image, &path);
+                    }
 
-        let mut inside_path = PathBuf::from(".");
-        inside_path.set_file_name(path.file_stem().unwrap());
-        inside_path.set_extension("zst");
+                    print!("\rProcessing: {}% {now}/{total}", (100 * now) / total);
+
+                    compressed.clear();
+                    compressed.reserve(compress_bound(image.len()));
+                    compressor
+                        .compress_to_buffer(image.as_bytes(), compressed)
+                        .unwrap();
+                    if let Some(cached) = &cached {
+                        write(cached, &compressed).ok();
+                    }
+
+                    (position, path, Some(compressed.clone()))
+                },
+            )
+            .for_each_with(sender, |sender, frame| {
+                sender.send(frame).unwrap();
+            });
+
+        linker.join().unwrap()
+    });
 
-        add_file(&mut tar_archive, &inside_path, &data).unwrap();
+    if !repaired.is_empty() {
+        eprintln!(
+            "\nWARN: Frames {} couldn't be read, and repeat the frame before them instead",
+            repaired.join(", ")
+        );
     }
 
+    let index = index(
+        encoded_frames
+            .iter()
+            .map(|(name, data)| (name.clone(), data.as_slice())),
+    );
+    archive.add("index", index.as_bytes())?;
+
     // Finally add the audio to the archive and finish
-    if !options.skip_audio {
-        let mut audio = File::open(tmp_path.join("audio.mp3")).unwrap();
-        let mut data = Vec::new();
-        audio.read_to_end(&mut data).unwrap();
+    let audio = audio().inspect_err(|_| {
+        drop(remove_file(&output));
+    })?;
+    for (number, data) in (1..).zip(&audio) {
+        archive.add(&track_name(number), data)?;
+    }
 
-        add_file(&mut tar_archive, "audio.mp3", &data).unwrap();
+    if !metadata.is_empty() {
+        archive.add("metadata", metadata.to_string().as_bytes())?;
+    }
+    if !events.is_empty() {
+        archive.add("events", write_events(events).as_bytes())?;
     }
 
-    tar_archive.finish().unwrap();
+    archive.finish()?;
+    Ok(())
 }
 
-fn process_image(image: &PathBuf, options: Options) -> Result<String, ImageError> {
-    let image = Reader::open(image)?.decode()?;
+/// Adds converted frames to the archive in order, whatever order they come
+/// in. Frames that failed to convert repeat the one before them, or start
+/// out blank. With `keyframes`, only every so many frames go in whole, and
+/// the rest as what changed since the one before. Returns each frame's name
+/// and data, for the index, and the names of the frames that had to be
+/// repeated.
+fn link(
+    converted: Receiver<(usize, PathBuf, Option<Vec<u8>>)>,
+    archive: &mut Writer,
+    keyframes: Option<usize>,
+) -> (Vec<(String, Vec<u8>)>, Vec<String>) {
+    let mut waiting = BTreeMap::new();
+    let mut linked: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut repaired = Vec::new();
+    // The last frame whole, to repeat and to tell what changed since
+    let mut last: Option<(Vec<u8>, AsciiFrame)> = None;
 
-    let resized_image = image.resize_exact(
-        options.redimension.0,
-        options.redimension.1,
-        FilterType::Nearest,
-    );
+    for (position, path, data) in converted {
+        waiting.insert(position, (path, data));
+        while let Some((path, data)) = waiting.remove(&linked.len()) {
+            let name = format!("{}.zst", path.file_stem().unwrap().to_string_lossy());
 
-    let size = resized_image.dimensions();
-
-    let mut res = String::new();
-    let mut last_pixel_rgb = resized_image.get_pixel(size.0 - 1, size.1 - 1);
-    let mut is_first_row_pixel = true;
-
-    for y in 0..size.1 {
-        for x in 0..size.0 {
-            let [r, g, b, _] = resized_image.get_pixel(x, y).0;
-
-            macro_rules! colorize {
-                ($input:expr) => {
-                    if options.colorize
-                        && (max_sub(last_pixel_rgb[0], r) > options.compression_threshold
-                            || max_sub(last_pixel_rgb[1], g) > options.compression_threshold
-                            || max_sub(last_pixel_rgb[2], b) > options.compression_threshold
-                            || is_first_row_pixel)
-                        || options.skip_compression
-                    {
-                        res.push_str(&format!(
-                            "\x1b[{}8;2;{r};{g};{b}m{}",
-                            match options.style {
-                                BgPaint | BgOnly => 4,
-                                FgPaint => 3,
-                            },
-                            match options.style {
-                                BgPaint | FgPaint => $input,
-                                BgOnly => ' ',
-                            }
-                        ));
-                    } else {
-                        res.push(match options.style {
-                            BgPaint | FgPaint => $input,
-                            BgOnly => ' ',
-                        });
+            let data = data.unwrap_or_else(|| {
+                repaired.push(path.file_stem().unwrap().to_string_lossy().into_owned());
+                last.as_ref()
+                    .map_or_else(|| compress(b"").unwrap(), |(last, _)| last.clone())
+            });
+            let stored = if let Some(every) = keyframes {
+                let frame = decompress(&data).unwrap();
+                let cells =
+                    AsciiFrame::parse(String::from_utf8_lossy(&frame).trim_end_matches('\n'));
+                let stored = match &last {
+                    Some((_, previous)) if !linked.len().is_multiple_of(every) => {
+                        compress(cells.diff(previous).as_bytes()).unwrap()
                     }
+                    _ => data.clone(),
                 };
-            }
+                last = Some((data, cells));
+                stored
+            } else {
+                last = Some((data.clone(), AsciiFrame::default()));
+                data
+            };
+            archive.add(&name, &stored).unwrap();
+            linked.push((name, stored));
+        }
+    }
+    (linked, repaired)
+}
+
+/// Everything that happens to a frame before it's resized: cropping it,
+/// splitting stereo pairs, then fixing its colors and tones
+fn prepare(image: &DynamicImage, options: Options) -> Cow<'_, DynamicImage> {
+    let mut image = Cow::Borrowed(image);
+
+    if let Some(crop) = options.crop {
+        image = Cow::Owned(crop.apply(&image));
+    }
+    if options.anaglyph {
+        image = Cow::Owned(anaglyph(&image));
+    }
+    if let Some(mode) = options.white_balance {
+        image = Cow::Owned(white_balance(&image, mode));
+    }
+    if !options.adjust.is_identity() {
+        image = Cow::Owned(options.adjust.apply(&image));
+    }
+    image
+}
+
+/// Converts an image into `res`, which gets cleared first so that callers
+/// can reuse it between frames
+fn render_image(image: &DynamicImage, options: Options, res: &mut String) {
+    res.clear();
+    write_image(image, options, res).expect("writing into a String can't fail");
+    if let Some(overlay) = options.overlay {
+        overlay.apply(res, options.color_mode);
+    }
+}
+
+/// Same as [`render_image`], also converting the image without colors into
+/// `plain`, from the same pass over it
+fn render_both(image: &DynamicImage, options: Options, res: &mut String, plain: &mut String) {
+    res.clear();
+    plain.clear();
+    draw_both(
+        image,
+        options,
+        Text::new(res, options.color_mode),
+        Some(&mut *plain),
+    )
+    .expect("writing into a String can't fail");
+    if let Some(overlay) = options.overlay {
+        overlay.apply(res, options.color_mode);
+        Overlay {
+            color: None,
+            ..overlay
+        }
+        .apply(plain, options.color_mode);
+    }
+}
+
+/// Same as [`write_image`], for files and terminals
+fn write_image_io(image: &DynamicImage, options: Options, mut out: impl Write) -> io::Result<()> {
+    // Overlays go over the finished frame, so it can't be streamed
+    if options.overlay.is_some() {
+        let mut text = String::new();
+        render_image(image, options, &mut text);
+        return out.write_all(text.as_bytes());
+    }
+    let mut sink = IoSink {
+        inner: out,
+        error: None,
+    };
+    write_image(image, options, &mut sink).map_err(|_| {
+        sink.error
+            .unwrap_or_else(|| io::Error::other("formatting failed"))
+    })
+}
+
+/// Renders a frame straight into `out`, one cell at a time
+fn write_image(image: &DynamicImage, options: Options, out: &mut impl fmt::Write) -> fmt::Result {
+    draw(image, options, Text::new(out, options.color_mode)).map(drop)
+}
+
+/// Renders a frame into a grid of cells, for code that needs to look at it
+/// rather than print it
+fn frame_cells(image: &DynamicImage, options: Options) -> AsciiFrame {
+    let mut frame = draw(image, options, Cells::new(options.color_mode))
+        .expect("drawing into cells can't fail")
+        .finish();
+    if let Some(overlay) = options.overlay {
+        overlay.apply_cells(&mut frame, options.color_mode);
+    }
+    frame
+}
+
+/// Colors to paint cells with, when they aren't the frame's own: moved onto
+/// `--gradient`, then posterized and dithered. Characters still come from
+/// the original.
+fn cell_colors(image: &DynamicImage, options: Options) -> Option<RgbImage> {
+    let colors = options.gradient.map(|stops| {
+        let mut colors = image.to_rgb8();
+        for pixel in colors.pixels_mut() {
+            pixel.0 = gradient(stops, brightness(options.luminance, pixel.0));
+        }
+        colors
+    });
 
-            match r {
-                0..=20 => colorize!(' '),
-                21..=40 => colorize!('.'),
-                41..=80 => colorize!(':'),
-                81..=100 => colorize!('-'),
-                101..=130 => colorize!('='),
-                131..=200 => colorize!('+'),
-                201..=250 => colorize!('#'),
-                _ => colorize!('@'),
+    // Dithering spreads the error of posterizing too
+    let levels = options.posterize.unwrap_or(u8::MAX);
+    let quantize = |color| displayed(options.color_mode, posterize(color, levels));
+    match options.dither {
+        _ if !options.colorize => None,
+        DitherMode::None if options.posterize.is_some() => {
+            let mut colors = colors.unwrap_or_else(|| image.to_rgb8());
+            for pixel in colors.pixels_mut() {
+                pixel.0 = posterize(pixel.0, levels);
             }
+            Some(colors)
+        }
+        DitherMode::None => colors,
+        DitherMode::FloydSteinberg => {
+            let mut colors = colors.unwrap_or_else(|| image.to_rgb8());
+            floyd_steinberg(&mut colors, quantize);
+            Some(colors)
+        }
+        DitherMode::Bayer => {
+            let mut colors = colors.unwrap_or_else(|| image.to_rgb8());
+            bayer(&mut colors, quantize, color_step(options.color_mode));
+            Some(colors)
+        }
+    }
+}
+
+/// Blank and filled, the only characters `--two-level` draws with, split
+/// where it says
+fn two_level_ramp(image: &DynamicImage, ramp: &Ramp, options: Options) -> Option<[(u8, char); 2]> {
+    let split = match options.two_level? {
+        TwoLevel::Auto => otsu(image.pixels().map(|(_, _, pixel)| {
+            let [r, g, b, _] = pixel.0;
+            brightness(options.luminance, [r, g, b])
+        })),
+        TwoLevel::At(level) => level,
+    };
+    let filled = ramp.last().map_or('#', |&(_, c)| c);
+    Some([(split, ' '), (u8::MAX, filled)])
+}
+
+fn draw<C: Canvas>(image: &DynamicImage, options: Options, canvas: C) -> Result<C, fmt::Error> {
+    draw_both(image, options, canvas, None)
+}
 
-            last_pixel_rgb.0 = [r, g, b, 255];
-            is_first_row_pixel = false;
+/// Same as [`draw`], also writing the frame as it'd be without colors into
+/// `plain` when it's given. Everything up to picking the characters is
+/// shared between the two.
+fn draw_both<C: Canvas>(
+    image: &DynamicImage,
+    options: Options,
+    canvas: C,
+    plain: Option<&mut String>,
+) -> Result<C, fmt::Error> {
+    let mut image = prepare(image, options);
+    let options = fit_options(&mut image, options);
+    let image = image.as_ref();
+
+    // Dots and quadrants are only drawn once per call, so plain ones get
+    // drawn apart
+    let plain_text = |plain| Text::new(plain, options.color_mode);
+    if options.render == RenderMode::Braille {
+        if let Some(plain) = plain {
+            braille(image, plain_options(options), plain_text(plain))?;
         }
-        if options.colorize {
-            res.push_str("\x1b[0m\n");
+        return braille(image, options, canvas);
+    }
+    if options.render == RenderMode::Quadrant {
+        if let Some(plain) = plain {
+            quadrant(image, plain_options(options), plain_text(plain))?;
+        }
+        return quadrant(image, options, canvas);
+    }
+
+    let resized_image = sample(image, options.redimension, options.sampling);
+    // Only sampled a second time when colors are asked to be sampled apart
+    let color_image = (options.colorize && options.color_sampling != options.sampling)
+        .then(|| sample(image, options.redimension, options.color_sampling));
+    let color_image = color_image.as_ref().unwrap_or(&resized_image);
+
+    let size = resized_image.dimensions();
+    let glyphs = options
+        .glyph_match
+        .then(|| match_glyphs(image, options.redimension));
+
+    // Tiny outputs get fewer characters and lean on finer color changes
+    let tiny = options.auto_charset && options.redimension.0 < charset::TINY_WIDTH;
+    let (ramp, options) = if tiny {
+        let threshold = options.compression_threshold.min(4);
+        let options = Options {
+            compression_threshold: threshold,
+            ..options
+        };
+        (charset::TINY, options)
+    } else {
+        (options.charset.unwrap_or(charset::DEFAULT), options)
+    };
+    let two_level = two_level_ramp(&resized_image, ramp, options);
+    let ramp = two_level.as_ref().map_or(ramp, |two_level| &two_level[..]);
+    let edges = options
+        .edges
+        .map(|threshold| edge_glyphs(image, options.redimension, threshold));
+
+    let colors = cell_colors(color_image, options);
+
+    // Mixed frames are line art over the colors, so nothing but the
+    // outlines gets a character
+    let outlines_only = matches!(options.style, Mixed) && options.edges.is_some();
+    // The cell in color, then without
+    let cell = |x, y| {
+        let [r, g, b, _] = resized_image.get_pixel(x, y).0;
+        let level = brightness(options.luminance, [r, g, b]);
+        let rgba = color_image.get_pixel(x, y).0;
+
+        let stroke = edges
+            .as_ref()
+            .and_then(|edges| edges[(y * size.0 + x) as usize]);
+        let input = if let Some(stroke) = stroke {
+            stroke
+        } else if outlines_only {
+            ' '
+        } else if let Some(glyphs) = &glyphs {
+            glyphs[(y * size.0 + x) as usize]
         } else {
-            res.push('\n');
+            charset::pick(ramp, level)
+        };
+
+        let [r, g, b, _] = rgba;
+        if options.chroma_key.is_some_and(|key| key.matches([r, g, b])) {
+            return (Stroke::Clear, Stroke::Clear);
+        }
+
+        let color = colors
+            .as_ref()
+            .map_or([r, g, b], |colors| colors.get_pixel(x, y).0);
+
+        let stroke = style::stroke(options, color, input);
+        let cell = CellContext {
+            x,
+            y,
+            size,
+            rgba,
+            brightness: level,
+        };
+        (
+            style::finish(options, cell, stroke),
+            Stroke::Paint([0; 3], input),
+        )
+    };
+
+    let foreground = matches!(options.style, FgPaint | Shade | Mixed);
+    let mut painter = Painter::new(canvas, options, foreground);
+    let Some(plain) = plain else {
+        painter.rows(size.1, |y| (0..size.0).map(|x| cell(x, y).0).collect())?;
+        return painter.finish();
+    };
+
+    let rows = (0..size.1)
+        .map(|y| (0..size.0).map(|x| cell(x, y)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    painter.rows(size.1, |y| {
+        rows[y as usize].iter().map(|cell| cell.0).collect()
+    })?;
+    let mut plain = Painter::new(plain_text(plain), plain_options(options), false);
+    plain.rows(size.1, |y| {
+        rows[y as usize].iter().map(|cell| cell.1).collect()
+    })?;
+    plain.finish()?;
+    painter.finish()
+}
+
+/// `options` with the size frames get from `--fit`, cutting `image` down to
+/// what `--fit-mode cover` keeps
+fn fit_options(image: &mut Cow<'_, DynamicImage>, options: Options) -> Options {
+    match options.fit {
+        Some(fit) if options.pixel_art => Options {
+            redimension: fit.pixel_grid(image.dimensions()),
+            ..options
+        },
+        Some(fit) if options.fit_mode == FitMode::Cover => {
+            *image = Cow::Owned(fit.cover(image.dimensions()).apply(image));
+            Options {
+                redimension: fit.max,
+                ..options
+            }
         }
-        is_first_row_pixel = true;
+        Some(fit) => Options {
+            redimension: fit.size(image.dimensions()),
+            ..options
+        },
+        None => options,
     }
+}
 
-    Ok(res)
+/// `options` for drawing the same characters without any colors
+fn plain_options(options: Options) -> Options {
+    Options {
+        colorize: false,
+        skip_compression: false,
+        ..options
+    }
 }
@@ -0,0 +1,184 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::error::CompilerError;
+
+/// Seconds of audio between two sync markers
+const SYNC_EVERY: f64 = 5.0;
+
+/// Playback hints stored in the `metadata` entry of an archive, as plain
+/// `key=value` lines. The player uses them as defaults for its own flags.
+#[derive(Debug, Default, Clone)]
+pub struct Metadata {
+    pub framerate: Option<f64>,
+    /// `truecolor`, or `none` for plain text archives
+    pub color_depth: Option<String>,
+    pub loop_stream: Option<bool>,
+    /// Smallest terminal (columns x rows) the frames fit in
+    pub min_size: Option<(u32, u32)>,
+    /// Whether the player should skip frames to keep up with the audio
+    pub drop_frames: Option<bool>,
+    /// Frames, counting from 1, where the player pauses in `--slides` mode
+    pub slides: Option<Vec<usize>>,
+    /// Audio positions in milliseconds of some frames, counting from 1, so
+    /// that the player can jump back in sync after a stall
+    pub sync: Option<Vec<(u64, usize)>>,
+    /// First frame of each scene, counting from 1, and which of the
+    /// `--scene-charsets` it was drawn with
+    pub scenes: Option<Vec<(usize, usize)>>,
+    /// Language of each audio track, in the order they're numbered in
+    pub audio_tracks: Option<Vec<String>>,
+    /// Which part of a split archive this is, counting from 1, and out of
+    /// how many
+    pub part: Option<(usize, usize)>,
+    /// The conversion settings the archive was compiled with, as JSON
+    pub config: Option<String>,
+    /// With `--temporal`, how often a frame is stored whole. The rest only
+    /// hold what changed since the frame before them.
+    pub keyframes: Option<usize>,
+}
+
+impl Metadata {
+    pub fn parse(data: &str) -> Self {
+        let mut metadata = Self::default();
+
+        for (key, value) in data.lines().filter_map(|line| line.split_once('=')) {
+            let value = value.trim();
+            match key.trim() {
+                "framerate" => metadata.framerate = value.parse().ok(),
+                "color_depth" => metadata.color_depth = Some(value.to_string()),
+                "loop" => metadata.loop_stream = value.parse().ok(),
+                "min_size" => {
+                    metadata.min_size = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+                }
+                "drop_frames" => metadata.drop_frames = value.parse().ok(),
+                "slides" => {
+                    metadata.slides = value
+                        .split(',')
+                        .map(|frame| frame.trim().parse().ok())
+                        .collect();
+                }
+                "sync" => {
+                    metadata.sync = value
+                        .split(',')
+                        .map(|marker| {
+                            let (ms, frame) = marker.split_once(':')?;
+                            Some((ms.trim().parse().ok()?, frame.trim().parse().ok()?))
+                        })
+                        .collect();
+                }
+                "scenes" => {
+                    metadata.scenes = value
+                        .split(',')
+                        .map(|scene| {
+                            let (frame, charset) = scene.split_once(':')?;
+                            Some((frame.trim().parse().ok()?, charset.trim().parse().ok()?))
+                        })
+                        .collect();
+                }
+                "audio_tracks" => {
+                    metadata.audio_tracks =
+                        Some(value.split(',').map(|tag| tag.trim().to_string()).collect());
+                }
+                "part" => {
+                    metadata.part = value
+                        .split_once('/')
+                        .and_then(|(part, of)| Some((part.parse().ok()?, of.parse().ok()?)));
+                }
+                "config" => metadata.config = Some(value.to_string()),
+                "keyframes" => metadata.keyframes = value.parse().ok(),
+                _ => (),
+            }
+        }
+
+        metadata
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.to_string().is_empty()
+    }
+
+    /// Refuses archives compiled with `--temporal`, for tools that need every
+    /// frame whole
+    pub fn require_whole_frames(&self, tool: &str) -> Result<(), CompilerError> {
+        match self.keyframes {
+            Some(_) => Err(CompilerError::InvalidInput(format!(
+                "{tool} needs whole frames, compile the archive again without --temporal"
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for Metadata {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(framerate) = self.framerate {
+            writeln!(f, "framerate={framerate}")?;
+        }
+        if let Some(color_depth) = &self.color_depth {
+            writeln!(f, "color_depth={color_depth}")?;
+        }
+        if let Some(loop_stream) = self.loop_stream {
+            writeln!(f, "loop={loop_stream}")?;
+        }
+        if let Some((width, height)) = self.min_size {
+            writeln!(f, "min_size={width}x{height}")?;
+        }
+        if let Some(drop_frames) = self.drop_frames {
+            writeln!(f, "drop_frames={drop_frames}")?;
+        }
+        if let Some(slides) = &self.slides {
+            let slides = slides.iter().map(ToString::to_string).collect::<Vec<_>>();
+            writeln!(f, "slides={}", slides.join(","))?;
+        }
+        if let Some(sync) = &self.sync {
+            let sync = sync
+                .iter()
+                .map(|(ms, frame)| format!("{ms}:{frame}"))
+                .collect::<Vec<_>>();
+            writeln!(f, "sync={}", sync.join(","))?;
+        }
+        if let Some(scenes) = &self.scenes {
+            let scenes = scenes
+                .iter()
+                .map(|(frame, charset)| format!("{frame}:{charset}"))
+                .collect::<Vec<_>>();
+            writeln!(f, "scenes={}", scenes.join(","))?;
+        }
+        if let Some(tracks) = &self.audio_tracks {
+            writeln!(f, "audio_tracks={}", tracks.join(","))?;
+        }
+        if let Some((part, of)) = self.part {
+            writeln!(f, "part={part}/{of}")?;
+        }
+        if let Some(config) = &self.config {
+            writeln!(f, "config={config}")?;
+        }
+        if let Some(keyframes) = self.keyframes {
+            writeln!(f, "keyframes={keyframes}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A marker every few seconds of a video with `frames` frames, each one
+/// holding the exact time its frame is due at
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn sync_markers(framerate: f64, frames: usize) -> Vec<(u64, usize)> {
+    let markers = (frames as f64 / framerate / SYNC_EVERY) as u32;
+    (1..=markers)
+        .map(|marker| (f64::from(marker) * SYNC_EVERY * framerate).round() as usize)
+        .filter(|&frame| frame < frames)
+        .map(|frame| {
+            (
+                ((frame as f64) * 1000.0 / framerate).round() as u64,
+                frame + 1,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,122 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use image::{io::Reader, DynamicImage, ImageError};
+
+/// How much of a file to look through for its EXIF data, which JPEGs keep
+/// in a segment of at most 64KiB near the start
+const HEADER: u64 = 128 * 1024;
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// Decodes the image at `path`. When `oriented`, photos get turned the way
+/// their EXIF orientation says, the way phones expect them to be shown.
+pub fn open_image(path: &Path, oriented: bool) -> Result<DynamicImage, ImageError> {
+    let image = Reader::open(path)?.decode()?;
+    if !oriented {
+        return Ok(image);
+    }
+    Ok(match read_orientation(path)? {
+        Some(orientation) => orient(image, orientation),
+        None => image,
+    })
+}
+
+/// Size of the image at `path` as [`open_image`] would return it, without
+/// decoding it
+pub fn oriented_dimensions(path: &Path, oriented: bool) -> Result<(u32, u32), ImageError> {
+    let (width, height) = image::image_dimensions(path)?;
+    Ok(match read_orientation(path)? {
+        // Turned a quarter, one way or the other
+        Some(5..=8) if oriented => (height, width),
+        _ => (width, height),
+    })
+}
+
+/// The EXIF orientation of the JPEG at `path`, if it has one
+fn read_orientation(path: &Path) -> io::Result<Option<u16>> {
+    let mut header = Vec::new();
+    File::open(path)?.take(HEADER).read_to_end(&mut header)?;
+    Ok(jpeg_exif(&header).and_then(exif_orientation))
+}
+
+/// The EXIF data in the APP1 segment of a JPEG, if it comes before the
+/// image data
+fn jpeg_exif(data: &[u8]) -> Option<&[u8]> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut position = 2;
+    loop {
+        let [0xFF, marker, high, low] = *data.get(position..position + 4)? else {
+            return None;
+        };
+        match marker {
+            // Padding before the actual marker
+            0xFF => {
+                position += 1;
+                continue;
+            }
+            // Start of the image data, or its end
+            0xDA | 0xD9 => return None,
+            _ => (),
+        }
+        let length = usize::from(u16::from_be_bytes([high, low]));
+        let segment = data.get(position + 4..position + 2 + length)?;
+        if marker == 0xE1 {
+            if let Some(exif) = segment.strip_prefix(b"Exif\0\0") {
+                return Some(exif);
+            }
+        }
+        position += 2 + length;
+    }
+}
+
+/// Looks through the first IFD of EXIF data for its orientation
+fn exif_orientation(exif: &[u8]) -> Option<u16> {
+    let big_endian = match exif.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let read_u16 = |at: usize| {
+        let bytes = exif.get(at..at + 2)?.try_into().ok()?;
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let read_u32 = |at: usize| {
+        let bytes = exif.get(at..at + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+
+    let ifd = usize::try_from(read_u32(4)?).ok()?;
+    let entries = read_u16(ifd)?;
+    (0..usize::from(entries))
+        .map(|entry| ifd + 2 + entry * 12)
+        .find(|&entry| read_u16(entry) == Some(ORIENTATION_TAG))
+        // A single short, at the start of the entry's value
+        .and_then(|entry| read_u16(entry + 8))
+}
+
+/// Turns `image` from how it was stored to how it should be shown
+fn orient(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
@@ -0,0 +1,73 @@
+/// Brightness that splits `levels` into a dark and a bright group with as
+/// little spread inside each as possible (Otsu's method). Levels up to it
+/// are the dark ones.
+pub fn otsu(levels: impl Iterator<Item = u8>) -> u8 {
+    let mut histogram = [0u64; 256];
+    for level in levels {
+        histogram[usize::from(level)] += 1;
+    }
+    let total = histogram.iter().sum::<u64>();
+    let sum = (0..)
+        .zip(histogram)
+        .map(|(level, count)| level * count)
+        .sum::<u64>();
+
+    // Maximizing the spread between the groups is the same thing, and only
+    // takes running sums
+    let (mut dark, mut dark_sum) = (0, 0);
+    let (mut best, mut split) = (0.0, 0);
+    for (level, count) in (0..=u8::MAX).zip(histogram) {
+        dark += count;
+        dark_sum += u64::from(level) * count;
+        let bright = total - dark;
+        if dark == 0 {
+            continue;
+        }
+        if bright == 0 {
+            break;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let (dark, bright, dark_sum, bright_sum) = (
+            dark as f64,
+            bright as f64,
+            dark_sum as f64,
+            (sum - dark_sum) as f64,
+        );
+        let difference = dark_sum / dark - bright_sum / bright;
+        let between = dark * bright * difference * difference;
+        if between > best {
+            best = between;
+            split = level;
+        }
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_two_groups() {
+        let levels = [10, 12, 14, 200, 210, 220, 230];
+        let split = otsu(levels.into_iter());
+        assert!((14..200).contains(&split), "{split}");
+        assert_eq!(otsu([10, 200].into_iter()), 10);
+    }
+
+    #[test]
+    fn splits_uneven_groups() {
+        // A few bright pixels on a mostly dark frame still get their own group
+        let levels = (20..60).cycle().take(1000).chain(180..200);
+        let split = otsu(levels);
+        assert!((59..180).contains(&split), "{split}");
+    }
+
+    #[test]
+    fn handles_flat_input() {
+        assert_eq!(otsu(std::iter::empty()), 0);
+        assert_eq!(otsu([128; 16].into_iter()), 0);
+        assert_eq!(otsu([0, 255].into_iter()), 0);
+    }
+}
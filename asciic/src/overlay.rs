@@ -0,0 +1,88 @@
+use crate::{
+    color::palette_color,
+    primitives::{ColorMode, Overlay},
+};
+use asciicore::ansi::{cells, tokenize, AsciiFrame, Cell, Pen, Token};
+
+impl Overlay {
+    /// Writes the text over a rendered frame. Only its row gets drawn again,
+    /// so the rest of the frame stays byte for byte the same.
+    pub fn apply(self, frame: &mut String, mode: ColorMode) {
+        let mut rows = cells(frame);
+        let Some(number) = self.row(rows.len()) else {
+            return;
+        };
+        let row = &mut rows[number];
+        self.stamp(row, mode);
+
+        let start = frame
+            .split_inclusive('\n')
+            .take(number)
+            .map(str::len)
+            .sum::<usize>();
+        let end = frame[start..]
+            .find('\n')
+            .map_or(frame.len(), |end| start + end);
+        let mut pen = pen_after(&frame[..start], Pen::default());
+        // The rows below expect whatever colors this one left behind
+        let left = pen_after(&frame[start..end], pen);
+
+        let mut line = String::new();
+        for cell in row.iter() {
+            if cell.pen != pen {
+                line.push_str(&cell.pen.escape());
+                pen = cell.pen;
+            }
+            line.push(cell.ch);
+        }
+        if pen != left {
+            line.push_str(&left.escape());
+        }
+        frame.replace_range(start..end, &line);
+    }
+
+    /// Same as [`Overlay::apply`], for frames that are still cells
+    pub fn apply_cells(self, frame: &mut AsciiFrame, mode: ColorMode) {
+        if let Some(number) = self.row(frame.height) {
+            let width = frame.width;
+            self.stamp(&mut frame.cells[number * width..][..width], mode);
+        }
+    }
+
+    fn row(self, height: usize) -> Option<usize> {
+        let row = match usize::try_from(self.y) {
+            Ok(y) => Some(y),
+            Err(_) => height.checked_sub(self.y.unsigned_abs() as usize),
+        };
+        row.filter(|&row| row < height)
+    }
+
+    /// Puts the text into `row`, cutting off whatever falls past its ends
+    fn stamp(self, row: &mut [Cell], mode: ColorMode) {
+        let len = self.text.chars().count();
+        let (start, skip) = if let Ok(x) = usize::try_from(self.x) {
+            (x, 0)
+        } else {
+            let end = (row.len() + 1).saturating_sub(self.x.unsigned_abs() as usize);
+            (end.saturating_sub(len), len.saturating_sub(end))
+        };
+        let fg = self.color.map(|color| palette_color(mode, color));
+
+        for (cell, ch) in row.iter_mut().skip(start).zip(self.text.chars().skip(skip)) {
+            cell.ch = ch;
+            if fg.is_some() {
+                cell.pen.fg = fg;
+            }
+        }
+    }
+}
+
+/// Colors in effect once `text` has been printed with `pen`
+fn pen_after(text: &str, mut pen: Pen) -> Pen {
+    for token in tokenize(text) {
+        if let Token::Sgr(params) = token {
+            pen.apply(&params);
+        }
+    }
+    pen
+}
@@ -0,0 +1,329 @@
+use std::{
+    fmt::{self, Write},
+    io,
+};
+
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    color::{difference, palette_color, EscapeCache, Rgb},
+    primitives::{ColorMode, FitMode, Options},
+};
+use asciicore::ansi::{AsciiFrame, Cell, Pen};
+
+/// Blocks from empty to full, with roughly how much of the cell they cover
+/// out of 255
+const SHADES: [(u8, char); 5] = [(16, ' '), (64, '░'), (128, '▒'), (192, '▓'), (255, '█')];
+
+/// A cell's worth of painting, worked out ahead of time so that rows can be
+/// computed in any order and still get painted one after the other
+#[derive(Debug, Clone, Copy)]
+pub enum Stroke {
+    Paint(Rgb, char),
+    /// Foreground, background and character
+    Both(Rgb, Rgb, char),
+    Clear,
+    Shade(Rgb),
+}
+
+/// What painters draw on: text with escape sequences for terminals, or a
+/// grid of cells for code that wants to look at the frame
+pub trait Canvas {
+    /// Switches either the foreground or the background to `color`
+    fn color(&mut self, foreground: bool, color: Rgb) -> fmt::Result;
+    /// Goes back to the terminal's own colors
+    fn reset(&mut self) -> fmt::Result;
+    fn put(&mut self, ch: char) -> fmt::Result;
+    fn end_row(&mut self) -> fmt::Result;
+}
+
+/// Writes cells into a frame, only switching colors when they changed by
+/// more than the compression threshold since the previous cell. Colors carry
+/// over from one row to the next, and only get reset once the frame is done.
+pub struct Painter<C: Canvas> {
+    canvas: C,
+    foreground: bool,
+    last: Option<Rgb>,
+    last_background: Option<Rgb>,
+    /// Rows end once the next one starts, so that the final reset can go
+    /// before the last newline and the terminal never scrolls in color
+    row_ended: bool,
+    options: Options,
+}
+
+impl<C: Canvas> Painter<C> {
+    pub fn new(canvas: C, options: Options, foreground: bool) -> Self {
+        Self {
+            canvas,
+            foreground,
+            last: None,
+            last_background: None,
+            row_ended: false,
+            options,
+        }
+    }
+
+    pub fn paint(&mut self, color: Rgb, ch: char) -> fmt::Result {
+        self.start_cell()?;
+        if self.should_switch(self.last, color) {
+            self.canvas.color(self.foreground, color)?;
+        }
+        self.canvas.put(ch)?;
+        self.last = Some(color);
+        Ok(())
+    }
+
+    /// Same as [`Painter::paint`], but sets the background too, no matter
+    /// which layer the painter was made for
+    pub fn paint_both(&mut self, foreground: Rgb, background: Rgb, ch: char) -> fmt::Result {
+        if self.should_switch(self.last_background, background) {
+            self.canvas.color(false, background)?;
+        }
+        self.last_background = Some(background);
+        self.paint(foreground, ch)
+    }
+
+    /// Leaves the cell blank in the terminal's own colors
+    pub fn clear(&mut self) -> fmt::Result {
+        self.start_cell()?;
+        if self.options.colorize && (self.last.is_some() || self.last_background.is_some()) {
+            self.canvas.reset()?;
+        }
+        self.canvas.put(' ')?;
+        self.last = None;
+        self.last_background = None;
+        Ok(())
+    }
+
+    /// Draws `color` as a shading block over black, picking the lightest
+    /// block that can reach it and brightening the foreground to make up for
+    /// the gaps. Flat areas then share a foreground and need no escapes.
+    pub fn shade(&mut self, color: Rgb) -> fmt::Result {
+        let peak = color.into_iter().max().unwrap_or(0);
+        let Some(&(density, ch)) = SHADES
+            .iter()
+            .find(|(density, _)| u16::from(peak) <= u16::from(*density))
+        else {
+            unreachable!("the last shade is fully dense")
+        };
+
+        #[allow(clippy::cast_possible_truncation)]
+        // Empty cells don't show their foreground, so they keep the last one
+        let foreground = if ch == ' ' {
+            self.last.unwrap_or_default()
+        } else {
+            color.map(|c| (u16::from(c) * 255 / u16::from(density)).min(255) as u8)
+        };
+        self.paint_both(foreground, [0; 3], ch)
+    }
+
+    fn should_switch(&self, last: Option<Rgb>, color: Rgb) -> bool {
+        let changed = last.is_none_or(|last| {
+            difference(self.options.threshold_metric, last, color)
+                > f32::from(self.options.compression_threshold)
+        });
+
+        self.options.colorize && changed || self.options.skip_compression
+    }
+
+    pub fn stroke(&mut self, stroke: Stroke) -> fmt::Result {
+        match stroke {
+            Stroke::Paint(color, ch) => self.paint(color, ch),
+            Stroke::Both(foreground, background, ch) => self.paint_both(foreground, background, ch),
+            Stroke::Clear => self.clear(),
+            Stroke::Shade(color) => self.shade(color),
+        }
+    }
+
+    /// Paints `height` rows made by `row`. With the `parallel` feature, rows
+    /// are all worked out at once on the thread pool, then painted in order,
+    /// so that the output is the same either way. Contained frames get
+    /// centered in blank cells, up to the size they're fit in.
+    pub fn rows<F>(&mut self, height: u32, row: F) -> fmt::Result
+    where
+        F: Fn(u32) -> Vec<Stroke> + Send + Sync,
+    {
+        #[cfg(feature = "parallel")]
+        let rows = (0..height).into_par_iter().map(row).collect::<Vec<_>>();
+        #[cfg(not(feature = "parallel"))]
+        let rows = (0..height).map(row);
+
+        let (left, top, right, bottom) = self.padding(height);
+        for _ in 0..top {
+            self.blank_row(left + self.options.redimension.0 + right)?;
+        }
+        for strokes in rows {
+            for _ in 0..left {
+                self.clear()?;
+            }
+            for stroke in strokes {
+                self.stroke(stroke)?;
+            }
+            for _ in 0..right {
+                self.clear()?;
+            }
+            self.end_row()?;
+        }
+        for _ in 0..bottom {
+            self.blank_row(left + self.options.redimension.0 + right)?;
+        }
+        Ok(())
+    }
+
+    /// Blank cells to the left, top, right and bottom of `height` rows
+    fn padding(&self, height: u32) -> (u32, u32, u32, u32) {
+        let Some(fit) = self
+            .options
+            .fit
+            .filter(|_| self.options.fit_mode == FitMode::Contain)
+        else {
+            return (0, 0, 0, 0);
+        };
+        let across = fit.max.0.saturating_sub(self.options.redimension.0);
+        let down = fit.max.1.saturating_sub(height);
+        (across / 2, down / 2, across - across / 2, down - down / 2)
+    }
+
+    fn blank_row(&mut self, width: u32) -> fmt::Result {
+        for _ in 0..width {
+            self.clear()?;
+        }
+        self.end_row()
+    }
+
+    pub fn end_row(&mut self) -> fmt::Result {
+        self.start_cell()?;
+        self.row_ended = true;
+        Ok(())
+    }
+
+    fn start_cell(&mut self) -> fmt::Result {
+        if self.row_ended {
+            self.row_ended = false;
+            self.canvas.end_row()?;
+        }
+        Ok(())
+    }
+
+    /// Goes back to the terminal's own colors and ends the last row
+    pub fn finish(mut self) -> Result<C, fmt::Error> {
+        if self.options.colorize && (self.last.is_some() || self.last_background.is_some()) {
+            self.canvas.reset()?;
+        }
+        self.start_cell()?;
+        Ok(self.canvas)
+    }
+}
+
+/// Draws into text, the way frames get stored and printed
+pub struct Text<'a, W: Write> {
+    out: &'a mut W,
+    foreground: EscapeCache,
+    background: EscapeCache,
+}
+
+impl<'a, W: Write> Text<'a, W> {
+    pub fn new(out: &'a mut W, mode: ColorMode) -> Self {
+        Self {
+            out,
+            foreground: EscapeCache::new(mode, true),
+            background: EscapeCache::new(mode, false),
+        }
+    }
+}
+
+impl<W: Write> Canvas for Text<'_, W> {
+    fn color(&mut self, foreground: bool, color: Rgb) -> fmt::Result {
+        let cache = if foreground {
+            &mut self.foreground
+        } else {
+            &mut self.background
+        };
+        self.out.write_str(cache.get(color))
+    }
+
+    fn reset(&mut self) -> fmt::Result {
+        self.out.write_str("\x1b[0m")
+    }
+
+    fn put(&mut self, ch: char) -> fmt::Result {
+        self.out.write_char(ch)
+    }
+
+    fn end_row(&mut self) -> fmt::Result {
+        self.out.write_char('\n')
+    }
+}
+
+/// Draws into an [`AsciiFrame`], with colors as the terminal would show them
+pub struct Cells {
+    frame: AsciiFrame,
+    row: usize,
+    pen: Pen,
+    mode: ColorMode,
+}
+
+impl Cells {
+    pub fn new(mode: ColorMode) -> Self {
+        Self {
+            frame: AsciiFrame::default(),
+            row: 0,
+            pen: Pen::default(),
+            mode,
+        }
+    }
+
+    pub fn finish(self) -> AsciiFrame {
+        self.frame
+    }
+}
+
+impl Canvas for Cells {
+    fn color(&mut self, foreground: bool, color: Rgb) -> fmt::Result {
+        let color = Some(palette_color(self.mode, color));
+        if foreground {
+            self.pen.fg = color;
+        } else {
+            self.pen.bg = color;
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) -> fmt::Result {
+        self.pen = Pen::default();
+        Ok(())
+    }
+
+    fn put(&mut self, ch: char) -> fmt::Result {
+        self.frame.cells.push(Cell { ch, pen: self.pen });
+        self.row += 1;
+        Ok(())
+    }
+
+    fn end_row(&mut self) -> fmt::Result {
+        // Painters always draw rows of the same width
+        if self.frame.height == 0 {
+            self.frame.width = self.row;
+        }
+        self.frame.height += 1;
+        self.row = 0;
+        Ok(())
+    }
+}
+
+/// Lets painters write straight into an [`io::Write`], holding on to the
+/// error that [`fmt::Write`] has no room for
+pub struct IoSink<W> {
+    pub inner: W,
+    pub error: Option<io::Error>,
+}
+
+impl<W: io::Write> Write for IoSink<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
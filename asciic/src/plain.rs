@@ -0,0 +1,62 @@
+use crate::{
+    charset,
+    error::CompilerError,
+    primitives::{Options, OutputSize, PlainProfile, RenderMode},
+};
+
+impl PlainProfile {
+    /// Longest line the profile allows
+    pub fn width(self) -> u32 {
+        match self {
+            // Room for a comment marker and some indentation
+            Self::Comment => 76,
+            Self::Commit => 72,
+            Self::Email => 78,
+        }
+    }
+
+    /// `options`, narrowed down to the profile's width
+    pub fn limit(self, options: Options) -> Result<Options, CompilerError> {
+        if options.render != RenderMode::Ascii {
+            return Err(CompilerError::InvalidInput(
+                "--plain-profile only works with --render ascii".into(),
+            ));
+        }
+        let ramp = options.charset.unwrap_or(charset::DEFAULT);
+        if let Some(&(_, ch)) = ramp.iter().find(|(_, ch)| !is_plain(*ch)) {
+            return Err(CompilerError::InvalidInput(format!(
+                "--plain-profile only takes printable ASCII characters, not {ch:?}"
+            )));
+        }
+
+        let OutputSize(width, height) = options.redimension;
+        Ok(Options {
+            redimension: OutputSize(width.min(self.width()), height),
+            fit: options.fit.map(|mut fit| {
+                fit.max.0 = fit.max.0.min(self.width());
+                fit
+            }),
+            ..options
+        })
+    }
+}
+
+/// Cleans a rendered frame up for pasting: trailing spaces go, and anything
+/// that isn't printable ASCII becomes a space, so that nothing can sneak past
+/// the checks in [`PlainProfile::limit`]
+pub fn tidy(frame: &str) -> String {
+    let mut plain = String::with_capacity(frame.len());
+    for line in frame.lines() {
+        let line = line
+            .chars()
+            .map(|ch| if is_plain(ch) { ch } else { ' ' })
+            .collect::<String>();
+        plain.push_str(line.trim_end());
+        plain.push('\n');
+    }
+    plain
+}
+
+fn is_plain(ch: char) -> bool {
+    ch == ' ' || ch.is_ascii_graphic()
+}
@@ -0,0 +1,55 @@
+use std::{
+    fmt::Write as _,
+    io::{stdout, Write},
+    path::Path,
+    sync::OnceLock,
+};
+
+use asciicore::termcaps::terminal_size;
+
+use asciicore::ansi::{render_row, AsciiFrame};
+
+/// Most columns and rows the preview takes up
+const PREVIEW_SIZE: (usize, usize) = (48, 16);
+
+/// Shows `frame`, converted from the image at `path`, next to the progress
+/// output. See [`preview`].
+pub fn show_preview(frame: &str, path: &Path) {
+    // Asked once, since it takes running stty
+    static COLUMNS: OnceLock<usize> = OnceLock::new();
+    let columns =
+        *COLUMNS.get_or_init(|| terminal_size().map_or(80, |(columns, _)| columns as usize));
+
+    let label = format!("Frame {}", path.file_stem().unwrap_or_default().display());
+    // A single write, so that other threads can't print in between the frame
+    stdout()
+        .lock()
+        .write_all(preview(frame, &label, columns).as_bytes())
+        .ok();
+}
+
+/// Draws a shrunk copy of `frame` in the top right corner of a terminal
+/// `columns` wide, with `label` under it. The cursor gets saved and restored
+/// around it, so the progress output carries on from where it was and the
+/// rest of the screen is left alone.
+pub fn preview(frame: &str, label: &str, columns: usize) -> String {
+    let frame = AsciiFrame::parse(frame.trim_end_matches('\n'));
+    // Same step both ways, so that the preview keeps the frame's proportions
+    let step = frame
+        .width
+        .div_ceil(PREVIEW_SIZE.0)
+        .max(frame.height.div_ceil(PREVIEW_SIZE.1))
+        .max(1);
+    let width = frame.width.div_ceil(step).max(label.chars().count());
+    let column = columns.saturating_sub(width) + 1;
+
+    let mut out = String::from("\x1b7");
+    let mut line = 1;
+    for row in frame.rows().step_by(step) {
+        let row = row.iter().step_by(step).copied().collect::<Vec<_>>();
+        write!(out, "\x1b[{line};{column}H{}", render_row(&row)).unwrap();
+        line += 1;
+    }
+    write!(out, "\x1b[{line};{column}H{label:<width$}\x1b8").unwrap();
+    out
+}
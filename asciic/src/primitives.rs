@@ -3,24 +3,463 @@ use clap::{
     ErrorKind, ValueEnum,
 };
 
-#[derive(Clone, Copy)]
+use std::hash::{Hash, Hasher};
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::{
+    adjust::Adjustments,
+    charset::Ramp,
+    color::{distance, Rgb},
+    error::CompilerError,
+};
+
+#[derive(Clone, Copy, Hash)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Options {
     pub compression_threshold: u8,
+    /// How color changes get measured against `compression_threshold`
+    pub threshold_metric: ThresholdMetric,
     pub redimension: OutputSize,
+    /// Replaces `redimension` with a size that keeps each frame's aspect ratio
+    pub fit: Option<Fit>,
+    /// What happens to frames that don't have the proportions of `fit`
+    pub fit_mode: FitMode,
+    /// Region of each frame to convert, before anything else happens to it
+    pub crop: Option<Crop>,
+    /// Snaps `fit` to whole cells per source pixel, for crisp sprites
+    pub pixel_art: bool,
     pub skip_compression: bool,
     pub style: PaintStyle,
     pub colorize: bool,
-    pub skip_audio: bool,
+    pub color_mode: ColorMode,
+    pub dither: DitherMode,
+    pub glyph_match: bool,
+    pub auto_charset: bool,
+    /// Replaces the built in ramp
+    pub charset: Option<&'static Ramp>,
+    /// Frames are side by side stereo pairs, drawn as a red/cyan anaglyph
+    pub anaglyph: bool,
+    /// Sobel magnitude above which cells get a directional stroke
+    pub edges: Option<u16>,
+    pub render: RenderMode,
+    /// How each cell's pixel is taken from the frame, for picking characters
+    pub sampling: Sampling,
+    /// Same as `sampling`, for the cell's color
+    pub color_sampling: Sampling,
+    pub luminance: Luminance,
+    pub dot_threshold: u8,
+    /// Only draws blank and filled cells, split at this brightness
+    pub two_level: Option<TwoLevel>,
+    pub adjust: Adjustments,
+    pub white_balance: Option<WhiteBalance>,
+    pub chroma_key: Option<ChromaKey>,
+    pub effect: Option<Effect>,
+    /// Replaces frames' own colors with their brightness along these stops
+    pub gradient: Option<&'static [Rgb]>,
+    /// Levels per channel that colors get snapped to
+    pub posterize: Option<u8>,
+    /// Fixed background behind `FgPaint` cells
+    pub background: Option<Rgb>,
+    /// `BgOnly` cells with no channel above this get `dark_fill`, or nothing
+    pub dark_cutoff: Option<u8>,
+    pub dark_fill: Option<Rgb>,
+    /// Text written over every frame once it's drawn
+    pub overlay: Option<Overlay>,
+    pub audio: AudioMode,
+}
+
+/// Same as the command line defaults, minus the audio
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            compression_threshold: 10,
+            threshold_metric: ThresholdMetric::MaxChannel,
+            redimension: OutputSize(216, 56),
+            fit: None,
+            fit_mode: FitMode::Stretch,
+            crop: None,
+            pixel_art: false,
+            skip_compression: false,
+            style: PaintStyle::BgPaint,
+            colorize: false,
+            color_mode: ColorMode::Truecolor,
+            dither: DitherMode::None,
+            glyph_match: false,
+            auto_charset: false,
+            charset: None,
+            anaglyph: false,
+            edges: None,
+            render: RenderMode::Ascii,
+            sampling: Sampling::Nearest,
+            color_sampling: Sampling::Nearest,
+            luminance: Luminance::Red,
+            dot_threshold: 127,
+            two_level: None,
+            adjust: Adjustments::default(),
+            white_balance: None,
+            chroma_key: None,
+            effect: None,
+            gradient: None,
+            posterize: None,
+            background: None,
+            dark_cutoff: None,
+            dark_fill: None,
+            overlay: None,
+            audio: AudioMode::Skip,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioMode {
+    Skip,
+    Extract,
+    DuckMusic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum RenderMode {
+    /// One character per pixel, picked by brightness
+    Ascii,
+    /// Braille dots, 2x4 pixels per character
+    Braille,
+    /// Quadrant blocks, 2x2 pixels per character with a color for each half
+    Quadrant,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Sampling {
+    /// A single pixel of the ones a cell covers
+    Nearest,
+    /// The average of all the pixels a cell covers, which keeps fine detail
+    /// from turning into speckles
+    Area,
+}
+
+/// Where `--two-level` splits blank cells from filled ones
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TwoLevel {
+    /// Worked out for each frame on its own, see [`crate::otsu`]
+    Auto,
+    /// Cells up to this brightness are blank
+    At(u8),
+}
+
+/// Color that gets keyed out into blank cells, like a green screen
+#[derive(Debug, Clone, Copy, Hash)]
+pub struct ChromaKey {
+    pub color: Rgb,
+    /// Largest distance from `color` that still counts as the key
+    pub tolerance: u8,
+}
+
+impl ChromaKey {
+    pub fn matches(self, color: Rgb) -> bool {
+        distance(self.color, color) <= u32::from(self.tolerance).pow(2)
+    }
+}
+
+/// Region of the source that gets converted, in pixels
+#[derive(Debug, Clone, Copy, Hash)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Crop {
+    /// Makes sure the region fits in a source of the given size, before
+    /// anything gets converted
+    pub fn check(self, (width, height): (u32, u32)) -> Result<(), CompilerError> {
+        let fits = |start: u32, length: u32, limit| {
+            length > 0 && start.checked_add(length).is_some_and(|end| end <= limit)
+        };
+        if fits(self.x, self.width, width) && fits(self.y, self.height, height) {
+            return Ok(());
+        }
+        Err(CompilerError::InvalidInput(format!(
+            "--crop {}x{}+{}+{} doesn't fit in the {width}x{height} source",
+            self.width, self.height, self.x, self.y
+        )))
+    }
+
+    /// Cuts the region out of `image`, which should have gone through
+    /// [`Crop::check`]. Frames that come out smaller than the first one
+    /// still get cut short at their edges, instead of failing halfway through.
+    pub fn apply(self, image: &DynamicImage) -> DynamicImage {
+        let (width, height) = image.dimensions();
+        let x = self.x.min(width.saturating_sub(1));
+        let y = self.y.min(height.saturating_sub(1));
+        image.crop_imm(x, y, self.width.min(width - x), self.height.min(height - y))
+    }
+}
+
+/// Text written over frames, like credits or a watermark. Negative
+/// positions count from the right and bottom edges instead, with -1 putting
+/// the text against them.
+#[derive(Debug, Clone, Copy, Hash)]
+pub struct Overlay {
+    pub text: &'static str,
+    pub x: i32,
+    pub y: i32,
+    /// Keeps the colors of the cells underneath when unset
+    pub color: Option<Rgb>,
+}
+
+/// How frames get to exactly `--size` when their proportions don't match it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum FitMode {
+    /// Squishes or stretches the frame to fill the whole size
+    Stretch,
+    /// Fits the whole frame in, with blank cells around it
+    Contain,
+    /// Fills the whole size, cutting off whatever doesn't fit
+    Cover,
+}
+
+/// How color casts get estimated and taken out of each frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum WhiteBalance {
+    /// Assumes that the frame averages out to gray
+    GrayWorld,
+    /// Assumes that the brightest parts of the frame are white
+    WhitePatch,
+}
+
+/// How the brightness that picks characters is worked out from a color
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Luminance {
+    /// The red channel alone, which is fine for grayscale sources
+    Red,
+    /// The brightest channel
+    MaxChannel,
+    /// The mean of all channels
+    Average,
+    /// Perceived brightness, weighted like SD video
+    Rec601,
+    /// Perceived brightness, weighted like HD video
+    Rec709,
+}
+
+/// How far apart two colors are, for the compression threshold
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum ThresholdMetric {
+    /// The biggest change in any one channel
+    MaxChannel,
+    /// The straight line distance between them
+    Euclidean,
+    /// How different they look, in hundredths of a deltaE in Oklab. Around
+    /// 2 is the smallest difference anyone notices
+    Oklab,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum DitherMode {
+    None,
+    FloydSteinberg,
+    /// Ordered dithering, which doesn't flicker between similar frames
+    Bayer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Transition {
+    None,
+    /// Crossfades into the next slide
+    Fade,
+    /// Uncovers the next slide from left to right
+    Wipe,
+}
+
+/// What `-i` writes each image into
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Text with escape sequences, for terminals
+    Text,
+    /// A `<pre>` block with inline styles, for web pages
+    Html,
+    /// Rows of `<text>` on their background rects, for posters and READMEs
+    Svg,
+    /// The characters drawn into a PNG with the built-in font
+    Png,
+}
+
+/// Built in per cell effects, see [`crate::style`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Effect {
+    /// Dims every other row
+    Scanlines,
+    /// Darkens the edges
+    Vignette,
+    /// Recolors everything along a diagonal rainbow
+    Rainbow,
+}
+
+/// How the entries of an archive are laid out on disk
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Container {
+    /// A plain tar file, which every version of asciix plays
+    #[default]
+    Tar,
+    /// Opens without reading the whole archive, and takes new entries
+    /// without being rewritten
+    V3,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+/// Built in charsets `--charset` takes by name, see `asciic charsets`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CharsetPreset {
+    /// A few characters that stay easy to tell apart at small sizes
+    Minimal,
+    /// The ramp asciic draws with when no charset is given
+    Standard,
+    /// Paul Bourke's 70 character ramp, for large frames
+    Detailed70,
+    /// Shade blocks, for solid looking frames
+    Blocks,
+    /// Braille cells with more and more dots
+    Dots,
+}
+
+/// Limits for text that gets pasted somewhere, see [`crate::plain`]
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PlainProfile {
+    /// Fits in a code comment, 76 columns
+    Comment,
+    /// Fits in a commit message body, 72 columns
+    Commit,
+    /// Fits in a plain text email, 78 columns
+    Email,
 }
 
+/// Sets of compile flags for `asciic daemon`
 #[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Preset {
+    /// Plain characters at the default size
+    Mono,
+    /// Colors at the default size
+    Color,
+    /// Small enough to post, in the colors Discord's ansi code blocks show
+    Discord,
+}
+
+#[derive(Clone, Copy, Debug, Hash, ValueEnum)]
 pub enum PaintStyle {
     FgPaint,
     BgPaint,
     BgOnly,
+    /// Shading blocks over black, with tones coming from how dense the block is
+    Shade,
+    /// Colors behind the characters, which get drawn in black or white on
+    /// top. With --edges, only the outlines get characters.
+    Mixed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum ColorMode {
+    /// 24-bit colors
+    Truecolor,
+    /// The xterm 256 color palette
+    Ansi256,
+    /// The 16 base colors, for the Linux console and serial terminals
+    Ansi16,
+    /// Shades of gray, in 24-bit colors
+    Gray,
+    /// The 24 shades of the xterm gray ramp
+    Gray256,
 }
 
+/// Fits frames inside of `max` without squishing them, given how many times
+/// taller than wide terminal cells are
 #[derive(Debug, Clone, Copy)]
+pub struct Fit {
+    pub max: OutputSize,
+    pub cell_aspect: f32,
+}
+
+impl Fit {
+    /// Columns and rows for an image of `width` by `height` pixels
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn size(&self, (width, height): (u32, u32)) -> OutputSize {
+        // Columns per row that keep the picture's proportions
+        let aspect = width as f32 / height.max(1) as f32 * self.cell_aspect;
+
+        let rows = (self.max.0 as f32 / aspect).round() as u32;
+        if rows <= self.max.1 {
+            OutputSize(self.max.0, rows.max(1))
+        } else {
+            let columns = (self.max.1 as f32 * aspect).round() as u32;
+            OutputSize(columns.clamp(1, self.max.0), self.max.1)
+        }
+    }
+
+    /// Like [`Fit::size`], but every pixel gets the same whole number of
+    /// cells, or every cell the same whole number of pixels when the image is
+    /// too big for that, so that nearest neighbor sampling never has to pick
+    /// between two pixels
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn pixel_grid(&self, (width, height): (u32, u32)) -> OutputSize {
+        // Cells side by side that make up a square
+        let columns = (self.cell_aspect.round() as u32).max(1);
+        let (width, height) = (width.max(1), height.max(1));
+
+        let scale = (self.max.0 / (width * columns)).min(self.max.1 / height);
+        if scale > 0 {
+            return OutputSize(width * columns * scale, height * scale);
+        }
+
+        // Each cell takes `step` pixels across and `step * columns` down
+        let step = width
+            .div_ceil(self.max.0.max(1))
+            .max(height.div_ceil(self.max.1.max(1) * columns));
+        OutputSize((width / step).max(1), (height / (step * columns)).max(1))
+    }
+
+    /// The middle of an image of `width` by `height` pixels that has the
+    /// proportions of `max`, for [`FitMode::Cover`]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn cover(&self, (width, height): (u32, u32)) -> Crop {
+        // Pixels across for every pixel down, once the frame is filled
+        let aspect = self.max.0 as f32 / (self.max.1.max(1) as f32 * self.cell_aspect);
+
+        let (cropped_width, cropped_height) = if width as f32 / height.max(1) as f32 > aspect {
+            ((height as f32 * aspect).round() as u32, height)
+        } else {
+            (width, (width as f32 / aspect).round() as u32)
+        };
+        let (cropped_width, cropped_height) = (cropped_width.max(1), cropped_height.max(1));
+        Crop {
+            x: (width - cropped_width.min(width)) / 2,
+            y: (height - cropped_height.min(height)) / 2,
+            width: cropped_width,
+            height: cropped_height,
+        }
+    }
+}
+
+impl Hash for Fit {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.max.hash(state);
+        self.cell_aspect.to_bits().hash(state);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
 pub struct OutputSize(pub u32, pub u32);
 impl ValueParserFactory for OutputSize {
     type Parser = OutputSizeParser;
@@ -0,0 +1,74 @@
+use std::{error::Error, io::ErrorKind, process::Command};
+
+use crate::error::CompilerError;
+
+/// What ffprobe has to say about the first video stream of a file
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VideoInfo {
+    pub frames: Option<u64>,
+    pub framerate: Option<f64>,
+    pub duration: Option<f64>,
+}
+
+impl VideoInfo {
+    /// Expected amount of frames, falling back to duration * framerate when
+    /// the container doesn't report a frame count.
+    pub fn expected_frames(&self) -> Option<u64> {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        self.frames.or_else(|| {
+            self.duration
+                .zip(self.framerate)
+                .map(|(duration, rate)| (duration * rate).round() as u64)
+        })
+    }
+}
+
+pub fn ffprobe(video: &str) -> Result<VideoInfo, Box<dyn Error>> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-count_packets",
+            "-show_entries",
+            "stream=nb_read_packets,r_frame_rate,duration",
+            "-of",
+            "default=noprint_wrappers=1",
+            video,
+        ])
+        .output()
+        .map_err(|error| -> Box<dyn Error> {
+            if error.kind() == ErrorKind::NotFound {
+                CompilerError::MissingTool("ffprobe".into()).into()
+            } else {
+                error.into()
+            }
+        })?;
+
+    if !output.status.success() {
+        return Err(CompilerError::ToolFailed("FFPROBE failed to run".into()).into());
+    }
+
+    let mut info = VideoInfo::default();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key {
+            "nb_read_packets" => info.frames = value.parse().ok(),
+            "r_frame_rate" => info.framerate = parse_rate(value),
+            "duration" => info.duration = value.parse().ok(),
+            _ => (),
+        }
+    }
+
+    Ok(info)
+}
+
+/// Parses ffmpeg rationals like `30000/1001`
+fn parse_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+    (den != 0.0 && num != 0.0).then(|| num / den)
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+
+use image::{imageops::FilterType, DynamicImage};
+
+use crate::{
+    color::{distance, luma, Rgb},
+    paint::{Canvas, Painter, Stroke},
+    primitives::Options,
+};
+
+/// Quadrant characters, indexed by their lit quadrants: top left is 1, top
+/// right is 2, bottom left is 4 and bottom right is 8
+pub const BLOCKS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// Patterns tried on colored frames. Swapping the colors covers the three
+/// quarter blocks, and the full block goes first so that it wins ties.
+const CANDIDATES: [u8; 11] = [15, 1, 2, 4, 8, 9, 6, 5, 10, 3, 12];
+
+/// Draws the image with quadrant blocks. Colored frames pick the pattern and
+/// the pair of colors that stray the least from the 4 pixels of each cell,
+/// while uncolored ones light the quadrants brighter than the dot threshold.
+pub fn quadrant<C: Canvas>(
+    image: &DynamicImage,
+    options: Options,
+    canvas: C,
+) -> Result<C, fmt::Error> {
+    let (width, height) = (options.redimension.0, options.redimension.1);
+    let resized = image
+        .resize_exact(width * 2, height * 2, FilterType::Triangle)
+        .to_rgb8();
+
+    let mut painter = Painter::new(canvas, options, true);
+    painter.rows(height, |row| {
+        (0..width)
+            .map(|column| {
+                let pixels = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                    .map(|(x, y)| resized.get_pixel(column * 2 + x, row * 2 + y).0);
+
+                if options.colorize {
+                    let (pattern, foreground, background) = best_fit(pixels);
+                    Stroke::Both(foreground, background, BLOCKS[usize::from(pattern)])
+                } else {
+                    let pattern = (0..4)
+                        .filter(|&i| luma(pixels[i]) > options.dot_threshold)
+                        .fold(0, |pattern, i| pattern | 1 << i);
+                    Stroke::Paint([0; 3], BLOCKS[pattern])
+                }
+            })
+            .collect()
+    })?;
+    painter.finish()
+}
+
+/// Pattern, foreground and background with the smallest error
+fn best_fit(pixels: [Rgb; 4]) -> (u8, Rgb, Rgb) {
+    CANDIDATES
+        .iter()
+        .map(|&pattern| {
+            // Every candidate lights at least one quadrant
+            let foreground = average(&pixels, |i| pattern & 1 << i != 0).unwrap();
+            let background = average(&pixels, |i| pattern & 1 << i == 0).unwrap_or(foreground);
+
+            let error: u32 = (0..4)
+                .map(|i| {
+                    let color = if pattern & 1 << i != 0 {
+                        foreground
+                    } else {
+                        background
+                    };
+                    distance(color, pixels[i])
+                })
+                .sum();
+            (error, pattern, foreground, background)
+        })
+        .min_by_key(|&(error, ..)| error)
+        .map(|(_, pattern, foreground, background)| (pattern, foreground, background))
+        .unwrap()
+}
+
+/// Average color of the pixels picked by `filter`, if any
+fn average(pixels: &[Rgb; 4], filter: impl Fn(usize) -> bool) -> Option<Rgb> {
+    let picked: Vec<Rgb> = (0..4).filter(|&i| filter(i)).map(|i| pixels[i]).collect();
+    if picked.is_empty() {
+        return None;
+    }
+
+    let mut sum = [0u32; 3];
+    for pixel in &picked {
+        for (sum, &channel) in sum.iter_mut().zip(pixel) {
+            *sum += u32::from(channel);
+        }
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let average = sum.map(|sum| (sum / picked.len() as u32) as u8);
+    Some(average)
+}
@@ -0,0 +1,208 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, stdin, BufRead, BufReader},
+    path::PathBuf,
+};
+
+use crate::{
+    archive::Writer,
+    error::CompilerError,
+    events::{write_events, Event},
+    metadata::Metadata,
+    primitives::{Container, Options},
+    render_image,
+    y4m::Y4m,
+};
+use asciicore::frame::{compress, index};
+use image::{DynamicImage, RgbaImage};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// Frames converted at once. Raw frames are big, so only a few are kept
+/// around at a time.
+const BATCH: usize = 32;
+
+/// How frames are laid out in a raw stream
+#[derive(Debug, Clone, Copy)]
+pub enum RawFormat {
+    /// Packed RGBA pixels of the given size, with nothing in between frames
+    Rgba((u32, u32)),
+    Y4m(Y4m),
+}
+
+impl RawFormat {
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            Self::Rgba(dimensions) => *dimensions,
+            Self::Y4m(y4m) => (y4m.width, y4m.height),
+        }
+    }
+
+    fn frame_size(&self) -> usize {
+        match self {
+            Self::Rgba((width, height)) => *width as usize * *height as usize * 4,
+            Self::Y4m(y4m) => y4m.frame_size(),
+        }
+    }
+
+    fn to_rgba(self, frame: Vec<u8>) -> Vec<u8> {
+        match self {
+            Self::Rgba(_) => frame,
+            Self::Y4m(y4m) => y4m.to_rgba(&frame),
+        }
+    }
+}
+
+/// Opens a raw stream, `-` being stdin
+pub fn open_raw(input: &str) -> io::Result<Box<dyn BufRead>> {
+    Ok(if input == "-" {
+        Box::new(stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(input)?))
+    })
+}
+
+/// Renders a frame of packed RGBA pixels, without going through an image
+/// format first
+pub fn render_rgba(
+    pixels: Vec<u8>,
+    (width, height): (u32, u32),
+    options: Options,
+    res: &mut String,
+) -> Result<(), CompilerError> {
+    let image = RgbaImage::from_raw(width, height, pixels).ok_or_else(|| {
+        CompilerError::InvalidInput(format!("Frames must be {width}x{height} RGBA pixels"))
+    })?;
+    render_image(&DynamicImage::ImageRgba8(image), options, res);
+    Ok(())
+}
+
+/// Compiles frames straight from a raw stream, like the ones of `ffmpeg -f
+/// rawvideo -pix_fmt rgba` or `ffmpeg -f yuv4mpegpipe`, without decoding any
+/// image files. Y4m streams must have had their header read already.
+pub fn compile_raw(
+    mut input: impl BufRead,
+    format: RawFormat,
+    output: &mut PathBuf,
+    container: Container,
+    options: Options,
+    metadata: &Metadata,
+    events: &[Event],
+) -> Result<(), Box<dyn Error>> {
+    let dimensions = format.dimensions();
+
+    output.set_extension("bapple");
+    let mut archive = Writer::create(output, container)?;
+    let mut names = Vec::new();
+    let mut frames = Vec::new();
+
+    loop {
+        let batch = read_batch(&mut input, format)?;
+        if batch.is_empty() {
+            break;
+        }
+
+        let converted = batch
+            .into_par_iter()
+            .map(|pixels| {
+                let mut res = String::new();
+                render_rgba(format.to_rgba(pixels), dimensions, options, &mut res)?;
+                Ok(compress(res.as_bytes())?)
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()
+            .map_err(|error| error as Box<dyn Error>)?;
+
+        for frame in converted {
+            let name = format!("{:03}.zst", frames.len() + 1);
+            archive.add(&name, &frame)?;
+            names.push(name);
+            frames.push(frame);
+        }
+        print!("\rProcessing: {} frames", frames.len());
+    }
+
+    let index = index(names.into_iter().zip(frames.iter().map(Vec::as_slice)));
+    archive.add("index", index.as_bytes())?;
+    archive.add("metadata", metadata.to_string().as_bytes())?;
+    if !events.is_empty() {
+        archive.add("events", write_events(events).as_bytes())?;
+    }
+    archive.finish()?;
+
+    println!(
+        "\n\n\
+        >=== Done! ===<\n\
+        >> Output available at {}",
+        output.display()
+    );
+    Ok(())
+}
+
+/// Up to [`BATCH`] whole frames. A frame cut short by the end of the input
+/// is an error, since it means the dimensions are wrong, and so is a y4m
+/// `FRAME` line with nothing after it.
+fn read_batch(input: &mut impl BufRead, format: RawFormat) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    let frame_size = format.frame_size();
+    let mut batch = Vec::new();
+    while batch.len() < BATCH {
+        if matches!(format, RawFormat::Y4m(_)) && Y4m::read_frame_header(input)?.is_none() {
+            break;
+        }
+        let mut frame = vec![0; frame_size];
+        let mut filled = 0;
+        while filled < frame_size {
+            match input.read(&mut frame[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        match filled {
+            0 if matches!(format, RawFormat::Rgba(_)) => break,
+            _ if filled < frame_size => {
+                let hint = match format {
+                    RawFormat::Rgba(_) => "check the --rawvideo size",
+                    RawFormat::Y4m(_) => "the stream was cut short",
+                };
+                return Err(CompilerError::InvalidInput(format!(
+                    "The input ended halfway through a frame ({filled} of {frame_size} bytes), \
+                    {hint}"
+                ))
+                .into());
+            }
+            _ => batch.push(frame),
+        }
+    }
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn y4m(frames: &[u8]) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+        let input = [b"YUV4MPEG2 W2 H2 Cmono\n", frames].concat();
+        let mut input = input.as_slice();
+        let format = RawFormat::Y4m(Y4m::read_header(&mut input)?);
+        read_batch(&mut input, format)
+    }
+
+    #[test]
+    fn reads_whole_frames() {
+        let batch = y4m(b"FRAME\n1234FRAME\n5678").unwrap();
+        assert_eq!(batch, [b"1234", b"5678"]);
+        assert!(y4m(b"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_frames() {
+        assert!(y4m(b"FRAME\n1234FRAME\n56").is_err());
+        assert!(y4m(b"FRAME\n1234FRAME\n").is_err());
+        assert!(y4m(b"FRAME\n1234garbage").is_err());
+
+        let mut input = &[0; 6][..];
+        assert!(read_batch(&mut input, RawFormat::Rgba((1, 1))).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use std::{error::Error, mem::take, path::PathBuf};
+
+use clap::ArgMatches;
+
+use crate::{archive::Bapple, error::CompilerError, metadata::sync_markers};
+
+/// Drops or duplicates frames of an existing archive so that it plays at a
+/// different framerate, without needing the original video.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn retime(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let input = matches.get_one::<PathBuf>("input").unwrap();
+    let fps = *matches.get_one::<f64>("fps").unwrap();
+
+    let mut bapple = Bapple::open(input)?;
+    bapple.metadata.require_whole_frames("retime")?;
+    let Some(source_fps) = matches
+        .get_one::<f64>("from")
+        .copied()
+        .or(bapple.metadata.framerate)
+    else {
+        return Err(CompilerError::InvalidInput(
+            "This archive doesn't record its framerate, pass it with --from".into(),
+        )
+        .into());
+    };
+
+    if fps <= 0.0 || source_fps <= 0.0 {
+        return Err(CompilerError::InvalidInput("Framerates must be positive".into()).into());
+    }
+
+    let frames = take(&mut bapple.frames);
+    let total = frames.len();
+    let retimed = (total as f64 * fps / source_fps).round() as usize;
+
+    bapple.frames = (0..retimed)
+        .map(|i| frames[((i as f64 * source_fps / fps) as usize).min(total - 1)].clone())
+        .collect();
+    bapple.metadata.framerate = Some(fps);
+    if let Some(slides) = &mut bapple.metadata.slides {
+        for frame in slides.iter_mut() {
+            *frame = ((*frame as f64 * fps / source_fps).round() as usize).clamp(1, retimed);
+        }
+    }
+    if let Some(scenes) = &mut bapple.metadata.scenes {
+        for (frame, _) in scenes.iter_mut().skip(1) {
+            let start = ((*frame - 1) as f64 * fps / source_fps).round() as usize;
+            *frame = (start + 1).min(retimed.max(1));
+        }
+    }
+    // Events happen when their frame starts, rather than when it ends
+    for event in &mut bapple.events {
+        let start = ((event.frame - 1) as f64 * fps / source_fps).round() as usize;
+        event.frame = (start + 1).min(retimed.max(1));
+    }
+    if bapple.metadata.sync.is_some() {
+        bapple.metadata.sync = Some(sync_markers(fps, retimed)).filter(|sync| !sync.is_empty());
+    }
+
+    let output = matches
+        .get_one::<PathBuf>("output")
+        .cloned()
+        .unwrap_or_else(|| {
+            let mut output = input.clone();
+            output.set_file_name(format!(
+                "{}_{fps}fps.bapple",
+                input.file_stem().unwrap().to_string_lossy()
+            ));
+            output
+        });
+    bapple.write(&output)?;
+
+    println!(
+        ">> Retimed {total} frames at {source_fps}fps into {retimed} frames at {fps}fps\n\
+        >> Output available at {}",
+        output.display()
+    );
+    Ok(())
+}
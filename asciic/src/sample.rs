@@ -0,0 +1,45 @@
+use image::{imageops::FilterType, DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::primitives::{OutputSize, Sampling};
+
+/// One pixel per cell of `size`, picked the way `sampling` says
+pub fn sample(image: &DynamicImage, size: OutputSize, sampling: Sampling) -> DynamicImage {
+    match sampling {
+        Sampling::Nearest => image.resize_exact(size.0, size.1, FilterType::Nearest),
+        Sampling::Area => DynamicImage::ImageRgba8(area(image, size)),
+    }
+}
+
+/// Averages every pixel each cell covers, so that fine detail blends into
+/// the cell instead of a single pixel of it standing for the rest. Cells
+/// smaller than a pixel get the one they fall in.
+fn area(image: &DynamicImage, size: OutputSize) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return RgbaImage::new(size.0, size.1);
+    }
+    let image = image.to_rgba8();
+    let span = |cell: u32, cells: u32, pixels: u32| {
+        let start = u64::from(cell) * u64::from(pixels) / u64::from(cells);
+        let end = u64::from(cell + 1) * u64::from(pixels) / u64::from(cells);
+        // Both fit, since they're at most `pixels`
+        #[allow(clippy::cast_possible_truncation)]
+        (start as u32, (end as u32).max(start as u32 + 1))
+    };
+
+    RgbaImage::from_fn(size.0, size.1, |column, row| {
+        let (left, right) = span(column, size.0, width);
+        let (top, bottom) = span(row, size.1, height);
+        let mut sums = [0u64; 4];
+        for y in top..bottom {
+            for x in left..right {
+                for (sum, channel) in sums.iter_mut().zip(image.get_pixel(x, y).0) {
+                    *sum += u64::from(channel);
+                }
+            }
+        }
+        let count = u64::from((right - left) * (bottom - top));
+        #[allow(clippy::cast_possible_truncation)]
+        Rgba(sums.map(|sum| ((sum + count / 2) / count) as u8))
+    })
+}
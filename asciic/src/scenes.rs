@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use image::{imageops::FilterType, io::Reader};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{charset::Ramp, color::brightness, prepare, primitives::Options};
+
+/// Share of a frame's brightness histogram that has to change from the frame
+/// before for it to start a new scene
+const CUT: f64 = 0.4;
+
+/// Frames get measured at about this many pixels, which is plenty for a
+/// histogram and keeps the extra pass cheap
+const SAMPLE: u32 = 64;
+
+type Histogram = [u32; 256];
+
+/// Splits `frames` into scenes at hard cuts and picks the charset that makes
+/// the most of each one. Returns the first frame of every scene, counting
+/// from 1, along with the index of its charset. Neighbouring scenes that end
+/// up with the same charset are merged.
+pub fn find_scenes(
+    frames: &[PathBuf],
+    options: Options,
+    charsets: &[&Ramp],
+) -> Vec<(usize, usize)> {
+    let histograms = frames
+        .par_iter()
+        .map(|path| histogram(path, options))
+        .collect::<Vec<_>>();
+
+    let mut scenes: Vec<(usize, Histogram)> = Vec::new();
+    let mut last = None;
+    for (number, histogram) in (1..).zip(histograms) {
+        // Frames that can't be read just carry on with the scene they're in
+        let Some(histogram) = histogram else {
+            continue;
+        };
+        match scenes.last_mut() {
+            Some((_, total)) if !last.is_some_and(|last| is_cut(&last, &histogram)) => {
+                for (total, count) in total.iter_mut().zip(histogram) {
+                    *total += count;
+                }
+            }
+            _ => scenes.push((number, histogram)),
+        }
+        last = Some(histogram);
+    }
+
+    let mut picked: Vec<(usize, usize)> = Vec::new();
+    for (start, histogram) in scenes {
+        let charset = best_charset(&histogram, charsets);
+        if picked.last().is_none_or(|&(_, last)| last != charset) {
+            picked.push((start, charset));
+        }
+    }
+    if let Some(first) = picked.first_mut() {
+        first.0 = 1;
+    }
+    picked
+}
+
+/// Index of the charset that `frame`, counting from 0, gets drawn with
+pub fn charset_at(scenes: &[(usize, usize)], frame: usize) -> usize {
+    let scene = scenes.partition_point(|&(start, _)| start <= frame + 1);
+    scene.checked_sub(1).map_or(0, |scene| scenes[scene].1)
+}
+
+/// How many pixels of a downscaled frame fall on each brightness, measured
+/// the same way characters get picked
+fn histogram(path: &PathBuf, options: Options) -> Option<Histogram> {
+    let image = Reader::open(path).ok()?.decode().ok()?;
+    let image = image.resize(SAMPLE, SAMPLE, FilterType::Triangle);
+    let image = prepare(&image, options).to_rgb8();
+
+    let mut histogram = [0; 256];
+    for pixel in image.pixels() {
+        histogram[brightness(options.luminance, pixel.0) as usize] += 1;
+    }
+    Some(histogram)
+}
+
+/// Whether the brightness of two frames is too different for them to be part
+/// of the same shot
+#[allow(clippy::cast_precision_loss)]
+fn is_cut(before: &Histogram, after: &Histogram) -> bool {
+    let share = |histogram: &Histogram| f64::from(histogram.iter().sum::<u32>().max(1));
+    let (total_before, total_after) = (share(before), share(after));
+    let changed = before
+        .iter()
+        .zip(after)
+        .map(|(&before, &after)| {
+            (f64::from(before) / total_before - f64::from(after) / total_after).abs()
+        })
+        .sum::<f64>()
+        / 2.0;
+    changed > CUT
+}
+
+/// The charset that spreads a scene's pixels the most evenly over its
+/// characters, so dark scenes go to ramps with more dark characters and
+/// bright scenes to the opposite
+fn best_charset(histogram: &Histogram, charsets: &[&Ramp]) -> usize {
+    let entropy = |ramp: &Ramp| {
+        let mut uses = vec![0u64; ramp.len()];
+        for (level, &count) in (0..=u8::MAX).zip(histogram) {
+            let position = ramp
+                .iter()
+                .position(|&(max, _)| level <= max)
+                .unwrap_or(ramp.len() - 1);
+            uses[position] += u64::from(count);
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let total = uses.iter().sum::<u64>().max(1) as f64;
+        #[allow(clippy::cast_precision_loss)]
+        uses.iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let share = count as f64 / total;
+                -share * share.log2()
+            })
+            .sum::<f64>()
+    };
+
+    charsets
+        .iter()
+        .map(|ramp| entropy(ramp))
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(0, |(index, _)| index)
+}
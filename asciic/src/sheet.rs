@@ -0,0 +1,87 @@
+use std::{error::Error, io, path::PathBuf, time::Duration};
+
+use clap::ArgMatches;
+use image::{io::Reader, DynamicImage, GenericImageView};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    archive::Bapple,
+    check_crop,
+    converter::AsciiConverter,
+    error::CompilerError,
+    events::Event,
+    player_metadata,
+    primitives::{Container, Options},
+};
+use asciicore::frame::compress;
+
+/// Cuts a sprite sheet into `columns` by `rows` frames, in reading order.
+/// Pixels left over on the right and bottom edges are dropped.
+pub fn split_sheet(sheet: &DynamicImage, (columns, rows): (u32, u32)) -> Vec<DynamicImage> {
+    let (width, height) = (sheet.width() / columns, sheet.height() / rows);
+    (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| sheet.crop_imm(column * width, row * height, width, height))
+        .collect()
+}
+
+/// Compiles each sprite of the sheet passed as the video into a frame,
+/// shown for `--frame-delay`
+pub fn compile_sheet(
+    matches: &ArgMatches,
+    options: Options,
+    grid: (u32, u32),
+    events: &[Event],
+) -> Result<(), Box<dyn Error>> {
+    let path = matches.get_one::<String>("video").unwrap();
+    let sheet = Reader::open(path)?.decode()?;
+    if sheet.width() < grid.0 || sheet.height() < grid.1 {
+        return Err(CompilerError::InvalidInput(format!(
+            "{path} is {}x{} pixels, too small for a {}x{} grid",
+            sheet.width(),
+            sheet.height(),
+            grid.0,
+            grid.1
+        ))
+        .into());
+    }
+
+    let delay = *matches.get_one::<f64>("frame-delay").unwrap();
+    if delay <= 0.0 {
+        return Err(CompilerError::InvalidInput("--frame-delay must be positive".into()).into());
+    }
+
+    let sprites = split_sheet(&sheet, grid);
+    let dimensions = sprites[0].dimensions();
+    check_crop(options, dimensions)?;
+    let frames = sprites
+        .into_par_iter()
+        .map_init(
+            || AsciiConverter::new(options),
+            |converter, sprite| compress(converter.convert(&sprite).as_bytes()),
+        )
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut output = matches.get_one::<PathBuf>("output").unwrap().clone();
+    output.set_extension("bapple");
+    let bapple = Bapple {
+        metadata: player_metadata(matches, options, Some(1.0 / delay), Some(dimensions)),
+        frames,
+        audio: None,
+        extra_tracks: Vec::new(),
+        events: events.to_vec(),
+        container: *matches.get_one::<Container>("container").unwrap(),
+    };
+    bapple.write(&output)?;
+
+    println!(
+        ">> {} sprites of {}x{} pixels, {:?} each\n\
+        >> Output available at {}",
+        bapple.frames.len(),
+        dimensions.0,
+        dimensions.1,
+        Duration::from_secs_f64(delay),
+        output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::fmt::Write;
+
+use image::RgbImage;
+
+/// Levels each channel gets in the palette, for 6 * 6 * 6 colors
+const LEVELS: usize = 6;
+const COLORS: usize = LEVELS * LEVELS * LEVELS;
+
+/// Sixel data for `image`, in a palette of evenly spaced colors. Sixels go
+/// six rows at a time, one pass over the band for every color in it.
+pub fn encode(image: &RgbImage) -> String {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut out = format!("\x1bPq\"1;1;{width};{height}");
+    for index in 0..COLORS {
+        // Palette colors go from 0 to 100
+        let [r, g, b] = [
+            index / (LEVELS * LEVELS),
+            index / LEVELS % LEVELS,
+            index % LEVELS,
+        ]
+        .map(|level| level * 100 / (LEVELS - 1));
+        write!(out, "#{index};2;{r};{g};{b}").unwrap();
+    }
+
+    let colors = image
+        .pixels()
+        .map(|pixel| palette_index(pixel.0))
+        .collect::<Vec<_>>();
+    for top in (0..height).step_by(6) {
+        let band = &colors[top * width..(top + 6).min(height) * width];
+
+        let mut used = [false; COLORS];
+        for &color in band {
+            used[color] = true;
+        }
+        for (pass, color) in (0..COLORS).filter(|&color| used[color]).enumerate() {
+            // Back to the start of the band for each color after the first
+            if pass > 0 {
+                out.push('$');
+            }
+            write!(out, "#{color}").unwrap();
+
+            let mut runs = Vec::<(u8, usize)>::new();
+            for x in 0..width {
+                let bits = band
+                    .chunks(width)
+                    .enumerate()
+                    .filter(|(_, row)| row[x] == color)
+                    .fold(0, |bits, (y, _)| bits | 1 << y);
+                let sixel = b'?' + bits;
+                match runs.last_mut() {
+                    Some((last, count)) if *last == sixel => *count += 1,
+                    _ => runs.push((sixel, 1)),
+                }
+            }
+            // Nothing needs drawing past the last pixel of the color
+            if runs.last().is_some_and(|&(sixel, _)| sixel == b'?') {
+                runs.pop();
+            }
+            for (sixel, count) in runs {
+                push_run(&mut out, sixel as char, count);
+            }
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Short runs are cheaper written out than with a repeat count
+fn push_run(out: &mut String, sixel: char, count: usize) {
+    if count > 3 {
+        write!(out, "!{count}{sixel}").unwrap();
+    } else {
+        out.extend((0..count).map(|_| sixel));
+    }
+}
+
+/// Closest palette color to `rgb`
+fn palette_index(rgb: [u8; 3]) -> usize {
+    let [r, g, b] = rgb.map(|channel| (usize::from(channel) * (LEVELS - 1) + 127) / 255);
+    (r * LEVELS + g) * LEVELS + b
+}
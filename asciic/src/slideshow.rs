@@ -0,0 +1,143 @@
+use std::{error::Error, io, iter::repeat_n, path::PathBuf};
+
+use clap::ArgMatches;
+use image::{imageops::FilterType, DynamicImage, ImageError, Rgb, RgbImage};
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+use crate::{
+    archive::Bapple,
+    error::CompilerError,
+    metadata::Metadata,
+    orientation::open_image,
+    primitives::{Container, Options, OutputSize, PaintStyle, Transition},
+    render_image,
+    util::expand_globs,
+};
+use asciicore::frame::compress;
+
+/// Pixels blended per cell during transitions, enough for every render mode
+const CANVAS_SCALE: (u32, u32) = (4, 8);
+
+/// Shows every image for `--per-slide`, blending neighbours together for the
+/// length of `--transition-time`
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn slideshow(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let images = expand_globs(matches.get_many::<String>("input").unwrap())?;
+    let size = *matches.get_one::<OutputSize>("frame-size").unwrap();
+    let colorize = matches.contains_id("colorize");
+    let transition = *matches.get_one::<Transition>("transition").unwrap();
+    let fps = *matches.get_one::<f64>("fps").unwrap();
+    let oriented = !matches.contains_id("ignore-orientation");
+
+    if fps <= 0.0 {
+        return Err(CompilerError::InvalidInput("Framerates must be positive".into()).into());
+    }
+
+    let hold = (matches.get_one::<f64>("per-slide").unwrap() * fps)
+        .round()
+        .max(1.0) as usize;
+    let steps = match transition {
+        Transition::None => 0,
+        _ => (matches.get_one::<f64>("transition-time").unwrap() * fps).round() as usize,
+    };
+
+    let options = Options {
+        redimension: size,
+        style: if colorize {
+            PaintStyle::BgOnly
+        } else {
+            PaintStyle::BgPaint
+        },
+        colorize,
+        ..Options::default()
+    };
+
+    let canvas = (size.0 * CANVAS_SCALE.0, size.1 * CANVAS_SCALE.1);
+    let slides = images
+        .par_iter()
+        .map(|path| {
+            Ok(open_image(path, oriented)?
+                .resize_exact(canvas.0, canvas.1, FilterType::Triangle)
+                .to_rgb8())
+        })
+        .collect::<Result<Vec<_>, ImageError>>()?;
+
+    let mut frames = Vec::new();
+    let mut markers = Vec::new();
+    for (index, slide) in slides.iter().enumerate() {
+        let still = encode(slide.clone(), options)?;
+        frames.extend(repeat_n(still, hold));
+        markers.push(frames.len());
+
+        let Some(next) = slides.get(index + 1) else {
+            continue;
+        };
+        let blended = (1..=steps)
+            .into_par_iter()
+            .map(|step| {
+                let progress = step as f32 / (steps + 1) as f32;
+                encode(blend(slide, next, transition, progress), options)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        frames.extend(blended);
+    }
+
+    let mut output = matches.get_one::<PathBuf>("output").unwrap().clone();
+    output.set_extension("bapple");
+
+    let bapple = Bapple {
+        frames,
+        audio: None,
+        extra_tracks: Vec::new(),
+        metadata: Metadata {
+            framerate: Some(fps),
+            color_depth: Some(if colorize { "truecolor" } else { "none" }.to_string()),
+            min_size: Some((size.0, size.1)),
+            slides: Some(markers),
+            ..Metadata::default()
+        },
+        events: Vec::new(),
+        container: Container::Tar,
+    };
+    bapple.write(&output)?;
+
+    println!(
+        ">> {} slide(s) compiled into {} frames\n\
+        >> Output available at {}",
+        slides.len(),
+        bapple.frames.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn encode(image: RgbImage, options: Options) -> io::Result<Vec<u8>> {
+    let mut res = String::new();
+    render_image(&DynamicImage::ImageRgb8(image), options, &mut res);
+    compress(res.as_bytes())
+}
+
+/// The frame `progress` of the way from `from` to `to`
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn blend(from: &RgbImage, to: &RgbImage, transition: Transition, progress: f32) -> RgbImage {
+    let edge = (from.width() as f32 * progress) as u32;
+
+    RgbImage::from_fn(from.width(), from.height(), |x, y| {
+        let (a, b) = (from.get_pixel(x, y).0, to.get_pixel(x, y).0);
+        match transition {
+            Transition::Wipe if x < edge => Rgb(b),
+            Transition::Fade => Rgb([0, 1, 2].map(|i| {
+                (f32::from(a[i]) + (f32::from(b[i]) - f32::from(a[i])) * progress).round() as u8
+            })),
+            _ => Rgb(a),
+        }
+    })
+}
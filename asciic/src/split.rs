@@ -0,0 +1,244 @@
+use std::{
+    error::Error,
+    fs::metadata,
+    mem::take,
+    path::{Path, PathBuf},
+};
+
+use clap::ArgMatches;
+
+use crate::{
+    archive::Bapple, error::CompilerError, events::write_events, metadata::Metadata,
+    primitives::Container,
+};
+
+/// Longest line a frame can take up in the `index` entry
+const INDEX_LINE: usize = 24;
+/// What a `metadata` entry grows by when it gets a `part` line
+const PART_LINE: usize = 24;
+/// Longest line an entry can take up in a v3 index: its name, then two
+/// offsets
+const V3_LINE: u64 = 64;
+
+/// Cuts an archive into parts no bigger than `--max-size`, for platforms
+/// that limit how big attachments can be. Each part is an archive of its
+/// own, but the audio, events and markers all stay in the first one, so
+/// that the player can go through the rest without stopping.
+pub fn split(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let input = matches.get_one::<PathBuf>("input").unwrap();
+    let base = matches.get_one::<PathBuf>("output").unwrap_or(input);
+    let max_size = *matches.get_one::<u64>("max-size").unwrap();
+
+    let Bapple {
+        frames,
+        mut audio,
+        mut extra_tracks,
+        metadata,
+        mut events,
+        container,
+    } = Bapple::open(input)?;
+    if metadata.part.is_some() {
+        return Err(CompilerError::InvalidInput(format!(
+            "{} is already a part, join it back first",
+            input.display()
+        ))
+        .into());
+    }
+    if frames.is_empty() {
+        return Err(
+            CompilerError::InvalidInput(format!("{} has no frames", input.display())).into(),
+        );
+    }
+
+    // Markers and events count frames from the start of the whole archive,
+    // so later parts only get what it takes to play them on their own
+    let later_metadata = |part| Metadata {
+        framerate: metadata.framerate,
+        color_depth: metadata.color_depth.clone(),
+        loop_stream: metadata.loop_stream,
+        min_size: metadata.min_size,
+        drop_frames: metadata.drop_frames,
+        keyframes: metadata.keyframes,
+        part,
+        ..Metadata::default()
+    };
+    let entry_size = |len| entry_size(container, len);
+    let metadata_size = |metadata: &Metadata| entry_size(metadata.to_string().len() + PART_LINE);
+    let shared = audio
+        .iter()
+        .chain(&extra_tracks)
+        .map(|track| entry_size(track.len()))
+        .sum::<u64>()
+        + entry_size(write_events(&events).len())
+        + metadata_size(&metadata);
+    let later = metadata_size(&later_metadata(None));
+
+    let sizes = frames.iter().map(|frame| entry_size(frame.len()));
+    let parts = pack(sizes, shared, later, max_size, container)?;
+
+    let total = frames.len();
+    let count = parts.len();
+    let mut frames = frames.into_iter();
+    for (number, len) in (1..).zip(&parts) {
+        let part = Some((number, count));
+        let metadata = if number == 1 {
+            Metadata {
+                part,
+                ..metadata.clone()
+            }
+        } else {
+            later_metadata(part)
+        };
+        Bapple {
+            frames: frames.by_ref().take(*len).collect(),
+            audio: audio.take(),
+            extra_tracks: take(&mut extra_tracks),
+            metadata,
+            events: take(&mut events),
+            container,
+        }
+        .write(&part_path(base, number))?;
+    }
+
+    println!(
+        ">> Split {total} frames into {count} part(s) of at most {max_size} bytes\n\
+        >> Output available at {} to {}",
+        part_path(base, 1).display(),
+        part_path(base, count).display()
+    );
+    Ok(())
+}
+
+/// Puts a split archive back together, from any of its parts
+pub fn join(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let input = matches.get_one::<PathBuf>("input").unwrap();
+    let Some(part) = Bapple::open(input)?.metadata.part else {
+        return Err(CompilerError::InvalidInput(format!(
+            "{} isn't part of a split archive",
+            input.display()
+        ))
+        .into());
+    };
+    let (base, paths) = part_paths(input, part)?;
+
+    let mut joined = Bapple::open(&paths[0])?;
+    for (number, path) in (2..).zip(&paths[1..]) {
+        let bapple = Bapple::open(path)?;
+        if bapple.metadata.part != Some((number, part.1)) {
+            return Err(CompilerError::InvalidInput(format!(
+                "{} isn't part {number} of {}",
+                path.display(),
+                part.1
+            ))
+            .into());
+        }
+        joined.frames.extend(bapple.frames);
+    }
+    joined.metadata.part = None;
+
+    let output = matches.get_one::<PathBuf>("output").unwrap_or(&base);
+    joined.write(output)?;
+
+    println!(
+        ">> Joined {} part(s) into {} frames\n>> Output available at {}",
+        paths.len(),
+        joined.frames.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Part `number` of the archive at `base`, like `video.part2.bapple`
+fn part_path(base: &Path, number: usize) -> PathBuf {
+    base.with_extension(format!("part{number}.bapple"))
+}
+
+/// The archive `path` is part `number` of, along with all of its parts
+fn part_paths(
+    path: &Path,
+    (number, count): (usize, usize),
+) -> Result<(PathBuf, Vec<PathBuf>), CompilerError> {
+    let base = path
+        .to_str()
+        .and_then(|path| path.strip_suffix(&format!(".part{number}.bapple")))
+        .map(|base| PathBuf::from(format!("{base}.bapple")))
+        .ok_or_else(|| {
+            CompilerError::InvalidInput(format!(
+                "{} was renamed, parts have to keep the names they were split into",
+                path.display()
+            ))
+        })?;
+
+    let paths = (1..=count)
+        .map(|number| part_path(&base, number))
+        .collect::<Vec<_>>();
+    if let Some(missing) = paths.iter().find(|path| metadata(path).is_err()) {
+        return Err(CompilerError::InvalidInput(format!(
+            "{} is missing",
+            missing.display()
+        )));
+    }
+    Ok((base, paths))
+}
+
+/// How many frames go in each part. Sizes are the most entries can take up,
+/// so parts never end up bigger than `max_size`.
+fn pack(
+    sizes: impl Iterator<Item = u64>,
+    shared: u64,
+    later: u64,
+    max_size: u64,
+    container: Container,
+) -> Result<Vec<usize>, CompilerError> {
+    let fits = |overhead: u64, used: u64, frames: usize| {
+        framing(container) + overhead + used + entry_size(container, INDEX_LINE * frames)
+            <= max_size
+    };
+    if !fits(shared, 0, 0) {
+        return Err(CompilerError::InvalidInput(format!(
+            "The audio and metadata alone can take up {} bytes, more than --max-size",
+            framing(container) + shared + entry_size(container, 0)
+        )));
+    }
+
+    let mut parts = vec![0];
+    let mut overhead = shared;
+    let mut used = 0;
+    for (number, size) in (1..).zip(sizes) {
+        let frames = parts.last_mut().unwrap();
+        if !fits(overhead, used + size, *frames + 1) && *frames > 0 {
+            parts.push(0);
+            overhead = later;
+            used = 0;
+        }
+
+        let frames = parts.last_mut().unwrap();
+        used += size;
+        *frames += 1;
+        if !fits(overhead, used, *frames) {
+            return Err(CompilerError::InvalidInput(format!(
+                "Frame {number} doesn't fit in --max-size on its own"
+            )));
+        }
+    }
+    Ok(parts)
+}
+
+/// What `container` takes up besides its entries
+fn framing(container: Container) -> u64 {
+    match container {
+        // Two empty blocks end tar archives
+        Container::Tar => 1024,
+        // Just the header
+        Container::V3 => 24,
+    }
+}
+
+/// Most an entry of `len` bytes can take up in `container`
+fn entry_size(container: Container, len: usize) -> u64 {
+    match container {
+        // A header, then the data padded to a whole block
+        Container::Tar => 512 + len.next_multiple_of(512) as u64,
+        Container::V3 => len as u64 + V3_LINE,
+    }
+}
@@ -0,0 +1,53 @@
+use std::{error::Error, fs::write, path::Path};
+
+use image::{imageops::FilterType, DynamicImage, GenericImageView, Rgb, RgbImage};
+
+use crate::{check_crop, color::luma, orientation::open_image, primitives::Options, render_image};
+
+/// Red/cyan anaglyph of a side by side stereo frame: the left half goes to
+/// the red channel and the right half to green and blue. Both eyes are turned
+/// gray first, which keeps colors from fighting between the glasses' filters.
+pub fn anaglyph(frame: &DynamicImage) -> DynamicImage {
+    let width = frame.width() / 2;
+    let left = frame.crop_imm(0, 0, width, frame.height()).to_rgb8();
+    let right = frame.crop_imm(width, 0, width, frame.height()).to_rgb8();
+
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, frame.height(), |x, y| {
+        let left = luma(left.get_pixel(x, y).0);
+        let right = luma(right.get_pixel(x, y).0);
+        Rgb([left, right, right])
+    }))
+}
+
+/// Puts two separate views next to each other, so that they can go through
+/// [`anaglyph`]. The right view is resized to match the left one.
+pub fn side_by_side(left: &DynamicImage, right: &DynamicImage) -> DynamicImage {
+    let (width, height) = left.dimensions();
+    let right = right.resize_exact(width, height, FilterType::Triangle);
+
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width * 2, height, |x, y| {
+        if x < width {
+            Rgb(left.get_pixel(x, y).0[..3].try_into().unwrap())
+        } else {
+            Rgb(right.get_pixel(x - width, y).0[..3].try_into().unwrap())
+        }
+    }))
+}
+
+/// Compiles a left and a right view into a single anaglyph, written next to
+/// the left one like any other image
+pub fn compile_pair(
+    left: &Path,
+    right: &Path,
+    options: Options,
+    oriented: bool,
+) -> Result<(), Box<dyn Error>> {
+    let frame = side_by_side(&open_image(left, oriented)?, &open_image(right, oriented)?);
+    check_crop(options, frame.dimensions())?;
+
+    let mut res = String::new();
+    render_image(&frame, options, &mut res);
+
+    write(left.with_extension("txt"), res)?;
+    Ok(())
+}
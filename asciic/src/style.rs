@@ -0,0 +1,138 @@
+use crate::{
+    color::{hue, luma, Rgb},
+    paint::Stroke,
+    primitives::{Effect, Options, PaintStyle},
+};
+
+/// Where a cell is and what it was made from, for [`CellStyler`]s
+#[derive(Debug, Clone, Copy)]
+pub struct CellContext {
+    pub x: u32,
+    pub y: u32,
+    /// Columns and rows of the whole frame
+    pub size: (u32, u32),
+    pub rgba: [u8; 4],
+    /// Brightness the character was picked with
+    pub brightness: u8,
+}
+
+/// Per cell effects, run after each cell's character and colors are worked
+/// out. Returning a different stroke changes what's drawn there, without
+/// touching the rest of the render loop.
+pub trait CellStyler: Sync {
+    fn style(&self, cell: CellContext, stroke: Stroke) -> Stroke;
+}
+
+/// Dims every other row, like a CRT
+pub struct Scanlines;
+
+/// Darkens cells the further they are from the center
+pub struct Vignette;
+
+/// Recolors cells with a hue that sweeps diagonally across the frame,
+/// keeping their brightness. Transparent pixels are left alone.
+pub struct Rainbow;
+
+/// How `--style` paints a cell of `color` that got `ch`. Dark cells under
+/// `--dark-cutoff` all share one color, or none at all, so they need no
+/// escapes of their own.
+pub fn stroke(options: Options, color: Rgb, ch: char) -> Stroke {
+    match options.style {
+        PaintStyle::BgPaint | PaintStyle::FgPaint => Stroke::Paint(color, ch),
+        PaintStyle::BgOnly
+            if options
+                .dark_cutoff
+                .is_some_and(|cutoff| color.iter().all(|&c| c < cutoff)) =>
+        {
+            options
+                .dark_fill
+                .map_or(Stroke::Clear, |fill| Stroke::Paint(fill, ' '))
+        }
+        PaintStyle::BgOnly => Stroke::Paint(color, ' '),
+        PaintStyle::Shade => Stroke::Shade(color),
+        PaintStyle::Mixed => Stroke::Both(contrast(color), color, ch),
+    }
+}
+
+/// Black over light colors and white over dark ones
+fn contrast(background: Rgb) -> Rgb {
+    if luma(background) > 127 {
+        [0; 3]
+    } else {
+        [255; 3]
+    }
+}
+
+/// Runs a cell through `--effect`, then puts it over `--background`. The
+/// background only gets written when it changes, which is once per frame, or
+/// after a keyed out cell.
+pub fn finish(options: Options, cell: CellContext, stroke: Stroke) -> Stroke {
+    let stroke = options
+        .effect
+        .map_or(stroke, |effect| effect.styler().style(cell, stroke));
+    match (stroke, options.background) {
+        (Stroke::Paint(color, ch), Some(background)) => Stroke::Both(color, background, ch),
+        _ => stroke,
+    }
+}
+
+impl Effect {
+    pub fn styler(self) -> &'static dyn CellStyler {
+        match self {
+            Self::Scanlines => &Scanlines,
+            Self::Vignette => &Vignette,
+            Self::Rainbow => &Rainbow,
+        }
+    }
+}
+
+impl CellStyler for Scanlines {
+    fn style(&self, cell: CellContext, stroke: Stroke) -> Stroke {
+        if cell.y.is_multiple_of(2) {
+            stroke
+        } else {
+            recolor(stroke, |color| scale(color, 0.6))
+        }
+    }
+}
+
+impl CellStyler for Vignette {
+    #[allow(clippy::cast_precision_loss)]
+    fn style(&self, cell: CellContext, stroke: Stroke) -> Stroke {
+        let (width, height) = (cell.size.0 as f32, cell.size.1 as f32);
+        let dx = (cell.x as f32 + 0.5) / width * 2.0 - 1.0;
+        let dy = (cell.y as f32 + 0.5) / height * 2.0 - 1.0;
+        // 0 in the center, 1 in the corners
+        let distance = f32::midpoint(dx * dx, dy * dy);
+        recolor(stroke, |color| scale(color, 1.0 - 0.7 * distance))
+    }
+}
+
+impl CellStyler for Rainbow {
+    #[allow(clippy::cast_precision_loss)]
+    fn style(&self, cell: CellContext, stroke: Stroke) -> Stroke {
+        if cell.rgba[3] == 0 {
+            return stroke;
+        }
+        let position = (cell.x + cell.y) as f32 / (cell.size.0 + cell.size.1).max(1) as f32;
+        let tint = scale(hue(position), f32::from(cell.brightness) / 255.0);
+        recolor(stroke, |_| tint)
+    }
+}
+
+/// `stroke` with `change` applied to each of its colors
+fn recolor(stroke: Stroke, change: impl Fn([u8; 3]) -> [u8; 3]) -> Stroke {
+    match stroke {
+        Stroke::Paint(color, ch) => Stroke::Paint(change(color), ch),
+        Stroke::Both(foreground, background, ch) => {
+            Stroke::Both(change(foreground), change(background), ch)
+        }
+        Stroke::Clear => Stroke::Clear,
+        Stroke::Shade(color) => Stroke::Shade(change(color)),
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn scale(color: [u8; 3], factor: f32) -> [u8; 3] {
+    color.map(|channel| (f32::from(channel) * factor.clamp(0.0, 1.0)).round() as u8)
+}
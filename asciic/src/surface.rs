@@ -0,0 +1,95 @@
+use std::io::{self, Write};
+
+use asciicore::ansi::{AsciiFrame, Cell, Pen};
+
+/// A grid of cells to draw on and then show in a terminal, for full screen
+/// views built out of frames and text. Only the cells that changed since the
+/// last [`Surface::present`] get written out again.
+pub struct Surface {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Cell>,
+    /// What the terminal shows, as of the last present
+    shown: Option<Vec<Cell>>,
+}
+
+impl Surface {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::blank(); width * height],
+            shown: None,
+        }
+    }
+
+    /// Blanks every cell, without touching the terminal until the next present
+    pub fn clear(&mut self) {
+        self.cells.fill(Cell::blank());
+    }
+
+    /// Sets the cell at (`x`, `y`). Cells outside of the surface are dropped.
+    pub fn draw(&mut self, x: usize, y: usize, cell: Cell) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = cell;
+        }
+    }
+
+    /// Writes `text` on a single row starting at (`x`, `y`), cut at the edge
+    pub fn text(&mut self, x: usize, y: usize, text: &str, pen: Pen) {
+        for (offset, ch) in text.chars().enumerate() {
+            self.draw(x + offset, y, Cell { ch, pen });
+        }
+    }
+
+    /// Copies the part of `frame` starting at `from` onto the surface at
+    /// `to`, as much of it as fits
+    pub fn blit(&mut self, frame: &AsciiFrame, from: (usize, usize), to: (usize, usize)) {
+        for (y, row) in frame.rows().skip(from.1).enumerate() {
+            if to.1 + y >= self.height {
+                break;
+            }
+            for (x, &cell) in row.iter().skip(from.0).enumerate() {
+                self.draw(to.0 + x, to.1 + y, cell);
+            }
+        }
+    }
+
+    /// Brings the terminal up to date, moving the cursor over runs of cells
+    /// that didn't change. The first present draws everything.
+    pub fn present(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let mut pen = None;
+        // Where the terminal's cursor ends up after the last cell written
+        let mut cursor = None;
+
+        for (index, cell) in self.cells.iter().enumerate() {
+            if self
+                .shown
+                .as_ref()
+                .is_some_and(|shown| shown[index] == *cell)
+            {
+                continue;
+            }
+
+            let (x, y) = (index % self.width, index / self.width);
+            if cursor != Some((x, y)) {
+                write!(out, "\x1b[{};{}H", y + 1, x + 1)?;
+            }
+            if pen != Some(cell.pen) {
+                out.write_all(cell.pen.escape().as_bytes())?;
+                pen = Some(cell.pen);
+            }
+            write!(out, "{}", cell.ch)?;
+            // Writing the last column leaves the cursor there, so the next row
+            // always gets an explicit move
+            cursor = (x + 1 < self.width).then_some((x + 1, y));
+        }
+
+        if pen.is_some() {
+            out.write_all(b"\x1b[0m")?;
+        }
+        out.flush()?;
+        self.shown = Some(self.cells.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use std::{error::Error, fs::write, iter::repeat_n, path::PathBuf};
+
+use clap::ArgMatches;
+use image::{DynamicImage, Rgb, RgbImage};
+
+use crate::{
+    archive::Bapple,
+    color::{hue, ANSI16},
+    error::CompilerError,
+    metadata::Metadata,
+    primitives::{Container, Options, OutputSize, PaintStyle},
+    render_image,
+};
+use asciicore::frame::compress;
+
+/// 75% bars, in the usual order
+const BARS: [[u8; 3]; 7] = [
+    [191, 191, 191],
+    [191, 191, 0],
+    [0, 191, 191],
+    [0, 191, 0],
+    [191, 0, 191],
+    [191, 0, 0],
+    [0, 0, 191],
+];
+
+/// Calibration frames, for checking a terminal before compiling anything
+/// real: gradients and color bars show its color depth, the aspect grid its
+/// font's proportions, and the stress frames how much output it keeps up with
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn testcard(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let size = *matches.get_one::<OutputSize>("frame-size").unwrap();
+    let colorize = matches.contains_id("colorize");
+    let cell_aspect = *matches.get_one::<f32>("cell-aspect").unwrap();
+    let fps = *matches.get_one::<f64>("fps").unwrap();
+
+    if fps <= 0.0 {
+        return Err(CompilerError::InvalidInput("Framerates must be positive".into()).into());
+    }
+    if cell_aspect <= 0.0 {
+        return Err(CompilerError::InvalidInput("--cell-aspect must be positive".into()).into());
+    }
+
+    let options = Options {
+        redimension: size,
+        style: if colorize {
+            PaintStyle::BgOnly
+        } else {
+            PaintStyle::BgPaint
+        },
+        colorize,
+        ..Options::default()
+    };
+    // Every cell gets its own escape, for the most bytes a frame can take
+    let stress_options = Options {
+        skip_compression: true,
+        compression_threshold: 0,
+        ..options
+    };
+
+    let cards = [
+        ("gradients", gradients(size)),
+        ("bars", bars(size)),
+        ("aspect", aspect_grid(size, cell_aspect)),
+    ]
+    .map(|(name, card)| (name, render(card, options)));
+
+    let mut output = matches.get_one::<PathBuf>("output").unwrap().clone();
+    if matches.contains_id("text") {
+        for (name, card) in &cards {
+            let mut path = output.clone();
+            path.set_file_name(format!(
+                "{}_{name}.txt",
+                output.file_stem().unwrap_or_default().to_string_lossy()
+            ));
+            write(&path, card)?;
+            println!(">> Wrote {}", path.display());
+        }
+    }
+
+    let hold = (matches.get_one::<f64>("per-card").unwrap() * fps)
+        .round()
+        .max(1.0) as usize;
+    let mut frames = Vec::new();
+    let mut markers = Vec::new();
+    for (_, card) in &cards {
+        frames.extend(repeat_n(compress(card.as_bytes())?, hold));
+        markers.push(frames.len());
+    }
+
+    let stress_frames = (matches.get_one::<f64>("stress").unwrap() * fps).round() as u64;
+    let mut stress_bytes = 0;
+    for seed in 1..=stress_frames {
+        let frame = render(stress(size, seed), stress_options);
+        stress_bytes = stress_bytes.max(frame.len());
+        frames.push(compress(frame.as_bytes())?);
+    }
+
+    output.set_extension("bapple");
+    let bapple = Bapple {
+        frames,
+        audio: None,
+        extra_tracks: Vec::new(),
+        metadata: Metadata {
+            framerate: Some(fps),
+            color_depth: Some(if colorize { "truecolor" } else { "none" }.to_string()),
+            min_size: Some((size.0, size.1)),
+            slides: Some(markers),
+            ..Metadata::default()
+        },
+        events: Vec::new(),
+        container: Container::Tar,
+    };
+    bapple.write(&output)?;
+
+    println!(
+        ">> Cards: gradients, color bars, aspect grid (the circle should look round)\n\
+        >> {stress_frames} stress frames of up to {} KiB, {:.0} KiB/s at {fps}fps\n\
+        >> Output available at {}",
+        stress_bytes / 1024,
+        stress_bytes as f64 * fps / 1024.0,
+        output.display()
+    );
+    Ok(())
+}
+
+fn render(card: RgbImage, options: Options) -> String {
+    let mut res = String::new();
+    render_image(&DynamicImage::ImageRgb8(card), options, &mut res);
+    res
+}
+
+/// Gray, red, green and blue ramps, then a sweep through every hue. Banding
+/// shows how many colors the terminal really has.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn gradients(OutputSize(width, height): OutputSize) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        let level = (x * 255 / (width - 1).max(1)) as u8;
+        match y * 5 / height {
+            0 => Rgb([level; 3]),
+            1 => Rgb([level, 0, 0]),
+            2 => Rgb([0, level, 0]),
+            3 => Rgb([0, 0, level]),
+            _ => Rgb(hue(x as f32 / width as f32)),
+        }
+    })
+}
+
+/// Color bars over the 16 base colors, which themes are free to change
+fn bars(OutputSize(width, height): OutputSize) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        if y < height * 2 / 3 {
+            Rgb(BARS[(x * 7 / width) as usize])
+        } else {
+            Rgb(ANSI16[(x * 16 / width) as usize])
+        }
+    })
+}
+
+/// Square boxes around a circle, both drawn assuming cells are `cell_aspect`
+/// times taller than wide
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn aspect_grid(OutputSize(width, height): OutputSize, cell_aspect: f32) -> RgbImage {
+    let step = (width / 8).max(2);
+    let rows = (step as f32 / cell_aspect).round().max(1.0) as u32;
+    let (center_x, center_y) = (width as f32 / 2.0, height as f32 * cell_aspect / 2.0);
+    let radius = center_x.min(center_y) * 0.8;
+
+    RgbImage::from_fn(width, height, |x, y| {
+        let dx = x as f32 + 0.5 - center_x;
+        let dy = (y as f32 + 0.5) * cell_aspect - center_y;
+        if dx.hypot(dy) <= radius {
+            Rgb([255; 3])
+        } else if x % step == 0 || y % rows == 0 {
+            Rgb([96; 3])
+        } else {
+            Rgb([0; 3])
+        }
+    })
+}
+
+/// A different color in every cell, so that nothing can be merged
+#[allow(clippy::cast_possible_truncation)]
+fn stress(OutputSize(width, height): OutputSize, seed: u64) -> RgbImage {
+    let mut state = seed;
+    RgbImage::from_fn(width, height, |_, _| {
+        // xorshift, plenty for noise
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        Rgb([state as u8, (state >> 8) as u8, (state >> 16) as u8])
+    })
+}
@@ -0,0 +1,72 @@
+use std::{error::Error, io, path::PathBuf};
+
+use clap::ArgMatches;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    archive::Bapple,
+    color::{nearest, read_palette, Rgb},
+};
+use asciicore::ansi::{sgr, tokenize, Token};
+use asciicore::frame::{compress, decompress};
+
+/// Remaps every color of an existing archive to the closest one in a palette
+pub fn theme(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let input = matches.get_one::<PathBuf>("input").unwrap();
+    let palette = read_palette(matches.get_one::<PathBuf>("palette").unwrap())?;
+
+    let mut bapple = Bapple::open(input)?;
+    bapple.frames = bapple
+        .frames
+        .into_par_iter()
+        .map(|frame| {
+            let frame = decompress(&frame)?;
+            compress(recolor(&String::from_utf8_lossy(&frame), &palette).as_bytes())
+        })
+        .collect::<io::Result<_>>()?;
+
+    let output = matches
+        .get_one::<PathBuf>("output")
+        .cloned()
+        .unwrap_or_else(|| {
+            let mut output = input.clone();
+            output.set_file_name(format!(
+                "{}_themed.bapple",
+                input.file_stem().unwrap().to_string_lossy()
+            ));
+            output
+        });
+    bapple.write(&output)?;
+
+    println!(">> Output available at {}", output.display());
+    Ok(())
+}
+
+pub fn recolor(frame: &str, palette: &[Rgb]) -> String {
+    let mut recolored = String::with_capacity(frame.len());
+
+    for token in tokenize(frame) {
+        match token {
+            Token::Text(text) | Token::Escape(text) => recolored.push_str(text),
+            Token::Sgr(mut params) => {
+                let mut i = 0;
+                while i < params.len() {
+                    // 38;2;r;g;b and 48;2;r;g;b are the truecolor sequences
+                    if matches!(params[i], 38 | 48) && params.get(i + 1) == Some(&2) {
+                        if let Some(&[r, g, b]) = params.get(i + 2..i + 5) {
+                            let clamp = |c: u16| u8::try_from(c).unwrap_or(u8::MAX);
+                            let themed = palette[nearest(palette, [clamp(r), clamp(g), clamp(b)])];
+                            params.splice(i + 2..i + 5, themed.map(u16::from));
+                        }
+                        i += 5;
+                    } else {
+                        i += 1;
+                    }
+                }
+                recolored.push_str(&sgr(&params));
+            }
+        }
+    }
+
+    recolored
+}
@@ -1,13 +1,18 @@
 use std::{
-    fs::{remove_dir_all, File},
+    fs::{read_dir, remove_dir_all, rename, File},
     io,
-    path::Path,
+    ops::Sub,
+    path::{Path, PathBuf},
     process::{abort, Command, Stdio},
     thread::sleep,
     time::Duration,
 };
 
-use tar::{Builder, Header};
+use glob::{glob, PatternError};
+use tar::{Archive, Builder, Header};
+
+use crate::{archive::count_frames, watchdog::Watchdog};
+use asciicore::container::{is_v3, V3Writer};
 
 pub fn clean_abort(tmp_path: &Path) -> ! {
     sleep(Duration::from_secs(2));
@@ -31,18 +36,58 @@ pub fn pause() -> ! {
 pub fn add_file(
     tar_archive: &mut Builder<File>,
     path: impl AsRef<Path>,
-    data: &Vec<u8>,
+    data: &[u8],
 ) -> io::Result<()> {
     let mut header = Header::new_gnu();
     header.set_size(data.len() as u64);
     header.set_cksum();
 
-    tar_archive.append_data(&mut header, path, data.as_slice())
+    tar_archive.append_data(&mut header, path, data)
+}
+
+/// Copies every entry of an existing archive over to a new one with `audio`
+/// in place of the old track. Returns how many frames were kept. v3
+/// archives get the new track appended instead, without copying anything.
+/// Only the first track gets replaced: the ones `--audio-track` added stay,
+/// since the metadata still lists their languages.
+pub fn replace_audio(archive_path: &Path, audio: &[u8]) -> io::Result<usize> {
+    if is_v3(&mut File::open(archive_path)?)? {
+        let mut archive = V3Writer::append(archive_path)?;
+        archive.add("audio.mp3", audio)?;
+        archive.finish()?;
+        return count_frames(archive_path);
+    }
+
+    let rewritten_path = archive_path.with_extension("bapple.tmp");
+    let mut archive = Archive::new(File::open(archive_path)?);
+    let mut rewritten = Builder::new(File::create(&rewritten_path)?);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.file_stem() == Some("audio".as_ref()) {
+            continue;
+        }
+
+        let header = entry.header().clone();
+        rewritten.append(&header, &mut entry)?;
+    }
+
+    add_file(&mut rewritten, "audio.mp3", audio)?;
+    rewritten.finish()?;
+    drop(rewritten);
+
+    rename(rewritten_path, archive_path)?;
+    count_frames(archive_path)
 }
 
-pub fn ffmpeg(args: &[&str], extra_flags: &[&String]) -> Result<(), Box<dyn std::error::Error>> {
+pub fn ffmpeg(
+    args: &[&str],
+    extra_flags: &[&String],
+    watchdog: &Watchdog,
+) -> Result<(), Box<dyn std::error::Error>> {
     let mut command = Command::new("ffmpeg");
     command
+        .arg("-y")
         .args(args)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
@@ -52,16 +97,44 @@ pub fn ffmpeg(args: &[&str], extra_flags: &[&String]) -> Result<(), Box<dyn std:
         command.args(extra_flags);
     }
 
-    let output = command.output()?;
+    watchdog.run("FFMPEG", &mut command)
+}
 
-    if !output.status.success() {
-        return Err("FFMPEG failed to run".into());
+/// Expands glob patterns, since shells like cmd.exe won't do it for us.
+/// Arguments that match nothing are kept as plain paths.
+pub fn expand_globs<'a>(
+    patterns: impl Iterator<Item = &'a String>,
+) -> Result<Vec<PathBuf>, PatternError> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        let matches = glob(pattern)?.filter_map(Result::ok).collect::<Vec<_>>();
+        if matches.is_empty() {
+            paths.push(PathBuf::from(pattern));
+        } else {
+            paths.extend(matches);
+        }
     }
+    Ok(paths)
+}
 
-    Ok(())
+/// Frames extracted into `tmp_path`, in order
+pub fn list_frames(tmp_path: &Path) -> Vec<PathBuf> {
+    let mut frames: Vec<_> = read_dir(tmp_path)
+        .map(|dir| {
+            dir.filter_map(Result::ok)
+                .filter(|e| !e.file_name().to_string_lossy().starts_with("audio"))
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    frames.sort_by_key(|path| {
+        path.file_stem()
+            .and_then(|stem| stem.to_str()?.parse::<usize>().ok())
+    });
+    frames
 }
 
 #[inline]
-pub fn max_sub(a: u8, b: u8) -> u8 {
+pub fn max_sub<T: Ord + Sub<Output = T> + Copy>(a: T, b: T) -> T {
     a.max(b) - a.min(b)
 }
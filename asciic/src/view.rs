@@ -0,0 +1,189 @@
+use std::{
+    error::Error,
+    fs::read,
+    io::{stdout, Write},
+    path::PathBuf,
+};
+
+use asciicore::termcaps::terminal_size;
+use clap::ArgMatches;
+use image::io::Reader;
+#[cfg(any(feature = "sixel", feature = "kitty"))]
+use image::{imageops::FilterType, DynamicImage, ImageError};
+#[cfg(any(feature = "sixel", feature = "kitty"))]
+use std::path::Path;
+
+#[cfg(any(feature = "sixel", feature = "kitty"))]
+use asciicore::termcaps::{probe, TermCaps};
+
+use crate::{
+    frame_cells,
+    keys::{read_key, Key},
+    primitives::{Options, OutputSize, PaintStyle},
+    surface::Surface,
+    write_image_io,
+};
+use asciicore::ansi::{render_row, AsciiFrame, Color, Pen};
+
+/// Shows a single frame centered in the terminal, or lets the user pan
+/// around it when it doesn't fit. With `--source`, the frame is rendered from
+/// the original image at the size of the terminal instead.
+pub fn view(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let terminal = terminal_size().filter(|&(width, height)| width > 0 && height > 0);
+    let source = matches.get_one::<PathBuf>("source");
+
+    #[cfg(any(feature = "sixel", feature = "kitty"))]
+    if let Some(source) = source {
+        if show_pixels(matches, source)? {
+            return Ok(());
+        }
+    }
+
+    let Some((width, height)) = terminal else {
+        // Nothing to center in, so just pass the frame along
+        if let Some(source) = source {
+            let options = source_options(matches, (80, 24));
+            write_image_io(&Reader::open(source)?.decode()?, options, stdout().lock())?;
+        } else {
+            stdout().write_all(&read(matches.get_one::<PathBuf>("input").unwrap())?)?;
+        }
+        return Ok(());
+    };
+
+    let screen = if let Some(source) = source {
+        let options = source_options(matches, (width, height));
+        frame_cells(&Reader::open(source)?.decode()?, options)
+    } else {
+        let frame = read(matches.get_one::<PathBuf>("input").unwrap())?;
+        AsciiFrame::parse(String::from_utf8_lossy(&frame).trim_end_matches('\n'))
+    };
+    let (width, height) = (width as usize, height as usize);
+
+    if screen.width <= width && screen.height < height {
+        let mut lock = stdout().lock();
+        let left = " ".repeat((width - screen.width) / 2);
+        write!(lock, "{}", "\n".repeat((height - 1 - screen.height) / 2))?;
+        for row in screen.rows() {
+            writeln!(lock, "{left}{}", render_row(row))?;
+        }
+        return Ok(());
+    }
+
+    pager(&screen, (width, height))
+}
+
+/// Draws `source` with real pixels, through the first of `--kitty` and
+/// `--sixel` the terminal supports. Returns false without drawing anything
+/// when it supports neither, so that the ASCII version goes out instead.
+#[cfg(any(feature = "sixel", feature = "kitty"))]
+fn show_pixels(matches: &ArgMatches, source: &Path) -> Result<bool, Box<dyn Error>> {
+    let caps = probe();
+    let data = match () {
+        #[cfg(feature = "kitty")]
+        () if matches.contains_id("kitty") && caps.kitty_graphics => {
+            crate::kitty::encode(&fit_terminal(source, caps)?)?
+        }
+        #[cfg(feature = "sixel")]
+        () if matches.contains_id("sixel") && caps.sixel => {
+            crate::sixel::encode(&fit_terminal(source, caps)?.to_rgb8())
+        }
+        () => return Ok(false),
+    };
+
+    let mut lock = stdout().lock();
+    lock.write_all(data.as_bytes())?;
+    lock.write_all(b"\n")?;
+    Ok(true)
+}
+
+/// `source`, scaled to fill the terminal's pixels like `--source` fills its
+/// cells
+#[cfg(any(feature = "sixel", feature = "kitty"))]
+fn fit_terminal(source: &Path, caps: TermCaps) -> Result<DynamicImage, ImageError> {
+    /// Cell size to go by in terminals that don't report theirs
+    const DEFAULT_CELL: (u32, u32) = (10, 20);
+
+    let (columns, rows) = caps
+        .size
+        .filter(|&(columns, rows)| columns > 0 && rows > 0)
+        .unwrap_or((80, 24));
+    let (cell_width, cell_height) = caps.cell_pixels.unwrap_or(DEFAULT_CELL);
+    // Leaves a row for the shell prompt
+    Ok(Reader::open(source)?.decode()?.resize(
+        columns * cell_width,
+        rows.saturating_sub(1).max(1) * cell_height,
+        FilterType::Triangle,
+    ))
+}
+
+/// Settings for rendering `--source` to fill a terminal of the given size
+fn source_options(matches: &ArgMatches, (width, height): (u32, u32)) -> Options {
+    let colorize = matches.contains_id("colorize");
+    Options {
+        // Leaves a row for the shell prompt
+        redimension: OutputSize(width, height.saturating_sub(1).max(1)),
+        style: if colorize {
+            PaintStyle::BgOnly
+        } else {
+            PaintStyle::BgPaint
+        },
+        colorize,
+        ..Options::default()
+    }
+}
+
+/// Full screen view of the part of `screen` that fits, moved around with the
+/// arrow keys. The last row is kept for a status line.
+fn pager(screen: &AsciiFrame, (width, height): (usize, usize)) -> Result<(), Box<dyn Error>> {
+    let rows = height.saturating_sub(1).max(1);
+    let (max_top, max_left) = (
+        screen.height.saturating_sub(rows),
+        screen.width.saturating_sub(width),
+    );
+    let (mut top, mut left) = (0, 0);
+
+    // Black on white, like the reverse video it stands in for
+    let status_pen = Pen {
+        fg: Some(Color::Indexed(0)),
+        bg: Some(Color::Indexed(7)),
+    };
+    let mut surface = Surface::new(width, height);
+
+    let mut lock = stdout().lock();
+    // Alternate screen, without a cursor
+    lock.write_all(b"\x1b[?1049h\x1b[?25l")?;
+
+    loop {
+        surface.clear();
+        surface.blit(screen, (left, top), (0, 0));
+        // The frame can be taller than the rows it gets
+        surface.text(0, rows, &" ".repeat(width), Pen::default());
+        let status = format!(
+            " rows {}-{} of {}, columns {}-{} of {} | arrows or hjkl to move, q to quit ",
+            top + 1,
+            (top + rows).min(screen.height),
+            screen.height,
+            left + 1,
+            (left + width).min(screen.width),
+            screen.width,
+        );
+        surface.text(0, rows, &status, status_pen);
+        surface.present(&mut lock)?;
+
+        match read_key()? {
+            Key::Up => top = top.saturating_sub(1),
+            Key::Down => top = (top + 1).min(max_top),
+            Key::Left => left = left.saturating_sub(1),
+            Key::Right => left = (left + 1).min(max_left),
+            Key::PageUp => top = top.saturating_sub(rows),
+            Key::PageDown => top = (top + rows).min(max_top),
+            Key::Home => (top, left) = (0, 0),
+            Key::End => top = max_top,
+            Key::Quit => break,
+            Key::Other => (),
+        }
+    }
+
+    lock.write_all(b"\x1b[?25h\x1b[?1049l")?;
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::{
+    error::Error,
+    fs::read_dir,
+    io::ErrorKind,
+    path::Path,
+    process::{Child, Command, ExitStatus},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use crate::error::CompilerError;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Supervises child processes that write into a directory, killing and
+/// retrying them when the directory stops growing for too long.
+pub struct Watchdog<'a> {
+    pub watch: &'a Path,
+    pub timeout: Duration,
+    pub retries: u8,
+}
+
+enum Outcome {
+    Exited(ExitStatus),
+    Stalled,
+}
+
+impl Watchdog<'_> {
+    pub fn run(&self, name: &str, command: &mut Command) -> Result<(), Box<dyn Error>> {
+        for attempt in 1..=self.retries + 1 {
+            let child = command.spawn().map_err(|error| -> Box<dyn Error> {
+                if error.kind() == ErrorKind::NotFound {
+                    CompilerError::MissingTool(name.to_lowercase()).into()
+                } else {
+                    error.into()
+                }
+            })?;
+
+            match self.supervise(child)? {
+                Outcome::Exited(status) if status.success() => return Ok(()),
+                Outcome::Exited(status) => {
+                    return Err(
+                        CompilerError::ToolFailed(format!("{name} failed ({status})")).into(),
+                    )
+                }
+                Outcome::Stalled => eprintln!(
+                    "\nWARN: {name} made no progress for {}s, killing it (attempt {attempt}/{})",
+                    self.timeout.as_secs(),
+                    self.retries + 1
+                ),
+            }
+        }
+
+        Err(CompilerError::ToolStalled(format!(
+            "{name} kept stalling. This is usually caused by a broken input or codec, \
+            try passing different flags to ffmpeg or raising --stall-timeout"
+        ))
+        .into())
+    }
+
+    fn supervise(&self, mut child: Child) -> Result<Outcome, Box<dyn Error>> {
+        let mut last_progress = self.progress();
+        let mut last_change = Instant::now();
+
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Outcome::Exited(status));
+            }
+
+            let progress = self.progress();
+            if progress != last_progress {
+                last_progress = progress;
+                last_change = Instant::now();
+            } else if !self.timeout.is_zero() && last_change.elapsed() >= self.timeout {
+                child.kill().ok();
+                child.wait()?;
+                return Ok(Outcome::Stalled);
+            }
+
+            sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Total bytes written into the watched directory so far
+    fn progress(&self) -> u64 {
+        read_dir(self.watch).map_or(0, |dir| {
+            dir.filter_map(Result::ok)
+                .filter_map(|entry| entry.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+    }
+}
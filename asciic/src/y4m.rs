@@ -0,0 +1,256 @@
+use std::io::{self, BufRead};
+
+use crate::error::CompilerError;
+
+/// How much of the chroma planes a y4m stream keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chroma {
+    /// Half the width and half the height
+    C420,
+    /// Half the width
+    C422,
+    C444,
+    /// No chroma planes at all
+    Mono,
+}
+
+/// Header of a YUV4MPEG2 stream, like the ones of `ffmpeg -f yuv4mpegpipe`.
+/// Only progressive, 8 bit planes are supported.
+#[derive(Debug, Clone, Copy)]
+pub struct Y4m {
+    pub width: u32,
+    pub height: u32,
+    pub framerate: Option<f64>,
+    chroma: Chroma,
+    /// Whether luma goes from 0 to 255 instead of 16 to 235
+    full_range: bool,
+}
+
+impl Y4m {
+    /// Reads the header line, which a stream starts with
+    pub fn read_header(input: &mut impl BufRead) -> Result<Self, CompilerError> {
+        let header = read_line(input)
+            .map_err(|error| CompilerError::InvalidInput(format!("Can't read y4m: {error}")))?
+            .unwrap_or_default();
+        let mut fields = header.split_ascii_whitespace();
+        if fields.next() != Some("YUV4MPEG2") {
+            return Err(CompilerError::InvalidInput(
+                "Not a y4m stream, it should start with YUV4MPEG2".into(),
+            ));
+        }
+
+        let mut y4m = Self {
+            width: 0,
+            height: 0,
+            framerate: None,
+            chroma: Chroma::C420,
+            full_range: false,
+        };
+        for field in fields {
+            let mut chars = field.chars();
+            let tag = chars.next();
+            let value = chars.as_str();
+            match tag {
+                Some('W') => y4m.width = value.parse().unwrap_or(0),
+                Some('H') => y4m.height = value.parse().unwrap_or(0),
+                Some('F') => {
+                    y4m.framerate = value
+                        .split_once(':')
+                        .and_then(|(num, den)| {
+                            Some((num.parse::<f64>().ok()?, den.parse::<f64>().ok()?))
+                        })
+                        .filter(|&(_, den)| den > 0.0)
+                        .map(|(num, den)| num / den);
+                }
+                Some('C') => {
+                    y4m.chroma = match value {
+                        "420" | "420jpeg" | "420mpeg2" | "420paldv" => Chroma::C420,
+                        "422" => Chroma::C422,
+                        "444" => Chroma::C444,
+                        "mono" => Chroma::Mono,
+                        other => {
+                            return Err(CompilerError::InvalidInput(format!(
+                                "y4m colorspace {other} isn't supported, try -pix_fmt yuv420p"
+                            )))
+                        }
+                    }
+                }
+                // Fields would get drawn woven together, so they're better
+                // left to ffmpeg to deinterlace
+                Some('I') if !matches!(value, "p" | "?") => {
+                    return Err(CompilerError::InvalidInput(
+                        "Interlaced y4m isn't supported, try -vf yadif".into(),
+                    ))
+                }
+                Some('X') => y4m.full_range |= value == "COLORRANGE=FULL",
+                _ => (),
+            }
+        }
+
+        if y4m.width == 0 || y4m.height == 0 {
+            return Err(CompilerError::InvalidInput(
+                "The y4m header has no frame size".into(),
+            ));
+        }
+        Ok(y4m)
+    }
+
+    /// Size of the chroma planes, each
+    fn chroma_size(&self) -> (usize, usize) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        match self.chroma {
+            Chroma::C420 => (width.div_ceil(2), height.div_ceil(2)),
+            Chroma::C422 => (width.div_ceil(2), height),
+            Chroma::C444 => (width, height),
+            Chroma::Mono => (0, 0),
+        }
+    }
+
+    /// Bytes of pixel data in each frame, after its `FRAME` line
+    pub fn frame_size(&self) -> usize {
+        let (chroma_width, chroma_height) = self.chroma_size();
+        self.width as usize * self.height as usize + 2 * chroma_width * chroma_height
+    }
+
+    /// Skips the `FRAME` line in front of each frame. `None` means the
+    /// stream ended.
+    pub fn read_frame_header(input: &mut impl BufRead) -> io::Result<Option<()>> {
+        match read_line(input)? {
+            None => Ok(None),
+            Some(line) if line.starts_with("FRAME") => Ok(Some(())),
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Expected a FRAME line between y4m frames",
+            )),
+        }
+    }
+
+    /// Converts the planes of a frame into packed RGBA pixels
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn to_rgba(self, planes: &[u8]) -> Vec<u8> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (chroma_width, chroma_height) = self.chroma_size();
+        let (luma, chroma) = planes.split_at(width * height);
+        let (u_plane, v_plane) = chroma.split_at(chroma_width * chroma_height);
+
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let (u, v) = if self.chroma == Chroma::Mono {
+                    (128, 128)
+                } else {
+                    let index =
+                        y * chroma_height / height * chroma_width + x * chroma_width / width;
+                    (u_plane[index], v_plane[index])
+                };
+                let (luma, u, v) = (
+                    i32::from(luma[y * width + x]),
+                    i32::from(u) - 128,
+                    i32::from(v) - 128,
+                );
+
+                // BT.601, in 8.8 fixed point
+                let rgb = if self.full_range {
+                    let luma = luma << 8;
+                    [luma + 359 * v, luma - 88 * u - 183 * v, luma + 454 * u]
+                } else {
+                    let luma = 298 * (luma - 16);
+                    [luma + 409 * v, luma - 100 * u - 208 * v, luma + 516 * u]
+                };
+                rgba.extend(rgb.map(|channel| ((channel + 128) >> 8).clamp(0, 255) as u8));
+                rgba.push(255);
+            }
+        }
+        rgba
+    }
+}
+
+/// A line without its newline, or `None` at the end of the input
+fn read_line(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    if input.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(line: &str) -> Result<Y4m, CompilerError> {
+        Y4m::read_header(&mut line.as_bytes())
+    }
+
+    #[test]
+    fn reads_headers() {
+        let y4m =
+            header("YUV4MPEG2 W64 H48 F30000:1001 Ip A1:1 C420jpeg XYSCSS=420JPEG\n").unwrap();
+        assert_eq!((y4m.width, y4m.height), (64, 48));
+        assert!((y4m.framerate.unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(y4m.chroma, Chroma::C420);
+        assert!(!y4m.full_range);
+        assert_eq!(y4m.frame_size(), 64 * 48 + 2 * 32 * 24);
+    }
+
+    #[test]
+    fn reads_chroma_tags() {
+        let chroma = |tag| header(&format!("YUV4MPEG2 W5 H3 C{tag}")).map(|y4m| y4m.chroma_size());
+        assert_eq!(chroma("420mpeg2").unwrap(), (3, 2));
+        assert_eq!(chroma("422").unwrap(), (3, 3));
+        assert_eq!(chroma("444").unwrap(), (5, 3));
+        assert_eq!(chroma("mono").unwrap(), (0, 0));
+        assert!(chroma("420p10").is_err());
+
+        // 4:2:0 when there's no C tag
+        assert_eq!(header("YUV4MPEG2 W5 H3").unwrap().chroma, Chroma::C420);
+        assert!(
+            header("YUV4MPEG2 W5 H3 XCOLORRANGE=FULL")
+                .unwrap()
+                .full_range
+        );
+    }
+
+    #[test]
+    fn reads_framerates() {
+        let framerate = |tag| header(&format!("YUV4MPEG2 W1 H1 {tag}")).unwrap().framerate;
+        assert_eq!(framerate("F25:1"), Some(25.0));
+        assert_eq!(framerate("F24:0"), None);
+        assert_eq!(framerate("F24"), None);
+        assert_eq!(framerate("Fx:1"), None);
+    }
+
+    #[test]
+    fn rejects_interlacing() {
+        assert!(header("YUV4MPEG2 W1 H1 I?").is_ok());
+        for tag in ["It", "Ib", "Im"] {
+            assert!(header(&format!("YUV4MPEG2 W1 H1 {tag}")).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_broken_headers() {
+        assert!(header("").is_err());
+        assert!(header("YUV4MPEG W1 H1").is_err());
+        assert!(header("YUV4MPEG2 W64").is_err());
+        assert!(header("YUV4MPEG2 W0 H48").is_err());
+    }
+
+    #[test]
+    fn reads_frame_lines() {
+        let mut input = "FRAME\nFRAME Ixyz\n".as_bytes();
+        assert!(Y4m::read_frame_header(&mut input).unwrap().is_some());
+        assert!(Y4m::read_frame_header(&mut input).unwrap().is_some());
+        assert!(Y4m::read_frame_header(&mut input).unwrap().is_none());
+        assert!(Y4m::read_frame_header(&mut "garbage\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn converts_gray() {
+        let y4m = header("YUV4MPEG2 W2 H1 Cmono").unwrap();
+        assert_eq!(y4m.to_rgba(&[16, 235]), [0, 0, 0, 255, 255, 255, 255, 255]);
+    }
+}
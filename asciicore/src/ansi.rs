@@ -0,0 +1,326 @@
+use alloc::{format, string::String, string::ToString, vec, vec::Vec};
+use core::{fmt::Write, iter::repeat_n};
+
+/// Unchanged cells in a row that are still cheaper to draw again than to
+/// move the cursor past, about the length of a cursor move
+const JUMP_COST: usize = 8;
+
+/// A color as its red, green and blue channels
+pub type Rgb = [u8; 3];
+
+/// A piece of a frame, as far as escape sequences are concerned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    Text(&'a str),
+    /// `ESC [ <params> m`, with empty parameters read as 0
+    Sgr(Vec<u16>),
+    /// Any other escape sequence, kept verbatim
+    Escape(&'a str),
+}
+
+pub fn tokenize(frame: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = frame;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('\x1b') else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+        }
+
+        let sequence_len = escape_len(&rest[start..]);
+        let sequence = &rest[start..start + sequence_len];
+        tokens.push(parse_sgr(sequence).map_or(Token::Escape(sequence), Token::Sgr));
+        rest = &rest[start + sequence_len..];
+    }
+
+    tokens
+}
+
+/// A color as written in an SGR sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 256 palette entries, including the 16 base colors
+    Indexed(u8),
+    Rgb(Rgb),
+}
+
+/// Colors in effect at some point of a frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pen {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+}
+
+impl Pen {
+    /// Updates the pen with the parameters of an SGR sequence. Attributes
+    /// other than colors are ignored.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn apply(&mut self, params: &[u16]) {
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Self::default(),
+                30..=37 => self.fg = Some(Color::Indexed(param as u8 - 30)),
+                90..=97 => self.fg = Some(Color::Indexed(param as u8 - 82)),
+                40..=47 => self.bg = Some(Color::Indexed(param as u8 - 40)),
+                100..=107 => self.bg = Some(Color::Indexed(param as u8 - 92)),
+                39 => self.fg = None,
+                49 => self.bg = None,
+                38 | 48 => {
+                    let clamp = |c: Option<u16>| u8::try_from(c.unwrap_or(0)).unwrap_or(u8::MAX);
+                    let color = match params.next() {
+                        Some(5) => Color::Indexed(clamp(params.next())),
+                        Some(2) => Color::Rgb([
+                            clamp(params.next()),
+                            clamp(params.next()),
+                            clamp(params.next()),
+                        ]),
+                        _ => continue,
+                    };
+                    if param == 38 {
+                        self.fg = Some(color);
+                    } else {
+                        self.bg = Some(color);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// SGR sequence that resets the terminal to exactly this pen
+    pub fn escape(self) -> String {
+        let mut params = vec![0];
+        for (color, base) in [(self.fg, 38), (self.bg, 48)] {
+            match color {
+                // The 16 base colors have shorter codes that more terminals know about
+                Some(Color::Indexed(index @ 0..=7)) => params.push(base - 8 + u16::from(index)),
+                Some(Color::Indexed(index @ 8..=15)) => {
+                    params.push(base + 44 + u16::from(index));
+                }
+                Some(Color::Indexed(index)) => params.extend([base, 5, u16::from(index)]),
+                Some(Color::Rgb(rgb)) => {
+                    params.extend([base, 2]);
+                    params.extend(rgb.map(u16::from));
+                }
+                None => (),
+            }
+        }
+        sgr(&params)
+    }
+}
+
+/// A character on screen, along with the colors it's drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub pen: Pen,
+}
+
+impl Cell {
+    /// An empty cell in the terminal's default colors
+    pub const fn blank() -> Self {
+        Self {
+            ch: ' ',
+            pen: Pen { fg: None, bg: None },
+        }
+    }
+}
+
+/// A frame as a `width` by `height` grid of cells, for code that needs to
+/// look at frames rather than print them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AsciiFrame {
+    pub width: usize,
+    pub height: usize,
+    /// Row after row, from the top left
+    pub cells: Vec<Cell>,
+}
+
+impl AsciiFrame {
+    /// Reads a frame back from text, padding short rows with blank cells
+    pub fn parse(frame: &str) -> Self {
+        let rows = cells(frame);
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let cells = rows
+            .iter()
+            .flat_map(|row| {
+                let padding = repeat_n(Cell::blank(), width - row.len());
+                row.iter().copied().chain(padding)
+            })
+            .collect();
+
+        Self {
+            width,
+            height: rows.len(),
+            cells,
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// Draws over `previous` on screen with this frame, moving the cursor to
+    /// the cells that changed instead of drawing every one of them again.
+    /// When the size changes, the screen gets cleared and drawn in full.
+    /// Either way, the cursor ends up on the row below the frame.
+    pub fn diff(&self, previous: &Self) -> String {
+        let resized = (self.width, self.height) != (previous.width, previous.height);
+        let mut update = String::new();
+        if resized {
+            update.push_str("\x1b[2J");
+        }
+
+        let mut pen = None;
+        for (y, row) in self.rows().enumerate() {
+            let old = (!resized).then(|| &previous.cells[y * self.width..][..self.width]);
+            // A cleared screen is already blank
+            let changed = |x: usize| old.map_or(row[x] != Cell::blank(), |old| old[x] != row[x]);
+
+            let mut x = 0;
+            while x < row.len() {
+                if !changed(x) {
+                    x += 1;
+                    continue;
+                }
+                let mut end = x + 1;
+                let mut next = end;
+                while next < row.len() && next - end < JUMP_COST {
+                    if changed(next) {
+                        end = next + 1;
+                    }
+                    next += 1;
+                }
+
+                write!(update, "\x1b[{};{}H", y + 1, x + 1).unwrap();
+                for cell in &row[x..end] {
+                    if pen != Some(cell.pen) {
+                        update.push_str(&cell.pen.escape());
+                        pen = Some(cell.pen);
+                    }
+                    update.push(cell.ch);
+                }
+                x = end;
+            }
+        }
+
+        if pen.is_some_and(|pen| pen != Pen::default()) {
+            update.push_str("\x1b[0m");
+        }
+        write!(update, "\x1b[{};1H", self.height + 1).unwrap();
+        update
+    }
+}
+
+/// Splits a frame into rows of cells. Colors carry over from one row to the
+/// next, the same way they would on a terminal.
+pub fn cells(frame: &str) -> Vec<Vec<Cell>> {
+    let mut rows = vec![Vec::new()];
+    let mut pen = Pen::default();
+
+    for token in tokenize(frame) {
+        match token {
+            Token::Text(text) => {
+                for ch in text.chars() {
+                    match ch {
+                        '\n' => rows.push(Vec::new()),
+                        '\r' => (),
+                        ch => rows.last_mut().unwrap().push(Cell { ch, pen }),
+                    }
+                }
+            }
+            Token::Sgr(params) => pen.apply(&params),
+            Token::Escape(_) => (),
+        }
+    }
+
+    if rows.last().is_some_and(Vec::is_empty) {
+        rows.pop();
+    }
+    rows
+}
+
+/// Draws a row of cells back, resetting colors at the end
+pub fn render_row(row: &[Cell]) -> String {
+    let mut line = String::new();
+    let mut last = None;
+
+    for cell in row {
+        if last != Some(cell.pen) {
+            line.push_str(&cell.pen.escape());
+            last = Some(cell.pen);
+        }
+        line.push(cell.ch);
+    }
+
+    line.push_str("\x1b[0m");
+    line
+}
+
+/// Draws a whole frame back, the inverse of [`cells`]. Colors carry over
+/// from one row to the next, and only get reset before the last newline.
+pub fn render(rows: &[Vec<Cell>]) -> String {
+    let mut frame = String::new();
+    let mut last = Pen::default();
+
+    for (number, row) in rows.iter().enumerate() {
+        if number > 0 {
+            frame.push('\n');
+        }
+        for cell in row {
+            if last != cell.pen {
+                frame.push_str(&cell.pen.escape());
+                last = cell.pen;
+            }
+            frame.push(cell.ch);
+        }
+    }
+
+    if last != Pen::default() {
+        frame.push_str("\x1b[0m");
+    }
+    if !rows.is_empty() {
+        frame.push('\n');
+    }
+    frame
+}
+
+/// Writes SGR parameters back into an escape sequence
+pub fn sgr(params: &[u16]) -> String {
+    let params = params.iter().map(u16::to_string).collect::<Vec<_>>();
+    format!("\x1b[{}m", params.join(";"))
+}
+
+/// Length in bytes of the escape sequence at the start of `input`.
+/// Truncated sequences take up the rest of the input.
+fn escape_len(input: &str) -> usize {
+    let bytes = input.as_bytes();
+    match bytes.get(1) {
+        // CSI: parameters and intermediates until a final byte in 0x40..=0x7E
+        Some(b'[') => bytes[2..]
+            .iter()
+            .position(|b| (0x40..=0x7E).contains(b))
+            .map_or(bytes.len(), |end| end + 3),
+        Some(_) => 1 + input[1..].chars().next().map_or(0, char::len_utf8),
+        None => 1,
+    }
+}
+
+fn parse_sgr(sequence: &str) -> Option<Vec<u16>> {
+    let params = sequence.strip_prefix("\x1b[")?.strip_suffix('m')?;
+    params
+        .split(';')
+        .map(|param| {
+            if param.is_empty() {
+                Some(0)
+            } else {
+                param.parse().ok()
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,263 @@
+use std::{
+    fmt::Write as _,
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::Path,
+};
+
+pub const MAGIC: &[u8; 6] = b"BAPPLE";
+pub const VERSION: u16 = 3;
+/// Magic, version, then the offset and length of the index
+const HEADER_SIZE: usize = 24;
+
+/// Entries of a v3 archive by name, with where their data sits in the file
+pub type Index = Vec<(String, Range<u64>)>;
+
+/// Writes the v3 container: a fixed size header pointing at a text index,
+/// with the entries' data stored back to back in between. The index goes
+/// after the data, so adding entries to an archive only takes writing them
+/// and a new index at its end, then pointing the header there. Opening one
+/// only needs the header and the index, and the index is all it takes to
+/// fetch any entry with a range request.
+pub struct V3Writer {
+    file: File,
+    index: Index,
+    end: u64,
+}
+
+impl V3Writer {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        file.write_all(&header(0..0))?;
+        Ok(Self {
+            file,
+            index: Vec::new(),
+            end: HEADER_SIZE as u64,
+        })
+    }
+
+    /// Opens an existing archive to add entries to. Nothing already in it gets
+    /// rewritten, the old index is just left behind when the new one goes in.
+    pub fn append(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let index = read_index(&mut file)?;
+        let end = file.seek(SeekFrom::End(0))?;
+        Ok(Self { file, index, end })
+    }
+
+    /// Stores `data` as `name`, in place of any entry that already had it
+    pub fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(data)?;
+        let range = self.end..self.end + data.len() as u64;
+        self.end = range.end;
+
+        self.index.retain(|(entry, _)| entry != name);
+        self.index.push((name.to_string(), range));
+        Ok(())
+    }
+
+    /// Writes the index and points the header at it
+    pub fn finish(mut self) -> io::Result<()> {
+        let mut index = String::new();
+        for (name, range) in &self.index {
+            writeln!(index, "{name} {} {}", range.start, range.end - range.start).unwrap();
+        }
+
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(index.as_bytes())?;
+        let index = self.end..self.end + index.len() as u64;
+        self.file.set_len(index.end)?;
+
+        // Only now does the archive see the new entries, so one cut short
+        // while appending still opens as it was
+        self.file.flush()?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header(index))?;
+        self.file.flush()
+    }
+}
+
+/// Whether `file` holds a v3 archive rather than a tar one, leaving it
+/// where it was
+pub fn is_v3(file: &mut (impl Read + Seek)) -> io::Result<bool> {
+    let position = file.stream_position()?;
+    let mut magic = [0; MAGIC.len()];
+    let found = file.read_exact(&mut magic).is_ok() && &magic == MAGIC;
+    file.seek(SeekFrom::Start(position))?;
+    Ok(found)
+}
+
+/// Reads the header and the index it points to. Only those get read, and
+/// every range in them is checked to lie within the file.
+pub fn read_index(file: &mut (impl Read + Seek)) -> io::Result<Index> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut header = [0; HEADER_SIZE];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(invalid("Not a v3 archive"));
+    }
+    let version = u16::from_le_bytes([header[6], header[7]]);
+    if version != VERSION {
+        return Err(invalid(format!(
+            "Archive version {version} isn't supported, only {VERSION} is. \
+            Archives from newer versions of asciic need a newer player"
+        )));
+    }
+
+    let [offset, length] = [8, 16].map(|at| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&header[at..at + 8]);
+        u64::from_le_bytes(bytes)
+    });
+    let index = checked_range(offset, length, len).ok_or_else(|| invalid("Broken header"))?;
+    let mut text = String::new();
+    file.seek(SeekFrom::Start(index.start))?;
+    file.take(length).read_to_string(&mut text)?;
+
+    text.lines()
+        .map(|line| {
+            let mut fields = line.rsplitn(3, ' ');
+            let (Some(length), Some(offset), Some(name)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid(format!("Broken index line {line:?}")));
+            };
+            let offset = offset.parse::<u64>().map_err(invalid)?;
+            let length = length.parse::<u64>().map_err(invalid)?;
+            let range = checked_range(offset, length, len)
+                .ok_or_else(|| invalid(format!("{name} goes past the end of the archive")))?;
+            Ok((name.to_string(), range))
+        })
+        .collect()
+}
+
+/// `offset..offset + length`, as long as it ends within `len`
+fn checked_range(offset: u64, length: u64, len: u64) -> Option<Range<u64>> {
+    let end = offset.checked_add(length).filter(|&end| end <= len)?;
+    Some(offset..end)
+}
+
+/// Data of the entry at `range`, as [`read_index`] found it
+pub fn read_entry(file: &mut (impl Read + Seek), range: &Range<u64>) -> io::Result<Vec<u8>> {
+    let length = range
+        .end
+        .checked_sub(range.start)
+        .ok_or_else(|| invalid("Entry ends before it starts"))?;
+    let mut data = Vec::new();
+    file.seek(SeekFrom::Start(range.start))?;
+    file.take(length).read_to_end(&mut data)?;
+    if data.len() as u64 != length {
+        return Err(io::Error::new(
+            ErrorKind::UnexpectedEof,
+            "Entry goes past the end of the archive",
+        ));
+    }
+    Ok(data)
+}
+
+fn header(index: Range<u64>) -> [u8; HEADER_SIZE] {
+    let mut header = [0; HEADER_SIZE];
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    header[6..8].copy_from_slice(&VERSION.to_le_bytes());
+    header[8..16].copy_from_slice(&index.start.to_le_bytes());
+    header[16..24].copy_from_slice(&(index.end - index.start).to_le_bytes());
+    header
+}
+
+fn invalid(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    /// An archive with `index` as its index, right after the header
+    fn archive(index: &str) -> Cursor<Vec<u8>> {
+        let start = HEADER_SIZE as u64;
+        let mut data = header(start..start + index.len() as u64).to_vec();
+        data.extend_from_slice(index.as_bytes());
+        Cursor::new(data)
+    }
+
+    fn is_invalid(result: io::Result<impl std::fmt::Debug>) -> bool {
+        result.is_err_and(|error| error.kind() == ErrorKind::InvalidData)
+    }
+
+    #[test]
+    fn round_trips() {
+        let path = NamedTempFile::new().unwrap().into_temp_path();
+        let mut writer = V3Writer::create(&path).unwrap();
+        writer.add("001.zst", b"first").unwrap();
+        writer.add("with spaces", b"second").unwrap();
+        writer.add("001.zst", b"replaced").unwrap();
+        writer.finish().unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        assert!(is_v3(&mut file).unwrap());
+        let index = read_index(&mut file).unwrap();
+        let names = index
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["with spaces", "001.zst"]);
+        assert_eq!(read_entry(&mut file, &index[1].1).unwrap(), b"replaced");
+    }
+
+    #[test]
+    fn appends() {
+        let path = NamedTempFile::new().unwrap().into_temp_path();
+        let mut writer = V3Writer::create(&path).unwrap();
+        writer.add("audio", b"sound").unwrap();
+        writer.finish().unwrap();
+        let mut writer = V3Writer::append(&path).unwrap();
+        writer.add("metadata", b"fps=30").unwrap();
+        writer.finish().unwrap();
+
+        let index = read_index(&mut File::open(&path).unwrap()).unwrap();
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn rejects_broken_lines() {
+        assert!(is_invalid(read_index(&mut archive("no-fields\n"))));
+        assert!(is_invalid(read_index(&mut archive("name x 1\n"))));
+    }
+
+    #[test]
+    fn rejects_ranges_past_the_end() {
+        // The index itself, which takes up the last 7 bytes
+        assert!(read_index(&mut archive("a 24 7\n")).is_ok());
+        assert!(is_invalid(read_index(&mut archive("a 24 1000\n"))));
+        assert!(is_invalid(read_index(&mut archive(&format!(
+            "a {} 10\n",
+            u64::MAX - 1
+        )))));
+    }
+
+    #[test]
+    fn rejects_broken_headers() {
+        let mut data = header(1000..1010).to_vec();
+        data.extend_from_slice(b"a 0 1\n");
+        assert!(is_invalid(read_index(&mut Cursor::new(data))));
+
+        let mut data = header(0..0).to_vec();
+        data[6] = 9;
+        assert!(is_invalid(read_index(&mut Cursor::new(data))));
+        assert!(!is_v3(&mut Cursor::new(b"ustar")).unwrap());
+    }
+
+    #[test]
+    fn rejects_backwards_entries() {
+        #[allow(clippy::reversed_empty_ranges)]
+        let range = 10..5;
+        assert!(is_invalid(read_entry(&mut archive(""), &range)));
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use zstd::{
+    bulk, decode_all,
+    zstd_safe::{get_frame_content_size, CONTENTSIZE_ERROR, CONTENTSIZE_UNKNOWN},
+};
+
+/// Largest buffer that gets set aside up front for a frame, from the size its
+/// header claims. A damaged or hostile header could ask for gigabytes, so
+/// anything bigger is streamed and only takes up what actually comes out.
+const MAX_PREALLOCATED: usize = 64 * 1024 * 1024;
+
+/// Compresses a frame as a single zstd frame, which records its decompressed
+/// size in the header
+pub fn compress(frame: &[u8]) -> io::Result<Vec<u8>> {
+    bulk::compress(frame, 1)
+}
+
+/// Size of a compressed frame once decompressed, when its header says so
+pub fn content_size(frame: &[u8]) -> Option<usize> {
+    match get_frame_content_size(frame) {
+        CONTENTSIZE_ERROR | CONTENTSIZE_UNKNOWN => None,
+        size => usize::try_from(size).ok(),
+    }
+}
+
+/// Decompresses into a buffer of exactly the size the header asks for, and
+/// makes sure that's what came out. Frames written by older versions don't
+/// record their size, so those are streamed instead, and so are the ones
+/// that claim more than [`MAX_PREALLOCATED`].
+pub fn decompress(frame: &[u8]) -> io::Result<Vec<u8>> {
+    let Some(size) = content_size(frame) else {
+        return decode_all(frame);
+    };
+
+    let decompressed = if size > MAX_PREALLOCATED {
+        decode_all(frame)?
+    } else {
+        bulk::decompress(frame, size)?
+    };
+    if decompressed.len() != size {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Frame is {} bytes, but its header says {size}",
+                decompressed.len()
+            ),
+        ));
+    }
+    Ok(decompressed)
+}
+
+/// Whether a compressed frame decompresses into text, as opposed to
+/// something cut short or scrambled on disk
+pub fn is_intact(frame: &[u8]) -> bool {
+    decompress(frame).is_ok_and(|frame| std::str::from_utf8(&frame).is_ok())
+}
+
+/// The `index` entry of an archive: one `name compressed decompressed` line
+/// per frame, so that players know every size before reading any frame
+pub fn index<'a>(frames: impl Iterator<Item = (String, &'a [u8])>) -> String {
+    let mut index = String::new();
+    for (name, frame) in frames {
+        let decompressed = content_size(frame).map_or_else(|| "?".into(), |size| size.to_string());
+        writeln!(index, "{name} {} {decompressed}", frame.len()).unwrap();
+    }
+    index
+}
+
+/// Compressed size of every frame listed in an archive's `index` entry, by
+/// frame number
+pub fn parse_index(index: &str) -> HashMap<usize, usize> {
+    index
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let number = Path::new(fields.next()?)
+                .file_stem()?
+                .to_str()?
+                .parse()
+                .ok()?;
+            Some((number, fields.next()?.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Makes sure a frame wasn't cut short or padded since the index was written
+#[allow(clippy::implicit_hasher)]
+pub fn check_size(index: &HashMap<usize, usize>, number: usize, frame: &[u8]) -> io::Result<()> {
+    match index.get(&number) {
+        Some(&expected) if expected != frame.len() => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Frame {number} is {} bytes, but the index says {expected}",
+                frame.len()
+            ),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let frame = b"\x1b[38;2;1;2;3m@@..\n";
+        let compressed = compress(frame).unwrap();
+        assert_eq!(content_size(&compressed), Some(frame.len()));
+        assert_eq!(decompress(&compressed).unwrap(), frame);
+        assert!(is_intact(&compressed));
+    }
+
+    #[test]
+    fn doesnt_trust_huge_headers() {
+        // A single segment frame claiming a terabyte, holding 3 raw bytes
+        let mut frame = vec![0x28, 0xB5, 0x2F, 0xFD, 0xE0];
+        frame.extend_from_slice(&(1_u64 << 40).to_le_bytes());
+        frame.extend_from_slice(&[0x19, 0, 0]);
+        frame.extend_from_slice(b"abc");
+
+        assert_eq!(content_size(&frame), Some(1 << 40));
+        assert!(decompress(&frame).is_err());
+    }
+
+    #[test]
+    fn checks_sizes_against_the_index() {
+        let index = parse_index("001.zst 4 10\n002.zst 3 ?\nbroken\n");
+        assert_eq!(index.len(), 2);
+        assert!(check_size(&index, 1, b"1234").is_ok());
+        assert!(check_size(&index, 2, b"1234").is_err());
+        // Frames missing from the index can't be checked
+        assert!(check_size(&index, 3, b"").is_ok());
+    }
+}
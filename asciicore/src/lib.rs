@@ -0,0 +1,25 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::pedantic)]
+// Only asciic and asciix use this, the same way they'd use their own modules
+#![allow(
+    clippy::must_use_candidate,
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc
+)]
+
+extern crate alloc;
+
+pub mod ansi;
+#[cfg(feature = "std")]
+pub mod container;
+#[cfg(feature = "std")]
+pub mod frame;
+#[cfg(feature = "std")]
+pub mod plugin;
+pub mod raster;
+#[cfg(feature = "std")]
+pub mod termcaps;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+#[cfg(feature = "ratatui")]
+pub mod widget;
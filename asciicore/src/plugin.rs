@@ -0,0 +1,234 @@
+use std::{error::Error, fs::read, path::Path};
+
+use wasmi::{core::TrapCode, Config, Engine, Linker, Memory, Module, Store, TypedFunc};
+
+use crate::ansi::{render, AsciiFrame, Cell, Color, Pen};
+
+/// Bytes each cell takes up in the plugin's memory: the character as a
+/// little endian `u32`, then the foreground and background colors
+const CELL_SIZE: usize = 12;
+/// Instructions a plugin gets to run for each call, plus [`FUEL_PER_CELL`]
+/// for each cell of the frame it transforms. Plugins that run out, like ones
+/// stuck in a loop, fail on that frame instead of hanging forever.
+const FUEL: u64 = 10_000_000;
+const FUEL_PER_CELL: u64 = 10_000;
+
+type BoxResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// A WASM module that transforms frames, compiled once and shared between
+/// threads by asciic and asciix alike. It has to export its `memory`, an
+/// `alloc(len) -> ptr` that hands out `len` bytes of it, and a
+/// `transform(cells, width, height, frame)` that edits the frame's cells in
+/// place. Frames count from 0.
+pub struct Plugin {
+    engine: Engine,
+    module: Module,
+}
+
+impl Plugin {
+    /// Compiles the plugin at `path`, and makes sure it exports what it needs to
+    pub fn load(path: &Path) -> BoxResult<Self> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &read(path)?)
+            .map_err(|error| format!("{} isn't a valid plugin: {error}", path.display()))?;
+        let plugin = Self { engine, module };
+        plugin.instantiate()?;
+        Ok(plugin)
+    }
+
+    /// Sets up a copy of the plugin with its own memory. Copies can't be
+    /// shared between threads, so every thread gets its own.
+    pub fn instantiate(&self) -> BoxResult<Instance> {
+        let mut store = Store::new(&self.engine, ());
+        refuel(&mut store, FUEL);
+        let instance = Linker::new(&self.engine)
+            .instantiate(&mut store, &self.module)?
+            .start(&mut store)
+            .map_err(|error| fuel_error(error, "starting up"))?;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("plugins have to export their memory")?;
+        Ok(Instance {
+            alloc: instance.get_typed_func(&store, "alloc")?,
+            transform: instance.get_typed_func(&store, "transform")?,
+            memory,
+            store,
+            buffer: None,
+            bytes: Vec::new(),
+        })
+    }
+}
+
+pub struct Instance {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    transform: TypedFunc<(i32, i32, i32, i32), ()>,
+    /// Where the cells go in the plugin's memory, and how many bytes that
+    /// is. Only asked for again when frames change size.
+    buffer: Option<(i32, usize)>,
+    bytes: Vec<u8>,
+}
+
+impl Instance {
+    /// Runs the plugin over a frame's text, drawing it back afterwards
+    pub fn apply(&mut self, frame: &mut String, number: usize) -> BoxResult<()> {
+        let mut cells = AsciiFrame::parse(frame.trim_end_matches('\n'));
+        self.transform(&mut cells, number)?;
+        *frame = render(&cells.rows().map(<[Cell]>::to_vec).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    /// Hands the cells of `frame` to the plugin, and takes them back once
+    /// it's done with them
+    pub fn transform(&mut self, frame: &mut AsciiFrame, number: usize) -> BoxResult<()> {
+        let len = frame.cells.len() * CELL_SIZE;
+        let ptr = match self.buffer {
+            Some((ptr, allocated)) if allocated == len => ptr,
+            _ => {
+                refuel(&mut self.store, FUEL);
+                let ptr = self
+                    .alloc
+                    .call(&mut self.store, i32::try_from(len)?)
+                    .map_err(|error| fuel_error(error, "allocating"))?;
+                self.buffer = Some((ptr, len));
+                ptr
+            }
+        };
+        let offset = usize::try_from(ptr).map_err(|_| "alloc returned a negative pointer")?;
+
+        self.bytes.clear();
+        for cell in &frame.cells {
+            self.bytes
+                .extend_from_slice(&u32::from(cell.ch).to_le_bytes());
+            self.bytes.extend_from_slice(&encode(cell.pen.fg));
+            self.bytes.extend_from_slice(&encode(cell.pen.bg));
+        }
+        self.memory
+            .write(&mut self.store, offset, &self.bytes)
+            .map_err(|error| format!("can't write the frame into the plugin: {error}"))?;
+
+        let cells = u64::try_from(frame.cells.len())?;
+        refuel(
+            &mut self.store,
+            FUEL.saturating_add(cells.saturating_mul(FUEL_PER_CELL)),
+        );
+        self.transform
+            .call(
+                &mut self.store,
+                (
+                    ptr,
+                    i32::try_from(frame.width)?,
+                    i32::try_from(frame.height)?,
+                    i32::try_from(number)?,
+                ),
+            )
+            .map_err(|error| fuel_error(error, &format!("frame {number}")))?;
+
+        self.memory
+            .read(&self.store, offset, &mut self.bytes)
+            .map_err(|error| format!("can't read the frame back from the plugin: {error}"))?;
+        for (cell, bytes) in frame
+            .cells
+            .iter_mut()
+            .zip(self.bytes.chunks_exact(CELL_SIZE))
+        {
+            let ch = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+            *cell = Cell {
+                // Anything that isn't a character gets blanked out
+                ch: char::from_u32(ch)
+                    .filter(|ch| !ch.is_control())
+                    .unwrap_or(' '),
+                pen: Pen {
+                    fg: decode(&bytes[4..8]),
+                    bg: decode(&bytes[8..]),
+                },
+            };
+        }
+        Ok(())
+    }
+}
+
+fn refuel(store: &mut Store<()>, fuel: u64) {
+    store.set_fuel(fuel).expect("plugins are always metered");
+}
+
+/// Tells plugins that ran out of fuel apart from ones that failed otherwise
+fn fuel_error(error: wasmi::Error, on: &str) -> Box<dyn Error + Send + Sync> {
+    if error.as_trap_code() == Some(TrapCode::OutOfFuel) {
+        format!("the plugin ran out of fuel on {on}, it may be stuck in a loop").into()
+    } else {
+        error.into()
+    }
+}
+
+/// Colors are a kind, then its value: 0 for the terminal's default, 1 for
+/// a palette index in the next byte, 2 for the RGB in the next three
+fn encode(color: Option<Color>) -> [u8; 4] {
+    match color {
+        None => [0; 4],
+        Some(Color::Indexed(index)) => [1, index, 0, 0],
+        Some(Color::Rgb([r, g, b])) => [2, r, g, b],
+    }
+}
+
+fn decode(bytes: &[u8]) -> Option<Color> {
+    match bytes[0] {
+        1 => Some(Color::Indexed(bytes[1])),
+        2 => Some(Color::Rgb([bytes[1], bytes[2], bytes[3]])),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    /// A plugin that exports one page of memory, an `alloc` that always
+    /// hands out its start, and a `transform` with the body `code`
+    fn plugin(code: &[u8]) -> Plugin {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        // Types of `alloc` and `transform`
+        wasm.extend_from_slice(b"\x01\x0d\x02\x60\x01\x7f\x01\x7f\x60\x04\x7f\x7f\x7f\x7f\x00");
+        // Functions, then one page of memory
+        wasm.extend_from_slice(b"\x03\x03\x02\x00\x01\x05\x03\x01\x00\x01");
+        wasm.extend_from_slice(
+            b"\x07\x1e\x03\x06memory\x02\x00\x05alloc\x00\x00\x09transform\x00\x01",
+        );
+        // `alloc` returns 0, `transform` has no locals
+        let size = u8::try_from(code.len() + 1).unwrap();
+        let transform = [&[size, 0], code].concat();
+        wasm.extend_from_slice(&[0x0a, size + 7, 2, 4, 0, 0x41, 0, 0x0b]);
+        wasm.extend_from_slice(&transform);
+
+        let file = NamedTempFile::new().unwrap();
+        write(file.path(), wasm).unwrap();
+        Plugin::load(file.path()).unwrap()
+    }
+
+    #[test]
+    fn runs_plugins() {
+        let mut instance = plugin(b"\x0b").instantiate().unwrap();
+        let mut frame = String::from("\x1b[38;5;1m@#\x1b[0m\n");
+        instance.apply(&mut frame, 0).unwrap();
+        assert_eq!(
+            AsciiFrame::parse(&frame),
+            AsciiFrame::parse("\x1b[38;5;1m@#")
+        );
+    }
+
+    #[test]
+    fn stops_plugins_stuck_in_a_loop() {
+        // loop, br 0, end
+        let mut instance = plugin(b"\x03\x40\x0c\x00\x0b\x0b").instantiate().unwrap();
+        let error = instance.apply(&mut "@".to_string(), 0).unwrap_err();
+        assert!(error.to_string().contains("ran out of fuel on frame 0"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Turning raw pixels into characters with nothing but `alloc`, for devices
+//! that drive a character display rather than a terminal
+
+use alloc::string::String;
+
+use crate::ansi::Rgb;
+
+/// Characters from darkest to brightest, each with the highest brightness
+/// it stands for
+pub type Ramp = [(u8, char)];
+
+pub const DEFAULT: &Ramp = &[
+    (20, ' '),
+    (40, '.'),
+    (80, ':'),
+    (100, '-'),
+    (130, '='),
+    (200, '+'),
+    (250, '#'),
+    (255, '@'),
+];
+
+/// Fewer characters that are easier to tell apart, since dense ramps turn
+/// into noise at badge and avatar sizes
+pub const TINY: &Ramp = &[(50, ' '), (120, ':'), (200, '#'), (255, '@')];
+
+/// Character of the ramp that stands for `brightness`. Anything brighter
+/// than the ramp goes to its last character.
+#[inline]
+pub fn pick(ramp: &Ramp, brightness: u8) -> char {
+    ramp.iter()
+        .find(|(max, _)| brightness <= *max)
+        .or(ramp.last())
+        .map_or(' ', |(_, ch)| *ch)
+}
+
+/// Perceived brightness of a color, using the Rec. 601 weights
+#[inline]
+pub fn luma([r, g, b]: Rgb) -> u8 {
+    #[allow(clippy::cast_possible_truncation)]
+    let luma = ((299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000) as u8;
+    luma
+}
+
+/// Draws `width` by `height` packed RGBA pixels as `columns` by `rows`
+/// characters of `ramp`, one line per row. Each character stands for the
+/// mean brightness of the pixels under it, with transparent ones counting
+/// as black. Returns `None` when `pixels` isn't the size it should be.
+pub fn render_rgba(
+    pixels: &[u8],
+    (width, height): (u32, u32),
+    (columns, rows): (u32, u32),
+    ramp: &Ramp,
+) -> Option<String> {
+    let (width, height) = (width as usize, height as usize);
+    if width.checked_mul(height)?.checked_mul(4)? != pixels.len() {
+        return None;
+    }
+
+    let (columns, rows) = (columns as usize, rows as usize);
+    let mut text = String::with_capacity((columns + 1) * rows);
+    for row in 0..rows {
+        let ys = span(row, rows, height);
+        for column in 0..columns {
+            let xs = span(column, columns, width);
+            let (mut sum, mut count) = (0_u64, 0_u64);
+            for y in ys.clone() {
+                for pixel in pixels[(y * width + xs.start) * 4..(y * width + xs.end) * 4].chunks(4)
+                {
+                    let alpha = u64::from(pixel[3]);
+                    sum += u64::from(luma([pixel[0], pixel[1], pixel[2]])) * alpha / 255;
+                    count += 1;
+                }
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            let brightness = sum.checked_div(count).unwrap_or(0) as u8;
+            text.push(pick(ramp, brightness));
+        }
+        text.push('\n');
+    }
+    Some(text)
+}
+
+/// Pixels under cell `cell` out of `cells`, along a side `pixels` long. Cells
+/// smaller than a pixel still get the one they start on.
+fn span(cell: usize, cells: usize, pixels: usize) -> core::ops::Range<usize> {
+    let start = cell * pixels / cells;
+    let end = ((cell + 1) * pixels / cells).max(start + 1).min(pixels);
+    start.min(end)..end
+}
+
+/// Same as [`render_rgba`], for images decoded by the `image` crate
+#[cfg(feature = "image")]
+pub fn render_image(image: &image::DynamicImage, size: (u32, u32), ramp: &Ramp) -> String {
+    let rgba = image.to_rgba8();
+    render_rgba(rgba.as_raw(), rgba.dimensions(), size, ramp)
+        .expect("an image holds as many pixels as its size says")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(levels: &[u8]) -> alloc::vec::Vec<u8> {
+        levels
+            .iter()
+            .flat_map(|&level| [level, level, level, 255])
+            .collect()
+    }
+
+    #[test]
+    fn picks_from_the_ramp() {
+        assert_eq!(pick(DEFAULT, 0), ' ');
+        assert_eq!(pick(DEFAULT, 40), '.');
+        assert_eq!(pick(DEFAULT, 41), ':');
+        assert_eq!(pick(&[(100, 'a')], 200), 'a');
+        assert_eq!(pick(&[], 0), ' ');
+        assert_eq!(luma([255, 255, 255]), 255);
+    }
+
+    #[test]
+    fn averages_the_pixels_under_each_cell() {
+        let pixels = gray(&[0, 0, 255, 255, 0, 0, 255, 255]);
+        assert_eq!(
+            render_rgba(&pixels, (4, 2), (2, 1), DEFAULT).unwrap(),
+            " @\n"
+        );
+        assert_eq!(
+            render_rgba(&pixels, (4, 2), (4, 2), TINY).unwrap(),
+            "  @@\n  @@\n"
+        );
+        // Upscaled, cells share pixels
+        assert_eq!(
+            render_rgba(&pixels, (4, 2), (8, 1), TINY).unwrap(),
+            "    @@@@\n"
+        );
+        // Half covered at 255 comes out at 127
+        assert_eq!(
+            render_rgba(&pixels, (4, 2), (1, 1), DEFAULT).unwrap(),
+            "=\n"
+        );
+    }
+
+    #[test]
+    fn counts_transparency_as_black() {
+        let pixels = [255, 255, 255, 0, 255, 255, 255, 255];
+        assert_eq!(render_rgba(&pixels, (2, 1), (2, 1), TINY).unwrap(), " @\n");
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn takes_decoded_images() {
+        let image = image::RgbaImage::from_raw(4, 2, gray(&[0, 0, 255, 255, 0, 0, 255, 255]));
+        let image = image::DynamicImage::ImageRgba8(image.unwrap());
+        assert_eq!(render_image(&image, (2, 1), DEFAULT), " @\n");
+    }
+
+    #[test]
+    fn rejects_the_wrong_size() {
+        assert_eq!(render_rgba(&gray(&[0; 3]), (2, 2), (1, 1), DEFAULT), None);
+        assert_eq!(render_rgba(&[], (0, 0), (2, 1), DEFAULT).unwrap(), "  \n");
+        assert_eq!(
+            render_rgba(&gray(&[0]), (1, 1), (0, 0), DEFAULT).unwrap(),
+            ""
+        );
+    }
+}
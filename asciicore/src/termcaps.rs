@@ -0,0 +1,165 @@
+use std::{
+    env::var,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    process::Command,
+};
+
+/// What the terminal we're running in is capable of
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TermCaps {
+    pub truecolor: bool,
+    /// Whether frames can be wrapped in synchronized updates (mode 2026),
+    /// so that they never show up half drawn
+    pub synchronized_output: bool,
+    /// Columns and rows
+    pub size: Option<(u32, u32)>,
+    /// Width and height of a single cell, in pixels
+    pub cell_pixels: Option<(u32, u32)>,
+    /// Whether the terminal can show sixel graphics
+    pub sixel: bool,
+    /// Whether the terminal can show images with the kitty graphics protocol
+    pub kitty_graphics: bool,
+    /// Old Windows consoles, which print escapes instead of following them.
+    /// Nothing here can tell, so it's up to the player to find out, and to
+    /// draw frames through the console API there.
+    pub legacy_console: bool,
+}
+
+/// Asks the terminal about itself. Terminals that don't answer some of the
+/// queries simply get the conservative default for them.
+pub fn probe() -> TermCaps {
+    let mut caps = TermCaps {
+        truecolor: colorterm_truecolor(),
+        size: terminal_size(),
+        ..TermCaps::default()
+    };
+
+    // Synchronized update mode, cell size, the current colors after setting
+    // a 24-bit one (DECRQSS), a one pixel kitty graphics query and primary
+    // device attributes. Every terminal answers the latter, so once it shows
+    // up there's nothing left to wait for.
+    let Some(reply) = query(
+        "\x1b[?2026$p\x1b[16t\x1b[38;2;1;2;3m\x1bP$qm\x1b\\\x1b[0m\
+        \x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c",
+    ) else {
+        return caps;
+    };
+    caps.kitty_graphics = reply.contains("\x1b_Gi=31;OK");
+
+    // Terminals without truecolor report some approximation of the color
+    if reply.contains("38;2;1;2;3") || reply.contains("38:2::1:2:3") || reply.contains("38:2:1:2:3")
+    {
+        caps.truecolor = true;
+    }
+
+    for (params, end) in csi_replies(&reply) {
+        match end {
+            "$y" => {
+                // DECRPM: ?2026;1 means set, ?2026;2 means reset but supported
+                if let Some(("?2026", status)) = params.split_once(';') {
+                    caps.synchronized_output = matches!(status, "1" | "2");
+                }
+            }
+            "t" => {
+                let numbers = params.split(';').collect::<Vec<_>>();
+                if let ["6", height, width] = numbers[..] {
+                    caps.cell_pixels = width.parse().ok().zip(height.parse().ok());
+                }
+            }
+            // DA1 lists what the terminal supports, 4 being sixels
+            "c" => {
+                if let Some(attributes) = params.strip_prefix('?') {
+                    caps.sixel = attributes.split(';').any(|attribute| attribute == "4");
+                }
+            }
+            _ => (),
+        }
+    }
+
+    caps
+}
+
+/// Columns and rows of the controlling terminal, if there's one
+pub fn terminal_size() -> Option<(u32, u32)> {
+    let output = Command::new("stty")
+        .arg("size")
+        .stdin(File::open("/dev/tty").ok()?)
+        .output()
+        .ok()?;
+
+    let size = String::from_utf8(output.stdout).ok()?;
+    let (rows, columns) = size.trim().split_once(' ')?;
+    Some((columns.parse().ok()?, rows.parse().ok()?))
+}
+
+#[inline]
+fn colorterm_truecolor() -> bool {
+    var("COLORTERM").is_ok_and(|colorterm| colorterm == "truecolor" || colorterm == "24bit")
+}
+
+/// Waits for the terminal to draw everything written to it so far, since
+/// it only answers queries once it gets to them
+pub fn catch_up() {
+    query("\x1b[c");
+}
+
+/// Sends `queries` to the terminal and collects everything it answers until
+/// the reply to DA1 arrives, or until it stays quiet for half a second
+fn query(queries: &str) -> Option<String> {
+    let stty = |args: &[&str]| {
+        Command::new("stty")
+            .args(args)
+            .stdin(File::open("/dev/tty").ok()?)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+    };
+
+    let saved = String::from_utf8(stty(&["-g"])?.stdout).ok()?;
+    // Raw mode, with reads giving up after 5 deciseconds of silence
+    stty(&["raw", "-echo", "min", "0", "time", "5"])?;
+
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok();
+    let mut reply = Vec::new();
+
+    if let Some(tty) = &mut tty {
+        if tty.write_all(queries.as_bytes()).is_ok() {
+            let mut buffer = [0; 64];
+            while let Ok(read @ 1..) = tty.read(&mut buffer) {
+                reply.extend_from_slice(&buffer[..read]);
+                if csi_replies(&String::from_utf8_lossy(&reply))
+                    .iter()
+                    .any(|(params, end)| *end == "c" && params.starts_with('?'))
+                {
+                    break;
+                }
+            }
+        }
+    }
+
+    stty(&[saved.trim()]);
+    Some(String::from_utf8_lossy(&reply).into_owned())
+}
+
+/// Parameters and final bytes of every `ESC [` sequence in a reply. `$` is
+/// kept along with the final byte since DECRPM ends with `$y`.
+fn csi_replies(reply: &str) -> Vec<(&str, &str)> {
+    reply
+        .split("\x1b[")
+        .skip(1)
+        .filter_map(|sequence| {
+            let end = sequence.find(|c: char| c.is_ascii_alphabetic())?;
+            let params = &sequence[..end];
+            match params.strip_suffix('$') {
+                Some(params) => Some((params, &sequence[end - 1..=end])),
+                None => Some((params, &sequence[end..=end])),
+            }
+        })
+        .collect()
+}
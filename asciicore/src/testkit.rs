@@ -0,0 +1,151 @@
+//! Tiny samples along with what `raster` and `ansi` make of them, for code
+//! that embeds asciicore to write regression tests against.
+//!
+//! # Stability
+//!
+//! Samples never change: new ones get added under new names instead.
+//! Goldens only change in releases that bump the minor version (the `y` of
+//! `0.y.z` while the crate is below 1.0), and every change is listed in the
+//! release notes. A patch release that renders any sample differently from
+//! its golden is a bug.
+
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{
+    ansi::AsciiFrame,
+    raster::{render_rgba, DEFAULT},
+};
+
+/// Packed RGBA pixels, along with the text [`render_rgba`] draws them as
+/// with the [`DEFAULT`] ramp at `columns`
+pub struct Sample {
+    pub name: &'static str,
+    pub size: (u32, u32),
+    pub pixels: Vec<u8>,
+    /// Columns and rows of the golden
+    pub columns: (u32, u32),
+    pub golden: &'static str,
+}
+
+impl Sample {
+    /// Draws the sample the way its golden was drawn
+    pub fn render(&self) -> String {
+        render_rgba(&self.pixels, self.size, self.columns, DEFAULT)
+            .expect("samples hold as many pixels as their size says")
+    }
+}
+
+/// Every sample, each small enough to read its golden at a glance
+pub fn samples() -> Vec<Sample> {
+    let gray = |levels: &mut dyn Iterator<Item = u8>| {
+        levels
+            .flat_map(|level| [level, level, level, u8::MAX])
+            .collect()
+    };
+    Vec::from([
+        Sample {
+            name: "gradient",
+            size: (8, 1),
+            pixels: gray(&mut [0, 30, 60, 90, 120, 180, 240, 255].into_iter()),
+            columns: (8, 1),
+            golden: " .:-=+#@\n",
+        },
+        Sample {
+            name: "checkerboard",
+            size: (4, 4),
+            pixels: gray(&mut (0..16).map(|i| if (i / 4 + i % 4) % 2 == 0 { 0 } else { 255 })),
+            columns: (4, 2),
+            golden: "====\n====\n",
+        },
+        Sample {
+            name: "fade-out",
+            size: (4, 1),
+            pixels: (0..4).flat_map(|x| [255, 255, 255, 255 - x * 85]).collect(),
+            columns: (4, 1),
+            golden: "@+- \n",
+        },
+    ])
+}
+
+/// A frame with palette, 24-bit and default colors, the kinds `ansi` reads
+pub const ANSI_SAMPLE: &str =
+    "\x1b[38;5;9mab\x1b[48;2;1;2;3mc\x1b[0m\n\x1b[34md\x1b[39m e\x1b[0m\n";
+
+/// What [`AsciiFrame::diff`] draws for [`ANSI_SAMPLE`] over a blank screen
+pub const ANSI_GOLDEN: &str = "\x1b[2J\x1b[1;1H\x1b[0;91mab\x1b[0;91;48;2;1;2;3mc\
+    \x1b[2;1H\x1b[0;34md\x1b[0m e\x1b[3;1H";
+
+/// Panics unless `actual` matches the golden of `sample`, naming the first
+/// row that doesn't
+#[track_caller]
+pub fn assert_golden(sample: &Sample, actual: &str) {
+    let mismatch = actual
+        .lines()
+        .zip(sample.golden.lines())
+        .position(|(actual, golden)| actual != golden)
+        .or_else(|| {
+            // One of them stops early
+            let rows = (actual.lines().count(), sample.golden.lines().count());
+            (rows.0 != rows.1).then_some(rows.0.min(rows.1))
+        });
+    if let Some(row) = mismatch {
+        panic!(
+            "{} doesn't match its golden from row {}:\n{actual}\nexpected:\n{}",
+            sample.name,
+            row + 1,
+            sample.golden
+        );
+    }
+}
+
+/// Panics unless both frames put the same cells on screen, however their
+/// escapes are written, naming the first cell that differs
+#[track_caller]
+pub fn assert_frame_eq(actual: &str, expected: &str) {
+    let (actual, expected) = (AsciiFrame::parse(actual), AsciiFrame::parse(expected));
+    let size = |frame: &AsciiFrame| format!("{}x{}", frame.width, frame.height);
+    assert!(
+        (actual.width, actual.height) == (expected.width, expected.height),
+        "frames are {} and {}",
+        size(&actual),
+        size(&expected)
+    );
+    if let Some(index) = (0..actual.cells.len()).find(|&i| actual.cells[i] != expected.cells[i]) {
+        let width = actual.width.max(1);
+        panic!(
+            "cells at row {}, column {} differ: {:?} and {:?}",
+            index / width + 1,
+            index % width + 1,
+            actual.cells[index],
+            expected.cells[index]
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_match_their_goldens() {
+        for sample in samples() {
+            assert_golden(&sample, &sample.render());
+        }
+    }
+
+    #[test]
+    fn ansi_sample_matches_its_golden() {
+        let frame = AsciiFrame::parse(ANSI_SAMPLE);
+        assert_eq!(frame.diff(&AsciiFrame::default()), ANSI_GOLDEN);
+        assert_frame_eq(
+            &crate::ansi::render(&crate::ansi::cells(ANSI_SAMPLE)),
+            ANSI_SAMPLE,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "row 2, column 1")]
+    fn names_the_first_cell_that_differs() {
+        assert_frame_eq("ab\ncd\n", "ab\nxd\n");
+    }
+}
@@ -0,0 +1,58 @@
+//! Frames as ratatui widgets, so that TUI applications can lay them out
+//! next to their own without printing any escapes. Frames of a playing
+//! archive go through [`AsciiFrame::parse`] first, one at a time.
+
+use ratatui::{buffer::Buffer, layout::Rect, style, widgets::Widget};
+
+use crate::ansi::{AsciiFrame, Color};
+
+impl From<Color> for style::Color {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Indexed(index) => Self::Indexed(index),
+            Color::Rgb([r, g, b]) => Self::Rgb(r, g, b),
+        }
+    }
+}
+
+/// Draws from the top left of `area`, cutting off whatever doesn't fit.
+/// Cells without a color of their own get the terminal's.
+impl Widget for &AsciiFrame {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = area.intersection(buf.area);
+        for (row, y) in self.rows().zip(area.top()..area.bottom()) {
+            for (cell, x) in row.iter().zip(area.left()..area.right()) {
+                let Some(target) = buf.cell_mut((x, y)) else {
+                    continue;
+                };
+                target
+                    .set_char(cell.ch)
+                    .set_fg(cell.pen.fg.map_or(style::Color::Reset, Into::into))
+                    .set_bg(cell.pen.bg.map_or(style::Color::Reset, Into::into));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_cells_into_the_buffer() {
+        let frame = AsciiFrame::parse("\x1b[38;5;9mab\x1b[48;2;1;2;3mc\x1b[0m\nd\n");
+        let mut buf = Buffer::empty(Rect::new(0, 0, 4, 3));
+        // Taller than the buffer, and too narrow for the third column
+        frame.render(Rect::new(1, 1, 2, 5), &mut buf);
+
+        let mut expected = Buffer::with_lines(["    ", " ab ", " d  "]);
+        for x in 1..3 {
+            expected[(x, 1)].set_fg(style::Color::Indexed(9));
+        }
+        assert_eq!(buf, expected);
+        assert_eq!(
+            style::Color::from(Color::Rgb([1, 2, 3])),
+            style::Color::Rgb(1, 2, 3)
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Cursor, ErrorKind, Read},
+    ops::{Deref, Range},
+    path::Path,
+    sync::Arc,
+    vec::IntoIter,
+};
+
+use asciicore::{
+    container::{is_v3, read_index},
+    frame::{check_size, parse_index},
+};
+use tar::Archive;
+
+/// A whole archive, read into a single buffer. Frames and audio are handed
+/// out as slices of it, so nothing gets copied again before decompression.
+pub struct Bapple {
+    data: Arc<[u8]>,
+    frames: Vec<Range<usize>>,
+    audio: Option<Range<usize>>,
+}
+
+impl Bapple {
+    /// Reads the archive and finds where each entry sits in it. Frames are
+    /// checked against the `index` entry, when there is one.
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        let mut index = HashMap::new();
+        let mut frames = Vec::new();
+        let mut audio = None;
+
+        for (name, range) in entries(&data)? {
+            let stem = Path::new(&name)
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy();
+
+            match &*stem {
+                "metadata" | "events" => {}
+                "index" => index = parse_index(&String::from_utf8_lossy(&data[range])),
+                "audio" => audio = Some(range),
+                // Other tracks get read on their own, see `read_track`
+                track if track.starts_with("audio") => {}
+                number => frames.push((number.parse::<usize>().map_err(invalid_data)?, range)),
+            }
+        }
+
+        frames.sort_by_key(|(number, _)| *number);
+        let (numbers, frames): (Vec<_>, _) = frames.into_iter().unzip();
+        let bapple = Self {
+            data: data.into(),
+            frames,
+            audio,
+        };
+
+        for (number, frame) in numbers.into_iter().zip(bapple.frames()) {
+            check_size(&index, number, frame)?;
+        }
+        Ok(bapple)
+    }
+
+    /// Compressed frames, in order, borrowed from the archive
+    pub fn frames(&self) -> impl ExactSizeIterator<Item = &[u8]> {
+        self.frames.iter().map(|range| &self.data[range.clone()])
+    }
+
+    pub fn audio(&self) -> Option<&[u8]> {
+        Some(&self.data[self.audio.clone()?])
+    }
+
+    /// Compressed frames that keep the archive alive on their own, for
+    /// handing out to other threads
+    pub fn into_frames(self) -> IntoFrames {
+        IntoFrames {
+            archives: VecDeque::from([(self.data, self.frames.into_iter())]),
+        }
+    }
+}
+
+/// A compressed frame sharing its archive's buffer
+pub struct Frame {
+    data: Arc<[u8]>,
+    range: Range<usize>,
+}
+
+impl Deref for Frame {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data[self.range.clone()]
+    }
+}
+
+/// An archive's buffer, along with the frames in it still to hand out
+type Remaining = (Arc<[u8]>, IntoIter<Range<usize>>);
+
+pub struct IntoFrames {
+    archives: VecDeque<Remaining>,
+}
+
+impl IntoFrames {
+    /// Hands out the frames of `next` once these run out, for archives
+    /// split in parts
+    pub fn chain(mut self, next: Self) -> Self {
+        self.archives.extend(next.archives);
+        self
+    }
+}
+
+impl Iterator for IntoFrames {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        loop {
+            let (data, frames) = self.archives.front_mut()?;
+            if let Some(range) = frames.next() {
+                return Some(Frame {
+                    data: Arc::clone(data),
+                    range,
+                });
+            }
+            self.archives.pop_front();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.archives.iter().map(|(_, frames)| frames.len()).sum();
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for IntoFrames {}
+
+/// Names of the entries in `data`, along with where each one sits in it
+fn entries(data: &[u8]) -> io::Result<Vec<(String, Range<usize>)>> {
+    let mut cursor = Cursor::new(data);
+    if is_v3(&mut cursor)? {
+        // Every range is already known to lie within `data`
+        return read_index(&mut cursor)?
+            .into_iter()
+            .map(|(name, range)| {
+                let start = usize::try_from(range.start).map_err(invalid_data)?;
+                let end = usize::try_from(range.end).map_err(invalid_data)?;
+                Ok((name, start..end))
+            })
+            .collect();
+    }
+
+    let mut entries = Vec::new();
+    for entry in Archive::new(data).entries()? {
+        let entry = entry?;
+        let start = usize::try_from(entry.raw_file_position()).map_err(invalid_data)?;
+        let range = start..start + usize::try_from(entry.size()).map_err(invalid_data)?;
+        entries.push((entry.path()?.to_string_lossy().into_owned(), range));
+    }
+    Ok(entries)
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,77 @@
+use std::{
+    fs::write,
+    io,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    time::Duration,
+};
+
+use tempfile::TempDir;
+
+/// The archive's audio tracks, played through mpv one at a time. Switching
+/// tracks starts mpv over on the new one, from where the old one was.
+pub struct Audio {
+    /// Keeps the tracks around for as long as mpv might need them
+    _dir: TempDir,
+    tracks: Vec<PathBuf>,
+    current: usize,
+    mpv: Option<Child>,
+}
+
+impl Audio {
+    pub fn new(tracks: &[Vec<u8>]) -> io::Result<Self> {
+        let dir = TempDir::new()?;
+        let tracks = (1..)
+            .zip(tracks)
+            .map(|(number, track)| {
+                let path = dir.path().join(format!("audio{number}.mp3"));
+                write(&path, track)?;
+                Ok(path)
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            _dir: dir,
+            tracks,
+            current: 0,
+            mpv: None,
+        })
+    }
+
+    /// Starts track `track`, counting from 0, `at` into it
+    pub fn play(&mut self, track: usize, at: Duration) {
+        self.stop();
+        self.current = track;
+
+        let mut mpv = Command::new("mpv");
+        if !at.is_zero() {
+            mpv.arg(format!("--start={:.3}", at.as_secs_f64()));
+        }
+        // Keeps mpv off the terminal, which the player is drawing on
+        self.mpv = mpv
+            .arg(&self.tracks[track])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok();
+    }
+
+    /// Moves on to the next track, or back to the first one
+    pub fn next(&mut self, at: Duration) {
+        self.play((self.current + 1) % self.tracks.len(), at);
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut mpv) = self.mpv.take() {
+            mpv.kill().ok();
+            mpv.wait().ok();
+        }
+    }
+}
+
+impl Drop for Audio {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
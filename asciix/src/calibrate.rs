@@ -0,0 +1,176 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::write,
+    io::{stdout, IsTerminal, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use asciicore::termcaps::{catch_up, probe};
+use clap::ArgMatches;
+
+use crate::BoxResult;
+
+/// How long each of the measurements runs for
+const MEASURE_FOR: Duration = Duration::from_secs(1);
+/// Framerates worth recommending, from the smoothest one down
+const FRAMERATES: [u32; 3] = [30, 24, 15];
+/// Compression thresholds, along with how many times more color changes
+/// they save compared to the default. Doubling the threshold roughly halves
+/// the changes on most videos.
+const THRESHOLDS: [(u8, f64); 3] = [(10, 1.0), (20, 2.0), (40, 4.0)];
+/// Characters the plain measurement draws with
+const RAMP: &[u8] = b" .:-=+*#%@";
+
+/// What the terminal could keep up with, and the settings that fit in that
+pub struct Calibration {
+    /// Bytes of plain text drawn per second
+    throughput: u64,
+    /// Colored cells drawn per second
+    escapes: u64,
+    size: (u32, u32),
+    /// `truecolor`, `256`, or `none` when colors would slow it down too much
+    color_depth: &'static str,
+    framerate: u32,
+    threshold: u8,
+}
+
+/// Floods the terminal with frames for a little while, first in plain text
+/// and then in colors, and works out how much of that it can take
+pub fn calibrate(matches: &ArgMatches) -> BoxResult<()> {
+    if !stdout().is_terminal() {
+        return Err("calibrate has to run in the terminal it measures".into());
+    }
+    let caps = probe();
+    // The last row stays free for the cursor
+    let (columns, rows) = caps
+        .size
+        .filter(|&(columns, rows)| columns > 0 && rows > 1)
+        .map_or((80, 23), |(columns, rows)| (columns, rows - 1));
+
+    let (_, throughput) = measure(|number| plain_frame(columns, rows, number))?;
+    let (framerate, _) = measure(|number| color_frame(columns, rows, number))?;
+    print!("\x1b[0m\x1b[2J\x1b[H");
+
+    let escapes = f64::from(columns * rows) * framerate;
+    let calibration = recommend((columns, rows), caps.truecolor, throughput, escapes);
+
+    print!("{calibration}");
+    if let Some(path) = matches.get_one::<PathBuf>("output") {
+        write(path, calibration.to_string())?;
+        println!(
+            "Compile with `asciic --from-calibration {}` to use these settings",
+            path.display()
+        );
+    }
+    println!(
+        "Videos above {} fps get frames dropped, `asciic retime --fps {0}` avoids that",
+        calibration.framerate
+    );
+    Ok(())
+}
+
+/// Draws frames from `frame` for [`MEASURE_FOR`], then waits until the
+/// terminal is done with them. Gives back how many frames and bytes it drew
+/// per second.
+fn measure(frame: impl Fn(usize) -> Vec<u8>) -> BoxResult<(f64, f64)> {
+    let mut out = stdout().lock();
+    let start = Instant::now();
+    let mut bytes = 0;
+    let mut frames = 0;
+    while start.elapsed() < MEASURE_FOR {
+        let frame = frame(frames);
+        out.write_all(&frame)?;
+        bytes += frame.len();
+        frames += 1;
+    }
+    out.flush()?;
+    drop(out);
+    catch_up();
+
+    let seconds = start.elapsed().as_secs_f64();
+    #[allow(clippy::cast_precision_loss)]
+    Ok((frames as f64 / seconds, bytes as f64 / seconds))
+}
+
+fn plain_frame(columns: u32, rows: u32, number: usize) -> Vec<u8> {
+    let mut frame = b"\x1b[H".to_vec();
+    for y in 0..rows as usize {
+        frame.extend((0..columns as usize).map(|x| RAMP[(x + y + number) % RAMP.len()]));
+        frame.push(b'\n');
+    }
+    frame
+}
+
+/// A frame where every cell changes color, as in the worst case
+fn color_frame(columns: u32, rows: u32, number: usize) -> Vec<u8> {
+    let mut frame = b"\x1b[H".to_vec();
+    for y in 0..rows as usize {
+        for x in 0..columns as usize {
+            let [r, g, b] = [x * 7 + number, y * 13 + number, x + y + number * 5].map(|c| c % 256);
+            write!(frame, "\x1b[48;2;{r};{g};{b}m ").unwrap();
+        }
+        frame.extend_from_slice(b"\x1b[0m\n");
+    }
+    frame
+}
+
+/// Picks the smoothest framerate the terminal keeps up with at its own size,
+/// raising the threshold first. Shrinks the frames when nothing fits, and
+/// drops the colors when that would take shrinking them by more than half.
+fn recommend(size: (u32, u32), truecolor: bool, throughput: f64, escapes: f64) -> Calibration {
+    let (columns, rows) = size;
+    let cells = f64::from(columns * rows);
+    let plain_bytes = f64::from((columns + 1) * rows);
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let calibration = |size, color_depth, framerate, threshold| Calibration {
+        throughput: throughput as u64,
+        escapes: escapes as u64,
+        size,
+        color_depth,
+        framerate,
+        threshold,
+    };
+    let color_depth = if truecolor { "truecolor" } else { "256" };
+
+    for framerate in FRAMERATES {
+        let rate = f64::from(framerate);
+        for (threshold, saved) in THRESHOLDS {
+            if cells * rate <= escapes * saved && plain_bytes * rate <= throughput {
+                return calibration(size, color_depth, framerate, threshold);
+            }
+        }
+    }
+
+    let (threshold, saved) = THRESHOLDS[THRESHOLDS.len() - 1];
+    let framerate = FRAMERATES[FRAMERATES.len() - 1];
+    let rate = f64::from(framerate);
+    let scale = (escapes * saved / (cells * rate)).sqrt();
+    if scale >= 0.5 {
+        calibration(shrink(size, scale), color_depth, framerate, threshold)
+    } else {
+        let scale = (throughput / (plain_bytes * rate)).sqrt();
+        calibration(shrink(size, scale), "none", framerate, THRESHOLDS[0].0)
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn shrink((columns, rows): (u32, u32), scale: f64) -> (u32, u32) {
+    let scale = scale.min(1.0);
+    (
+        ((f64::from(columns) * scale) as u32).max(1),
+        ((f64::from(rows) * scale) as u32).max(1),
+    )
+}
+
+/// The same `key=value` lines as archive metadata, for asciic to read back
+impl Display for Calibration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "throughput={}", self.throughput)?;
+        writeln!(f, "escapes={}", self.escapes)?;
+        writeln!(f, "size={}x{}", self.size.0, self.size.1)?;
+        writeln!(f, "color_depth={}", self.color_depth)?;
+        writeln!(f, "framerate={}", self.framerate)?;
+        writeln!(f, "threshold={}", self.threshold)
+    }
+}
@@ -0,0 +1,263 @@
+use std::io::{self, Write};
+
+use asciicore::ansi::{AsciiFrame, Color, Rgb};
+
+/// Colors of the old Windows console, in the order of their attribute bits:
+/// blue is 1, green 2, red 4, and 8 makes them bright
+const PALETTE: [Rgb; 16] = [
+    [0, 0, 0],
+    [0, 0, 128],
+    [0, 128, 0],
+    [0, 128, 128],
+    [128, 0, 0],
+    [128, 0, 128],
+    [128, 128, 0],
+    [192, 192, 192],
+    [128, 128, 128],
+    [0, 0, 255],
+    [0, 255, 0],
+    [0, 255, 255],
+    [255, 0, 0],
+    [255, 0, 255],
+    [255, 255, 0],
+    [255, 255, 255],
+];
+/// Gray on black, what the console starts with
+const DEFAULT_FG: u16 = 7;
+const DEFAULT_BG: u16 = 0;
+
+/// A character with the console attributes it's drawn with: the foreground
+/// in the low 4 bits and the background in the next 4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsoleCell {
+    pub ch: char,
+    pub attributes: u16,
+}
+
+/// The cells of a frame, with colors brought down to the 16 the console has
+pub fn console_cells(frame: &AsciiFrame) -> Vec<ConsoleCell> {
+    frame
+        .cells
+        .iter()
+        .map(|cell| {
+            let fg = cell.pen.fg.map_or(DEFAULT_FG, attribute);
+            let bg = cell.pen.bg.map_or(DEFAULT_BG, attribute);
+            ConsoleCell {
+                ch: cell.ch,
+                attributes: fg | bg << 4,
+            }
+        })
+        .collect()
+}
+
+/// Console color closest to `color`
+fn attribute(color: Color) -> u16 {
+    let rgb = match color {
+        Color::Indexed(index @ 0..=15) => return swap_red_blue(u16::from(index)),
+        Color::Indexed(index @ 16..=231) => {
+            let index = index - 16;
+            let level = |level: u8| if level == 0 { 0 } else { 55 + level * 40 };
+            [index / 36, index / 6 % 6, index % 6].map(level)
+        }
+        Color::Indexed(index) => [8 + (index - 232) * 10; 3],
+        Color::Rgb(rgb) => rgb,
+    };
+    let distance = |candidate: &Rgb| {
+        candidate
+            .iter()
+            .zip(rgb)
+            .map(|(&a, b)| (i32::from(a) - i32::from(b)).pow(2))
+            .sum::<i32>()
+    };
+    (0..)
+        .zip(PALETTE)
+        .min_by_key(|(_, candidate)| distance(candidate))
+        .map_or(DEFAULT_FG, |(index, _)| index)
+}
+
+/// Escape sequences number the same 16 colors with red and blue the other
+/// way around, so this goes both ways
+fn swap_red_blue(index: u16) -> u16 {
+    index & 0b1010 | (index & 1) << 2 | (index & 4) >> 2
+}
+
+/// Draws a frame over the last one through the console API, so that
+/// consoles that don't know about escape sequences show colors instead of
+/// printing the escapes
+#[cfg(windows)]
+pub fn draw(out: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    let frame = AsciiFrame::parse(&String::from_utf8_lossy(frame));
+    // Anything still buffered has to land before the console gets written to
+    out.flush()?;
+    windows::draw(&frame, &console_cells(&frame))
+}
+
+/// Elsewhere, frames get the console's 16 colors through escapes instead,
+/// to see what they'll look like there
+#[cfg(not(windows))]
+pub fn draw(out: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    let frame = AsciiFrame::parse(&String::from_utf8_lossy(frame));
+    let cells = console_cells(&frame);
+
+    let mut text = String::from("\r\x1b[2J\r\x1b[H");
+    for row in cells.chunks(frame.width.max(1)) {
+        let mut last = None;
+        for cell in row {
+            if last != Some(cell.attributes) {
+                text.push_str(&escape(cell.attributes));
+                last = Some(cell.attributes);
+            }
+            text.push(cell.ch);
+        }
+        text.push_str("\x1b[0m\n");
+    }
+    out.write_all(text.as_bytes())
+}
+
+/// SGR sequence for console attributes
+#[cfg(not(windows))]
+fn escape(attributes: u16) -> String {
+    let ansi = |color: u16, base: u16| {
+        let index = swap_red_blue(color);
+        if index < 8 {
+            base + index
+        } else {
+            base + 60 + index - 8
+        }
+    };
+    format!(
+        "\x1b[{};{}m",
+        ansi(attributes & 15, 30),
+        ansi(attributes >> 4 & 15, 40)
+    )
+}
+
+/// Whether this console shows escape sequences as text, even after being
+/// asked to process them
+#[cfg(windows)]
+pub fn needs_legacy() -> bool {
+    windows::needs_legacy()
+}
+
+#[cfg(not(windows))]
+pub fn needs_legacy() -> bool {
+    false
+}
+
+/// The few console functions that drawing takes, straight from kernel32
+#[cfg(windows)]
+mod windows {
+    use std::{io, ptr::null_mut};
+
+    use super::ConsoleCell;
+    use asciicore::ansi::AsciiFrame;
+
+    #[allow(clippy::cast_sign_loss)]
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 4;
+
+    type Handle = *mut std::ffi::c_void;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    struct Coord {
+        x: i16,
+        y: i16,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    #[allow(dead_code)] // Filled in by the console
+    struct SmallRect {
+        left: i16,
+        top: i16,
+        right: i16,
+        bottom: i16,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    #[allow(dead_code)] // Filled in by the console
+    struct ScreenBufferInfo {
+        size: Coord,
+        cursor: Coord,
+        attributes: u16,
+        window: SmallRect,
+        maximum_window_size: Coord,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(handle: u32) -> Handle;
+        fn GetConsoleMode(console: Handle, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: Handle, mode: u32) -> i32;
+        fn GetConsoleScreenBufferInfo(console: Handle, info: *mut ScreenBufferInfo) -> i32;
+        fn SetConsoleCursorPosition(console: Handle, position: Coord) -> i32;
+        fn SetConsoleTextAttribute(console: Handle, attributes: u16) -> i32;
+        fn WriteConsoleW(
+            console: Handle,
+            buffer: *const u16,
+            length: u32,
+            written: *mut u32,
+            reserved: *mut std::ffi::c_void,
+        ) -> i32;
+    }
+
+    fn check(result: i32) -> io::Result<()> {
+        if result == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn needs_legacy() -> bool {
+        // SAFETY: the handle comes from the system, and the mode is written
+        // into a local
+        unsafe {
+            let console = GetStdHandle(STD_OUTPUT_HANDLE);
+            let mut mode = 0;
+            if GetConsoleMode(console, &raw mut mode) == 0 {
+                // Not a console, so escapes go wherever they're redirected
+                return false;
+            }
+            SetConsoleMode(console, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) == 0
+        }
+    }
+
+    /// Writes each run of cells sharing their attributes in one go, from
+    /// the top left of the window, then puts the console's colors back
+    pub fn draw(frame: &AsciiFrame, cells: &[ConsoleCell]) -> io::Result<()> {
+        // SAFETY: every pointer handed over points into a live local or
+        // buffer, with the length it's given along with it
+        unsafe {
+            let console = GetStdHandle(STD_OUTPUT_HANDLE);
+            let mut info = ScreenBufferInfo::default();
+            check(GetConsoleScreenBufferInfo(console, &raw mut info))?;
+
+            let mut text = Vec::new();
+            let rows = cells.chunks(frame.width.max(1));
+            // Rows past the end of the buffer have nowhere to go
+            for (y, row) in (info.window.top..info.size.y).zip(rows) {
+                check(SetConsoleCursorPosition(console, Coord { x: 0, y }))?;
+                for run in row.chunk_by(|a, b| a.attributes == b.attributes) {
+                    text.clear();
+                    let mut units = [0; 2];
+                    for cell in run {
+                        text.extend_from_slice(cell.ch.encode_utf16(&mut units));
+                    }
+                    check(SetConsoleTextAttribute(console, run[0].attributes))?;
+                    let mut written = 0;
+                    check(WriteConsoleW(
+                        console,
+                        text.as_ptr(),
+                        u32::try_from(text.len()).unwrap_or(u32::MAX),
+                        &raw mut written,
+                        null_mut(),
+                    ))?;
+                }
+            }
+            check(SetConsoleTextAttribute(console, info.attributes))
+        }
+    }
+}
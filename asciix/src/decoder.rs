@@ -0,0 +1,93 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{available_parallelism, spawn},
+};
+
+use crate::archive::{Frame, IntoFrames};
+use asciicore::frame::decompress;
+
+type Job = (usize, Frame);
+type Decoded = (usize, io::Result<Vec<u8>>);
+
+/// Decompresses frames on a small pool of worker threads, keeping at most
+/// `readahead` of them in flight. Frames still come out in order.
+pub struct Decoder {
+    jobs: Sender<Job>,
+    results: Receiver<Decoded>,
+    compressed: IntoFrames,
+    ready: BTreeMap<usize, io::Result<Vec<u8>>>,
+    queued: usize,
+    next: usize,
+    readahead: usize,
+}
+
+impl Decoder {
+    pub fn new(frames: IntoFrames, readahead: usize) -> Self {
+        let readahead = readahead.max(1);
+        let (jobs, job_recv) = channel::<Job>();
+        let (result_sender, results) = channel();
+        let job_recv = Arc::new(Mutex::new(job_recv));
+
+        let workers = available_parallelism()
+            .map_or(1, usize::from)
+            .min(readahead);
+        for _ in 0..workers {
+            let job_recv = Arc::clone(&job_recv);
+            let result_sender = result_sender.clone();
+            spawn(move || loop {
+                // The lock is only held while waiting for a job, not while decoding it
+                let job = job_recv.lock().unwrap().recv();
+                let Ok((index, frame)) = job else {
+                    break;
+                };
+                if result_sender.send((index, decompress(&frame))).is_err() {
+                    break;
+                }
+            });
+        }
+
+        Self {
+            jobs,
+            results,
+            compressed: frames,
+            ready: BTreeMap::new(),
+            queued: 0,
+            next: 0,
+            readahead,
+        }
+    }
+}
+
+impl Iterator for Decoder {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.queued < self.next + self.readahead {
+            let Some(frame) = self.compressed.next() else {
+                break;
+            };
+            self.jobs.send((self.queued, frame)).ok()?;
+            self.queued += 1;
+        }
+
+        if self.next == self.queued {
+            return None;
+        }
+
+        let frame = loop {
+            if let Some(frame) = self.ready.remove(&self.next) {
+                break frame;
+            }
+            let (index, frame) = self.results.recv().ok()?;
+            self.ready.insert(index, frame);
+        };
+
+        self.next += 1;
+        Some(frame)
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    process::{Command, Stdio},
+    thread::spawn,
+};
+
+/// A key/value pair from the archive's event track, due once its frame shows up
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub frame: usize,
+    pub key: String,
+    pub value: String,
+}
+
+/// Reads `frame key=value` lines, sorted by frame
+pub fn parse_events(data: &str) -> Vec<Event> {
+    let mut events = data
+        .lines()
+        .filter_map(|line| {
+            let (frame, pair) = line.split_once(' ')?;
+            let (key, value) = pair.split_once('=')?;
+            Some(Event {
+                frame: frame.parse().ok()?,
+                key: key.to_string(),
+                value: value.to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+    events.sort_by_key(|event| event.frame);
+    events
+}
+
+/// Runs `command` through `sh` for every event up to `frame` that didn't run
+/// yet, moving `next` past them. Commands don't hold up playback.
+pub fn fire(events: &[Event], next: &mut usize, frame: usize, command: &str) {
+    while let Some(event) = events.get(*next).filter(|event| event.frame <= frame) {
+        *next += 1;
+        let child = Command::new("sh")
+            .args(["-c", command])
+            .env("BAPPLE_FRAME", event.frame.to_string())
+            .env("BAPPLE_KEY", &event.key)
+            .env("BAPPLE_VALUE", &event.value)
+            // Whatever the command prints would end up over the frames
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn();
+
+        match child {
+            Ok(mut child) => {
+                spawn(move || child.wait());
+            }
+            Err(error) => eprintln!("WARN: --on-event couldn't run: {error}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fires_once_its_frame_is_up() {
+        let events = parse_events("3 scene=end\n1 scene=start\n1 light=on\nx bad\n");
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2].key, "scene");
+
+        let mut next = 0;
+        for (frame, fired) in [(1, 2), (2, 2), (5, 3), (6, 3)] {
+            fire(&events, &mut next, frame, "true");
+            assert_eq!(next, fired, "frame {frame}");
+        }
+    }
+}
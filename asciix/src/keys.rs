@@ -0,0 +1,96 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    process::{Command, Output},
+    sync::mpsc::{channel, Receiver},
+    thread::spawn,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    /// `q`, Esc or Ctrl-C, which raw mode turns into a plain byte instead of
+    /// a signal
+    Quit,
+    /// `a`, switches audio tracks
+    Audio,
+    Other,
+}
+
+/// Blocks until a key is pressed on the controlling terminal. Arrows also
+/// come as vi keys (`hjkl`), and space works as page down.
+pub fn read_key() -> io::Result<Key> {
+    let saved = stty(&["-g"])?;
+    let saved = String::from_utf8_lossy(&saved.stdout);
+    stty(&["raw", "-echo", "min", "1"])?;
+
+    // Arrow keys and friends arrive as a whole escape sequence in one read
+    let mut buffer = [0; 16];
+    let read = File::open("/dev/tty").and_then(|mut tty| tty.read(&mut buffer));
+
+    stty(&[saved.trim()])?;
+    Ok(parse(&buffer[..read?]))
+}
+
+/// Reads keys in the background while frames play, for as long as the
+/// returned guard is around. Ctrl-C comes through as [`Key::Quit`] in the
+/// meantime, so that the terminal gets put back the way it was.
+pub fn listen() -> io::Result<(Receiver<Key>, Listening)> {
+    let saved = stty(&["-g"])?;
+    let saved = String::from_utf8_lossy(&saved.stdout).trim().to_string();
+    // Unlike raw mode, this leaves newlines alone, so frames still draw right
+    stty(&["-icanon", "-echo", "-isig", "min", "1"])?;
+
+    let mut tty = File::open("/dev/tty")?;
+    let (sender, keys) = channel();
+    spawn(move || {
+        let mut buffer = [0; 16];
+        while let Ok(read @ 1..) = tty.read(&mut buffer) {
+            if sender.send(parse(&buffer[..read])).is_err() {
+                break;
+            }
+        }
+    });
+    Ok((keys, Listening { saved }))
+}
+
+/// Puts the terminal back once keys stop being read
+pub struct Listening {
+    saved: String,
+}
+
+impl Drop for Listening {
+    fn drop(&mut self) {
+        stty(&[&self.saved]).ok();
+    }
+}
+
+fn parse(key: &[u8]) -> Key {
+    match key {
+        b"\x1b[A" | b"k" => Key::Up,
+        b"\x1b[B" | b"j" => Key::Down,
+        b"\x1b[D" | b"h" => Key::Left,
+        b"\x1b[C" | b"l" => Key::Right,
+        b"\x1b[5~" => Key::PageUp,
+        b"\x1b[6~" | b" " => Key::PageDown,
+        b"\x1b[H" | b"\x1b[1~" | b"g" => Key::Home,
+        b"\x1b[F" | b"\x1b[4~" | b"G" => Key::End,
+        b"q" | b"Q" | b"\x1b" | b"\x03" => Key::Quit,
+        b"a" | b"A" => Key::Audio,
+        _ => Key::Other,
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<Output> {
+    Command::new("stty")
+        .args(args)
+        .stdin(File::open("/dev/tty")?)
+        .output()
+}
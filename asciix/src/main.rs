@@ -2,103 +2,382 @@
 
 use std::{
     error::Error,
-    fs::{write, File},
-    io::{self, stdout, Write},
+    fs::File,
+    io::{self, stdout, IsTerminal, Write},
     path::PathBuf,
-    process::Command as Shell,
+    sync::mpsc::Receiver,
     thread::{sleep, spawn},
     time::{Duration, Instant},
 };
 
+use asciicore::ansi::AsciiFrame;
+use asciicore::plugin::Plugin;
+use asciicore::termcaps::{probe, TermCaps};
+use audio::Audio;
 use bidirectional_channel::BiChannel;
-use clap::{value_parser, Arg, Command};
-use reader::{manage_buffer, next_frame};
-use tempfile::TempDir;
+use calibrate::calibrate;
+use clap::{value_parser, Arg, ArgMatches, Command};
+use events::{fire, Event};
+use keys::{listen, read_key, Key};
+use metadata::Metadata;
+use reader::{manage_buffer, next_frame, part_paths, read_events, read_metadata, read_track};
+use timeline::Timeline;
 
+mod archive;
+mod audio;
 mod bidirectional_channel;
+mod calibrate;
+#[cfg(feature = "legacy-console")]
+mod console;
+mod decoder;
+mod events;
+mod keys;
+mod metadata;
 mod reader;
+mod repair;
+mod timeline;
 
 type BoxResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
 
 fn main() -> BoxResult<()> {
     let matches = cli().get_matches();
+    if let Some(("calibrate", matches)) = matches.subcommand() {
+        return calibrate(matches);
+    }
 
     let frames_file = matches.get_one::<PathBuf>("file").unwrap();
-    let framerate = *matches.get_one::<u64>("framerate").unwrap();
-    let loop_stream = matches.contains_id("loop");
+    let metadata = read_metadata(File::open(frames_file)?)?;
+
+    #[allow(clippy::cast_precision_loss)]
+    let framerate = matches
+        .get_one::<u64>("framerate")
+        .map(|&rate| rate as f64)
+        .or(metadata.framerate)
+        .filter(|&rate| rate > 0.0)
+        .unwrap_or(30.0);
+    let loop_stream = toggle(&matches, "loop", "no-loop", metadata.loop_stream).unwrap_or(false);
+    let drop_frames = toggle(&matches, "drop", "no-drop", metadata.drop_frames).unwrap_or(true);
+
+    let readahead = *matches.get_one::<usize>("readahead").unwrap();
 
+    // Archives without markers are treated as one slide per frame
+    let slides = matches
+        .contains_id("slides")
+        .then(|| metadata.slides.clone().unwrap_or_default());
+    // Markers follow the archive's own framerate, and slides stop the clock
+    let sync = metadata
+        .sync
+        .as_deref()
+        .filter(|_| !matches.contains_id("framerate") && slides.is_none());
+
+    let events = if matches.contains_id("on-event") {
+        read_events(File::open(frames_file)?)?
+    } else {
+        Vec::new()
+    };
+
+    if metadata.keyframes.is_some() && matches.contains_id("plugin") {
+        return Err(
+            "plugins need whole frames, which archives compiled with --temporal don't keep".into(),
+        );
+    }
+    let plugin = matches
+        .get_one::<PathBuf>("plugin")
+        .map(|path| Plugin::load(path))
+        .transpose()
+        .map_err(|error| error.to_string())?;
+
+    let languages = metadata.audio_tracks.clone().unwrap_or_default();
+    let track = matches
+        .get_one::<String>("audio-track")
+        .map(|track| pick_track(track, &languages))
+        .transpose()?
+        .unwrap_or(0);
+    let extra_tracks = (2..=languages.len())
+        .map(|number| read_track(File::open(frames_file)?, number))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let caps = with_legacy_console(probe(), &matches);
+    if caps.legacy_console && metadata.keyframes.is_some() {
+        return Err(
+            "the legacy console needs whole frames, which archives compiled with --temporal don't keep"
+                .into(),
+        );
+    }
+    warn_about_terminal(&metadata, caps);
+
+    // Started after probing, so that it doesn't take the terminal's answers.
+    // Slides read keys on their own, and one track has nothing to switch to.
+    let listener = (languages.len() > 1 && slides.is_none() && io::stdin().is_terminal())
+        .then(listen)
+        .and_then(Result::ok);
+
+    let playback = Playback {
+        rate: framerate,
+        drop_frames,
+        readahead,
+        max_rows: metadata.min_size.map(|(_, height)| height as usize),
+        caps,
+        slides: slides.as_deref(),
+        sync,
+        on_event: matches
+            .get_one::<String>("on-event")
+            .map(|command| (command.as_str(), events.as_slice())),
+        plugin: plugin.as_ref(),
+        extra_tracks: &extra_tracks,
+        track,
+        keys: listener.as_ref().map(|(keys, _)| keys),
+        // Archives that only keep changes already draw just those
+        diff: matches.contains_id("diff") && metadata.keyframes.is_none(),
+        keyframes: metadata.keyframes,
+    };
+    let parts = match metadata.part {
+        Some(part) => part_paths(frames_file, part)?,
+        None => vec![frames_file.clone()],
+    };
     loop {
         // When `do {} while bool`?
-        play(frames_file.clone(), framerate)?;
-        if !loop_stream {
+        let finished = play(&parts, &playback)?;
+        if !finished || !loop_stream {
             break;
         }
     }
     Ok(())
 }
 
-fn play(tar_file: PathBuf, rate: u64) -> io::Result<()> {
+/// Index of the track `--audio-track` asks for, either by its number or by
+/// its language
+fn pick_track(track: &str, languages: &[String]) -> Result<usize, String> {
+    let index = match track.parse::<usize>() {
+        Ok(number) => number
+            .checked_sub(1)
+            .filter(|&index| index < languages.len().max(1)),
+        Err(_) => languages.iter().position(|language| language == track),
+    };
+    index.ok_or_else(|| {
+        let tracks = (1..)
+            .zip(languages)
+            .map(|(number, language)| format!("{number} ({language})"))
+            .collect::<Vec<_>>();
+        if tracks.is_empty() {
+            format!("No audio track {track}, this archive only has track 1")
+        } else {
+            format!("No audio track {track}, try {}", tracks.join(", "))
+        }
+    })
+}
+
+/// `--flag` and `--no-flag` take priority over what the archive asks for
+fn toggle(matches: &ArgMatches, on: &str, off: &str, default: Option<bool>) -> Option<bool> {
+    if matches.contains_id(on) {
+        Some(true)
+    } else if matches.contains_id(off) {
+        Some(false)
+    } else {
+        default
+    }
+}
+
+fn warn_about_terminal(metadata: &Metadata, caps: TermCaps) {
+    if let (Some((min_width, min_height)), Some((width, height))) = (metadata.min_size, caps.size) {
+        if width < min_width || height < min_height {
+            eprintln!(
+                "WARN: This archive needs a {min_width}x{min_height} terminal, \
+                but yours is {width}x{height}. Frames will probably wrap."
+            );
+            sleep(Duration::from_secs(2));
+        }
+    }
+
+    // The legacy console brings every color down to its own 16 anyway
+    if metadata.color_depth.as_deref() == Some("truecolor")
+        && !caps.truecolor
+        && !caps.legacy_console
+    {
+        eprintln!("WARN: This archive uses 24-bit colors, which your terminal may not support.");
+        sleep(Duration::from_secs(2));
+    }
+}
+
+/// How to play an archive, worked out from the flags and its metadata
+struct Playback<'a> {
+    rate: f64,
+    drop_frames: bool,
+    readahead: usize,
+    /// Rows the archive's frames shouldn't go past
+    max_rows: Option<usize>,
+    caps: TermCaps,
+    slides: Option<&'a [usize]>,
+    sync: Option<&'a [(u64, usize)]>,
+    /// `--on-event`, along with the event track it runs for
+    on_event: Option<(&'a str, &'a [Event])>,
+    plugin: Option<&'a Plugin>,
+    /// Audio tracks after the first, which comes with the frames
+    extra_tracks: &'a [Vec<u8>],
+    /// Track to start on, counting from 0
+    track: usize,
+    /// Keys pressed while frames play
+    keys: Option<&'a Receiver<Key>>,
+    /// Only redraws the cells that changed
+    diff: bool,
+    /// How often frames are whole, when the rest only hold changes
+    keyframes: Option<usize>,
+}
+
+/// Returns `false` when the viewer quit before the end
+fn play(parts: &[PathBuf], playback: &Playback) -> io::Result<bool> {
+    let Playback {
+        rate,
+        drop_frames,
+        readahead,
+        max_rows,
+        caps,
+        slides,
+        sync,
+        on_event,
+        plugin,
+        extra_tracks,
+        track,
+        keys,
+        diff,
+        keyframes,
+    } = *playback;
+    // Started over on every loop, so that plugins see frame 0 again
+    let mut plugin = plugin
+        .map(Plugin::instantiate)
+        .transpose()
+        .map_err(|error| io::Error::other(error.to_string()))?;
     let (signal_sender, signal_recv) = BiChannel::<bool, Vec<u8>>::new();
 
-    spawn(move || manage_buffer(&signal_recv, File::open(tar_file)?, Vec::new()));
+    let parts = parts.iter().map(File::open).collect::<io::Result<_>>()?;
+    let buffer = spawn(move || manage_buffer(&signal_recv, parts, readahead, max_rows));
 
-    if let Some(audio_file) = next_frame(&signal_sender) {
-        spawn(|| audio(audio_file));
+    // Archives without audio still send an empty entry in its place
+    let mut audio = next_frame(&signal_sender)
+        .filter(|audio_file| !audio_file.is_empty())
+        .map(|audio_file| Audio::new(&[&[audio_file], extra_tracks].concat()))
+        .transpose()?;
+    if let Some(audio) = &mut audio {
+        audio.play(track, Duration::ZERO);
     }
+    let audio_start = audio.as_ref().map(|_| Instant::now());
+    // Markers are only worth following with audio to follow
+    let timeline = Timeline::new(rate, sync.filter(|_| audio_start.is_some()).unwrap_or(&[]));
+    let mut start = audio_start.unwrap_or_else(Instant::now);
+
+    let is_slide =
+        |index| slides.is_some_and(|slides| slides.is_empty() || slides.contains(&index));
 
-    let delay = 1000 / rate;
+    // What's on screen, for --diff to draw over
+    let mut shown = diff.then(AsciiFrame::default);
     let mut lock = stdout().lock();
-    let mut ms_behind = 0;
-    loop {
-        let time = Instant::now();
-        if let Some(frame) = next_frame(&signal_sender) {
-            if ms_behind >= delay {
-                ms_behind -= delay;
-                continue;
+    // The archive's own frame number, which events, slides, sync markers and
+    // keyframes all count by
+    let mut index = 0;
+    let mut next_event = 0;
+    while let Some(mut frame) = next_frame(&signal_sender) {
+        index += 1;
+
+        // A long stall can leave the video whole frames behind, so jump
+        // straight to the one that should be up, without skipping slides.
+        // Frames that only hold changes need the ones before them, so those
+        // archives can only jump to whole frames.
+        if drop_frames {
+            let mut target = timeline.frame_at(start.elapsed());
+            if let Some(every) = keyframes {
+                target = target.saturating_sub(1) / every * every + 1;
+            }
+            while index < target && !is_slide(index) {
+                let Some(skipped) = next_frame(&signal_sender) else {
+                    return Ok(true);
+                };
+                frame = skipped;
+                index += 1;
             }
-            lock.write_all(b"\r\x1b[2J\r\x1b[H")?;
-            lock.write_all(&frame)?;
+        }
+        // Frames that get skipped still hand out their events
+        if let Some((command, events)) = on_event {
+            fire(events, &mut next_event, index, command);
+        }
 
-            #[allow(clippy::cast_possible_truncation)]
-            let delay_sub = remaining_sub(delay, time.elapsed().as_millis() as u64);
-            ms_behind += delay_sub.1;
+        if let Some(plugin) = &mut plugin {
+            let mut text = String::from_utf8_lossy(&frame).into_owned();
+            plugin
+                .apply(&mut text, index - 1)
+                .map_err(|error| io::Error::other(format!("plugin failed: {error}")))?;
+            frame = text.into_bytes();
+        }
 
-            sleep(Duration::from_millis(delay_sub.0));
-        } else {
-            break;
+        let due = start + timeline.time_of(index);
+        match due.checked_duration_since(Instant::now()) {
+            Some(early) => sleep(early),
+            // Without dropping, the clock waits for frames that run late
+            None if !drop_frames => start += due.elapsed(),
+            None => (),
         }
-    }
 
-    Ok(())
-}
+        let changes = keyframes.is_some_and(|every| !(index - 1).is_multiple_of(every));
+        draw(&mut lock, &frame, shown.as_mut(), changes, caps)?;
 
-#[inline]
-fn remaining_sub(a: u64, b: u64) -> (u64, u64) {
-    if a >= b {
-        (a - b, 0)
-    } else {
-        (0, max_sub(a, b))
+        for key in keys.into_iter().flat_map(Receiver::try_iter) {
+            match (key, &mut audio) {
+                (Key::Quit, _) => return Ok(false),
+                (Key::Audio, Some(audio)) => audio.next(start.elapsed()),
+                _ => (),
+            }
+        }
+
+        if is_slide(index) {
+            lock.flush()?;
+            let paused = Instant::now();
+            if read_key()? == Key::Quit {
+                return Ok(false);
+            }
+            // Slides stop the clock
+            start += paused.elapsed();
+        }
     }
-}
 
-#[inline]
-fn max_sub(a: u64, b: u64) -> u64 {
-    a.max(b) - a.min(b)
+    // Told after the last frame, so that the warnings don't get drawn over
+    if let Ok(Ok(repaired)) = buffer.join() {
+        for (number, damage) in repaired {
+            eprintln!("WARN: Repaired frame {number}: {damage}");
+        }
+    }
+    Ok(true)
 }
 
-fn audio(mp3_buf: Vec<u8>) {
-    let Ok(tmp_dir) = TempDir::new() else {
-        return;
-    };
-    let mut file_path = tmp_dir.path().to_path_buf();
-    file_path.set_file_name("audio");
-    file_path.set_extension("mp3");
-
-    if write(&file_path, mp3_buf).is_err() {
-        return;
+/// Puts a frame on screen, drawing over `shown` when it's kept track of.
+/// Frames that only hold `changes` go straight over the one before them.
+fn draw(
+    out: &mut impl Write,
+    frame: &[u8],
+    shown: Option<&mut AsciiFrame>,
+    changes: bool,
+    caps: TermCaps,
+) -> io::Result<()> {
+    #[cfg(feature = "legacy-console")]
+    if caps.legacy_console {
+        return console::draw(out, frame);
     }
-
-    Shell::new("mpv").args([file_path]).output().ok();
+    // Synchronized updates keep the terminal from showing half drawn frames
+    if caps.synchronized_output {
+        out.write_all(b"\x1b[?2026h")?;
+    }
+    if let Some(shown) = shown {
+        let current = AsciiFrame::parse(&String::from_utf8_lossy(frame));
+        out.write_all(current.diff(shown).as_bytes())?;
+        *shown = current;
+    } else if changes {
+        out.write_all(frame)?;
+    } else {
+        out.write_all(b"\r\x1b[2J\r\x1b[H")?;
+        out.write_all(frame)?;
+    }
+    if caps.synchronized_output {
+        out.write_all(b"\x1b[?2026l")?;
+    }
+    Ok(())
 }
 
 fn cli() -> Command<'static> {
@@ -106,6 +385,19 @@ fn cli() -> Command<'static> {
         .about("An asciinema player")
         .version("0.1.0")
         .author("S0raWasTaken")
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            Command::new("calibrate")
+                .about("measures how fast this terminal draws, and recommends asciic settings to match")
+                .arg(
+                    Arg::new("output")
+                        .index(1)
+                        .takes_value(true)
+                        .help("saves the recommendations here, for asciic --from-calibration")
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
         .args([
             Arg::new("file")
                 .index(1)
@@ -115,10 +407,78 @@ fn cli() -> Command<'static> {
                 .value_parser(value_parser!(PathBuf)),
             Arg::new("framerate")
                 .index(2)
-                .default_value("30")
                 .takes_value(true)
-                .help("framerate to play the ascii. Default: 30")
+                .help("framerate to play the ascii. Default: the archive's own, or 30")
                 .value_parser(value_parser!(u64)),
             Arg::new("loop").long("loop").help("loops the stream"),
+            Arg::new("no-loop")
+                .long("no-loop")
+                .conflicts_with("loop")
+                .help("doesn't loop the stream, even if the archive asks to"),
+            Arg::new("drop")
+                .long("drop")
+                .help("skips frames when falling behind. This is the default"),
+            Arg::new("no-drop")
+                .long("no-drop")
+                .conflicts_with("drop")
+                .help("never skips frames, even when falling behind"),
+            Arg::new("readahead")
+                .long("readahead")
+                .takes_value(true)
+                .default_value("8")
+                .help("how many frames get decompressed ahead of time, in parallel")
+                .value_parser(value_parser!(usize)),
+            Arg::new("slides")
+                .long("slides")
+                .help("pauses on every slide until a key is pressed. q or Esc quits"),
+            Arg::new("on-event")
+                .long("on-event")
+                .takes_value(true)
+                .value_name("CMD")
+                .help("runs CMD through sh for each event of the archive, as its frame comes up"),
+            Arg::new("audio-track")
+                .long("audio-track")
+                .takes_value(true)
+                .value_name("TRACK")
+                .help("plays this audio track, by number or language. a switches tracks while playing"),
+            Arg::new("plugin")
+                .long("plugin")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("runs every frame through a WASM plugin before drawing it")
+                .value_parser(value_parser!(PathBuf)),
+            Arg::new("diff")
+                .long("diff")
+                .help("only redraws the cells that change between frames, which stops the flicker on slow terminals"),
         ])
+        .args(legacy_console_args())
+}
+
+#[cfg(feature = "legacy-console")]
+#[inline]
+fn legacy_console_args() -> [Arg<'static>; 1] {
+    [Arg::new("legacy-console")
+        .long("legacy-console")
+        .help("draws with the 16 colors of old Windows consoles, through the console API there. Picked on its own when escapes don't work")]
+}
+
+#[cfg(not(feature = "legacy-console"))]
+#[inline]
+fn legacy_console_args() -> [Arg<'static>; 0] {
+    []
+}
+
+#[cfg(feature = "legacy-console")]
+#[inline]
+fn with_legacy_console(caps: TermCaps, matches: &ArgMatches) -> TermCaps {
+    TermCaps {
+        legacy_console: console::needs_legacy() || matches.contains_id("legacy-console"),
+        ..caps
+    }
+}
+
+#[cfg(not(feature = "legacy-console"))]
+#[inline]
+fn with_legacy_console(caps: TermCaps, _: &ArgMatches) -> TermCaps {
+    caps
 }
@@ -0,0 +1,67 @@
+/// Playback hints written by asciic into the `metadata` entry of an archive
+#[derive(Debug, Default, Clone)]
+pub struct Metadata {
+    pub framerate: Option<f64>,
+    pub color_depth: Option<String>,
+    pub loop_stream: Option<bool>,
+    pub min_size: Option<(u32, u32)>,
+    pub drop_frames: Option<bool>,
+    pub slides: Option<Vec<usize>>,
+    pub sync: Option<Vec<(u64, usize)>>,
+    /// Language of each audio track
+    pub audio_tracks: Option<Vec<String>>,
+    /// Which part of a split archive this is, and out of how many
+    pub part: Option<(usize, usize)>,
+    /// How often frames are stored whole, in archives where the rest only
+    /// hold what changed since the frame before
+    pub keyframes: Option<usize>,
+}
+
+impl Metadata {
+    pub fn parse(data: &str) -> Self {
+        let mut metadata = Self::default();
+
+        for (key, value) in data.lines().filter_map(|line| line.split_once('=')) {
+            let value = value.trim();
+            match key.trim() {
+                "framerate" => metadata.framerate = value.parse().ok(),
+                "color_depth" => metadata.color_depth = Some(value.to_string()),
+                "loop" => metadata.loop_stream = value.parse().ok(),
+                "min_size" => {
+                    metadata.min_size = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+                }
+                "drop_frames" => metadata.drop_frames = value.parse().ok(),
+                "slides" => {
+                    metadata.slides = value
+                        .split(',')
+                        .map(|frame| frame.trim().parse().ok())
+                        .collect();
+                }
+                "sync" => {
+                    metadata.sync = value
+                        .split(',')
+                        .map(|marker| {
+                            let (ms, frame) = marker.split_once(':')?;
+                            Some((ms.trim().parse().ok()?, frame.trim().parse().ok()?))
+                        })
+                        .collect();
+                }
+                "audio_tracks" => {
+                    metadata.audio_tracks =
+                        Some(value.split(',').map(|tag| tag.trim().to_string()).collect());
+                }
+                "part" => {
+                    metadata.part = value
+                        .split_once('/')
+                        .and_then(|(part, of)| Some((part.parse().ok()?, of.parse().ok()?)));
+                }
+                "keyframes" => metadata.keyframes = value.parse().ok().filter(|&every| every > 0),
+                _ => (),
+            }
+        }
+
+        metadata
+    }
+}
@@ -1,76 +1,157 @@
-use std::{ffi::OsString, fs::File, io::Read, process::exit};
-
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{self, ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    archive::{Bapple, IntoFrames},
+    bidirectional_channel::BiChannel,
+    decoder::Decoder,
+    events::{parse_events, Event},
+    metadata::Metadata,
+    repair::{repair, Damage},
+    BoxResult,
+};
+use asciicore::container::{is_v3, read_entry as read_entry_at, read_index};
 use tar::{Archive, Entry};
-use zstd::decode_all;
 
-use crate::{bidirectional_channel::BiChannel, BoxResult};
+/// Feeds frames to the player as it asks for them, patching up damaged
+/// ones on the way. Parts of a split archive play one after the other, with
+/// the audio of the first. The first request gets the audio, or an empty
+/// entry without any, so that every request after it is for the next frame
+/// of the archive. Returns what got repaired, by frame number.
+pub fn manage_buffer(
+    signal_recv: &BiChannel<Vec<u8>, bool>,
+    parts: Vec<File>,
+    readahead: usize,
+    max_rows: Option<usize>,
+) -> BoxResult<Vec<(usize, Damage)>> {
+    let parts = parts
+        .into_iter()
+        .map(Bapple::read)
+        .collect::<io::Result<Vec<_>>>()?;
+
+    signal_recv.recv()?;
+    signal_recv.send(parts[0].audio().map(<[u8]>::to_vec).unwrap_or_default())?;
+
+    let frames = parts
+        .into_iter()
+        .map(Bapple::into_frames)
+        .reduce(IntoFrames::chain)
+        .unwrap();
+    let mut repaired = Vec::new();
+    // Held back by one, so that unreadable frames can show the one before
+    let mut frame: Option<Vec<u8>> = None;
+    // Now wait for `next_frame` calls
+    for (number, content) in (1..).zip(Decoder::new(frames, readahead)) {
+        let content = if let Ok(content) = content {
+            let (content, damage) = repair(content, max_rows);
+            repaired.extend(damage.into_iter().map(|damage| (number, damage)));
+            content
+        } else {
+            repaired.push((number, Damage::Unreadable));
+            frame.clone().unwrap_or_default()
+        };
 
-macro_rules! closure_error {
-    ($x:expr) => {
-        match $x {
-            Ok(res) => res,
-            Err(e) => {
-                eprintln!("{e:#?}");
-                exit(7);
+        if let Some(previous) = frame.replace(content) {
+            if signal_recv.recv()? {
+                signal_recv.send(previous)?;
             }
         }
-    };
-}
+    }
 
-pub fn manage_buffer(
-    signal_recv: &BiChannel<Vec<u8>, bool>,
-    tar_file: File,
-    mut frame: Vec<u8>,
-) -> BoxResult<()> {
-    // Spawn a new thread to receive ticks from the receiver and update the index
-    let mut archive = Archive::new(tar_file);
-    let mut files = archive
-        .entries()?
-        .map(|e| closure_error!(e))
-        .map(|mut e| {
-            let file_stem = get_file_stem(&e).unwrap();
+    // Display last frame
+    if let Some(frame) = frame {
+        if signal_recv.recv()? {
+            signal_recv.send(frame)?;
+        }
+    }
 
-            let mut content = Vec::new();
-            closure_error!(e.read_to_end(&mut content));
+    Ok(repaired)
+}
 
-            if file_stem == *"audio" {
-                return (0, content);
+/// Every part of a split archive from `path` on, which is part `number` of
+/// `count`. Parts are found by the names `asciic split` gave them.
+pub fn part_paths(path: &Path, (number, count): (usize, usize)) -> io::Result<Vec<PathBuf>> {
+    let base = path
+        .to_str()
+        .and_then(|path| path.strip_suffix(&format!(".part{number}.bapple")))
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "{} was renamed, so the parts after it can't be found",
+                    path.display()
+                ),
+            )
+        })?;
+
+    (number..=count)
+        .map(|number| {
+            let part = PathBuf::from(format!("{base}.part{number}.bapple"));
+            if part.exists() {
+                Ok(part)
+            } else {
+                Err(io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("{} is missing", part.display()),
+                ))
             }
-
-            let file_number = closure_error!(file_stem.to_str().unwrap().parse::<usize>());
-
-            (file_number, content)
         })
-        .collect::<Vec<_>>();
-
-    drop(archive);
+        .collect()
+}
 
-    files.sort_by_key(|e| e.0);
+pub fn read_metadata(tar_file: File) -> io::Result<Metadata> {
+    Ok(read_entry(tar_file, "metadata")?
+        .map(|content| Metadata::parse(&String::from_utf8_lossy(&content)))
+        .unwrap_or_default())
+}
 
-    // Now wait for `next_frame` calls
-    for (x, entry) in files {
-        if x == 0 {
-            signal_recv.recv()?; // First entry is audio
-            signal_recv.send(entry)?;
-            continue;
-        }
+pub fn read_events(tar_file: File) -> io::Result<Vec<Event>> {
+    Ok(read_entry(tar_file, "events")?
+        .map(|content| parse_events(&String::from_utf8_lossy(&content)))
+        .unwrap_or_default())
+}
 
-        let content = decode_all(entry.as_slice())?;
+/// Audio track `number`, counting from 1. The first one comes along with
+/// the frames instead.
+pub fn read_track(tar_file: File, number: usize) -> io::Result<Vec<u8>> {
+    read_entry(tar_file, &format!("audio{number}"))?.ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::NotFound,
+            format!("the archive lists audio track {number}, but doesn't have it"),
+        )
+    })
+}
 
-        if signal_recv.recv()? {
-            signal_recv.send(frame.clone())?;
-            frame = content;
-        } else {
-            frame = content;
-        }
+/// Contents of the entry called `name`, if the archive has one
+fn read_entry(mut tar_file: File, name: &str) -> io::Result<Option<Vec<u8>>> {
+    if is_v3(&mut tar_file)? {
+        // Straight to the entry, without going through the frames
+        let index = read_index(&mut tar_file)?;
+        let Some((_, range)) = index
+            .into_iter()
+            .find(|(entry, _)| Path::new(entry).file_stem() == Some(name.as_ref()))
+        else {
+            return Ok(None);
+        };
+        return read_entry_at(&mut tar_file, &range).map(Some);
     }
 
-    // Display last frame
-    if signal_recv.recv()? {
-        signal_recv.send(frame)?;
+    let mut archive = Archive::new(tar_file);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if get_file_stem(&entry).is_some_and(|stem| stem == name) {
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            return Ok(Some(content));
+        }
     }
 
-    Ok(())
+    Ok(None)
 }
 
 #[inline]
@@ -82,3 +163,51 @@ pub fn next_frame(bi_channel: &BiChannel<bool, Vec<u8>>) -> Option<Vec<u8>> {
 fn get_file_stem(e: &'_ Entry<File>) -> Option<OsString> {
     Some(e.header().path().ok()?.file_stem()?.to_os_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asciicore::frame::compress;
+    use std::{io::Seek, thread::spawn};
+    use tar::{Builder, Header};
+
+    fn archive(audio: Option<&[u8]>, frames: &[&str]) -> File {
+        let mut builder = Builder::new(tempfile::tempfile().unwrap());
+        let mut add = |name: &str, data: &[u8]| {
+            let mut header = Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, data).unwrap();
+        };
+        if let Some(audio) = audio {
+            add("audio.mp3", audio);
+        }
+        add("metadata", b"framerate=30\n");
+        for (number, frame) in (1..).zip(frames) {
+            add(&number.to_string(), &compress(frame.as_bytes()).unwrap());
+        }
+        let mut file = builder.into_inner().unwrap();
+        file.rewind().unwrap();
+        file
+    }
+
+    /// Everything the player gets, starting with the audio
+    fn play(file: File) -> Vec<Vec<u8>> {
+        let (player, reader) = BiChannel::<bool, Vec<u8>>::new();
+        let buffer = spawn(move || manage_buffer(&reader, vec![file], 2, None).unwrap());
+        let entries = std::iter::from_fn(|| next_frame(&player)).collect();
+        assert!(buffer.join().unwrap().is_empty());
+        entries
+    }
+
+    #[test]
+    fn frames_follow_the_audio() {
+        let frames = ["one", "two", "three"];
+        for audio in [Some(b"ID3".as_slice()), None] {
+            let received = play(archive(audio, &frames));
+            assert_eq!(received[0], audio.unwrap_or_default());
+            assert_eq!(received[1..], frames.map(str::as_bytes));
+        }
+    }
+}
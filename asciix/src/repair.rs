@@ -0,0 +1,135 @@
+use std::fmt;
+
+/// Something wrong with a frame that got patched up before playing it
+#[derive(Debug, Clone, Copy)]
+pub enum Damage {
+    /// It didn't decompress, so the frame before it plays again
+    Unreadable,
+    /// Bytes that aren't UTF-8, swapped for replacement characters
+    InvalidUtf8,
+    /// An escape sequence cut off at the end, dropped along with it
+    CutEscape,
+    /// More rows than the archive's frames have, which would scroll the
+    /// terminal. Holds how many there were.
+    Rows(usize),
+}
+
+impl fmt::Display for Damage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable => write!(f, "unreadable, repeated the frame before"),
+            Self::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            Self::CutEscape => write!(f, "cut off escape sequence"),
+            Self::Rows(rows) => write!(f, "{rows} rows, more than the archive allows"),
+        }
+    }
+}
+
+/// Fixes whatever in a decompressed frame would throw the terminal off for
+/// the frames after it. Frames get cut to `max_rows`, when it's known.
+pub fn repair(frame: Vec<u8>, max_rows: Option<usize>) -> (Vec<u8>, Vec<Damage>) {
+    let mut damage = Vec::new();
+
+    let mut frame = match String::from_utf8(frame) {
+        Ok(frame) => frame.into_bytes(),
+        Err(error) => {
+            damage.push(Damage::InvalidUtf8);
+            String::from_utf8_lossy(error.as_bytes())
+                .into_owned()
+                .into_bytes()
+        }
+    };
+
+    if let Some(start) = cut_escape(&frame) {
+        damage.push(Damage::CutEscape);
+        frame.truncate(start);
+        frame.extend_from_slice(b"\x1b[0m");
+    }
+
+    let newlines = frame
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &byte)| (byte == b'\n').then_some(index))
+        .collect::<Vec<_>>();
+    if let Some(max_rows) = max_rows.filter(|&max_rows| newlines.len() > max_rows) {
+        damage.push(Damage::Rows(newlines.len()));
+        let end = max_rows.checked_sub(1).map_or(0, |last| newlines[last] + 1);
+        frame.truncate(end);
+        frame.extend_from_slice(b"\x1b[0m");
+    }
+
+    (frame, damage)
+}
+
+/// Where the escape sequence at the end of `frame` starts, if it never got
+/// to its final byte
+fn cut_escape(frame: &[u8]) -> Option<usize> {
+    let start = frame.iter().rposition(|&byte| byte == 0x1b)?;
+    let sequence = &frame[start + 1..];
+
+    let complete = match sequence.split_first() {
+        None => false,
+        // CSI: parameters and intermediates, then a final byte
+        Some((b'[', rest)) => rest
+            .iter()
+            .find(|byte| !(0x20..=0x3f).contains(*byte))
+            .is_some_and(|byte| (0x40..=0x7e).contains(byte)),
+        Some(_) => true,
+    };
+    (!complete).then_some(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_intact_frames_alone() {
+        let frame = b"\x1b[38;2;1;2;3m@.\x1b[0m\n\x1bM\n".to_vec();
+        let (repaired, damage) = repair(frame.clone(), Some(2));
+        assert_eq!(repaired, frame);
+        assert!(damage.is_empty());
+    }
+
+    #[test]
+    fn drops_cut_off_escapes() {
+        for cut in [b"ab\x1b[38;5".as_slice(), b"ab\x1b[", b"ab\x1b"] {
+            let (repaired, damage) = repair(cut.to_vec(), None);
+            assert_eq!(repaired, b"ab\x1b[0m");
+            assert!(matches!(damage[..], [Damage::CutEscape]));
+        }
+        assert_eq!(cut_escape(b"\x1b[0mab"), None);
+        assert_eq!(cut_escape(b"a\x1b[1;2"), Some(1));
+    }
+
+    #[test]
+    fn replaces_invalid_utf8() {
+        let (repaired, damage) = repair(b"a\xffb".to_vec(), None);
+        assert_eq!(repaired, "a\u{fffd}b".as_bytes());
+        assert!(matches!(damage[..], [Damage::InvalidUtf8]));
+
+        // Both at once, with the escape cut after the replacement
+        let (repaired, damage) = repair(b"\xff\x1b[3".to_vec(), None);
+        assert_eq!(repaired, "\u{fffd}\x1b[0m".as_bytes());
+        assert!(matches!(
+            damage[..],
+            [Damage::InvalidUtf8, Damage::CutEscape]
+        ));
+    }
+
+    #[test]
+    fn cuts_extra_rows() {
+        let (repaired, damage) = repair(b"1\n2\n3\n".to_vec(), Some(2));
+        assert_eq!(repaired, b"1\n2\n\x1b[0m");
+        assert!(matches!(damage[..], [Damage::Rows(3)]));
+
+        let (repaired, damage) = repair(b"1\n2\n".to_vec(), Some(0));
+        assert_eq!(repaired, b"\x1b[0m");
+        assert!(matches!(damage[..], [Damage::Rows(2)]));
+
+        // A last row without its newline doesn't scroll anything
+        let (repaired, damage) = repair(b"1\n2".to_vec(), Some(1));
+        assert_eq!(repaired, b"1\n2");
+        assert!(damage.is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+/// When each frame of an archive is due, counting frames from 1 and time
+/// from the start of playback. Frames go at a steady rate, except that sync
+/// markers pin frames to where the audio is, so the rate only decides the
+/// frames in between.
+pub struct Timeline<'a> {
+    rate: f64,
+    markers: &'a [(u64, usize)],
+}
+
+impl<'a> Timeline<'a> {
+    /// `markers` are `(milliseconds, frame)` pairs, sorted by both
+    pub fn new(rate: f64, markers: &'a [(u64, usize)]) -> Self {
+        Self { rate, markers }
+    }
+
+    /// When `frame` should go up. Frames that the rate would push past the
+    /// next marker are due at that marker instead, so they get dropped or
+    /// drawn late rather than holding the audio up.
+    pub fn time_of(&self, frame: usize) -> Duration {
+        let (start, ms, next) = self.segment(|&(_, marker)| marker <= frame);
+        #[allow(clippy::cast_precision_loss)]
+        let offset = Duration::from_secs_f64((frame - start) as f64 / self.rate);
+        let due = Duration::from_millis(ms) + offset;
+        next.map_or(due, |(next_ms, _)| due.min(Duration::from_millis(next_ms)))
+    }
+
+    /// The frame that should be up at `time`
+    pub fn frame_at(&self, time: Duration) -> usize {
+        let (start, ms, next) = self.segment(|&(ms, _)| Duration::from_millis(ms) <= time);
+        let into = time.saturating_sub(Duration::from_millis(ms));
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let frame = start + (into.as_secs_f64() * self.rate) as usize;
+        // Frames past the next marker have to wait for it
+        next.map_or(frame, |(_, marker)| frame.min(marker - 1).max(start))
+    }
+
+    /// The first frame and time of the stretch between markers that ends
+    /// where `before` stops holding, and the marker after it
+    fn segment(
+        &self,
+        before: impl Fn(&(u64, usize)) -> bool,
+    ) -> (usize, u64, Option<(u64, usize)>) {
+        let passed = self.markers.partition_point(before);
+        let (ms, start) = passed
+            .checked_sub(1)
+            .map_or((0, 1), |last| self.markers[last]);
+        (start, ms, self.markers.get(passed).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_pin_their_frame() {
+        let markers = [(1000, 5), (3000, 10)];
+        let timeline = Timeline::new(10.0, &markers);
+        assert_eq!(timeline.time_of(1), Duration::ZERO);
+        assert_eq!(timeline.time_of(5), Duration::from_secs(1));
+        assert_eq!(timeline.time_of(6), Duration::from_millis(1100));
+        assert_eq!(timeline.frame_at(Duration::from_millis(999)), 4);
+        assert_eq!(timeline.frame_at(Duration::from_secs(1)), 5);
+        // Frames past the next marker wait for it
+        assert_eq!(timeline.frame_at(Duration::from_millis(2500)), 9);
+        assert_eq!(timeline.frame_at(Duration::from_secs(3)), 10);
+    }
+}